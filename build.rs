@@ -1,12 +1,22 @@
 // build.rs
-use std::{env, fs, path::PathBuf, process::Command};
+//
+// With the `c-binding` feature, build the `labrador24` C library of LaZer as a static library in OUT_DIR and generate
+// its bindings. The sources are taken from the directory in LABRADOR_C_DIR, or from the vendored `labrador-c`.
+// The library is compiled for the host CPU with `-march=native`, unless LABRADOR_C_PORTABLE is set or the target is
+// not the host, in which case it is compiled for x86-64-v4, the baseline with the AVX-512 instructions it needs.
+// The default build does not need a C compiler or the C sources.
 
 fn main() {
-    let manifest = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
-    let src = manifest.join("labrador-c");
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "c-binding")]
+    c_binding::build();
+}
+
+#[cfg(feature = "c-binding")]
+mod c_binding {
+    use std::{env, path::Path, path::PathBuf, process::Command};
 
-    // 1) Gather all .c files under labrador-c
-    let c_files = vec![
+    const C_FILES: [&str; 17] = [
         "greyhound.c",
         "dachshund.c",
         "pack.c",
@@ -24,53 +34,97 @@ fn main() {
         "randombytes.c",
         "cpucycles.c",
         "sparsemat.c",
-    ]
-    .into_iter()
-    .map(|f| src.join(f))
-    .collect::<Vec<_>>();
+    ];
+
+    pub fn build() {
+        let manifest = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+        println!("cargo:rerun-if-env-changed=LABRADOR_C_DIR");
+        println!("cargo:rerun-if-env-changed=LABRADOR_C_PORTABLE");
+        let src = env::var_os("LABRADOR_C_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| manifest.join("labrador-c"));
+        for file in C_FILES {
+            let path = src.join(file);
+            if !path.exists() {
+                panic!(
+                    "the c-binding feature needs the labrador24 sources, but {} does not exist; \
+                     set LABRADOR_C_DIR to the directory of the C sources of LaZer's labrador",
+                    path.display()
+                );
+            }
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+        let header = manifest.join("labrador.h");
+        println!("cargo:rerun-if-changed={}", header.display());
 
-    // 2) Invoke gcc to build a shared lib
-    let mut cmd = Command::new("gcc");
-    cmd.args(&[
-        "-std=gnu18",
-        "-Wall",
-        "-Wextra",
-        "-Wmissing-prototypes",
-        "-Wredundant-decls",
-    ]);
-    cmd.args(&[
-        "-Wshadow",
-        "-Wpointer-arith",
-        "-Wno-unused-function",
-        "-fmax-errors=1",
-        "-flto=auto",
-        "-fwrapv",
-    ]);
-    cmd.args(&[
-        "-march=native",
-        "-mtune=native",
-        "-O3",
-        "-fvisibility=hidden",
-    ]);
-    cmd.args(&["-fPIC", "-shared", "-DLOGQ=24", "-I", src.to_str().unwrap()]);
-    for file in &c_files {
-        cmd.arg(file);
+        let portable = env::var_os("LABRADOR_C_PORTABLE").is_some()
+            || env::var("TARGET").unwrap() != env::var("HOST").unwrap();
+        if portable || !compile(&src, &out, "native") {
+            if !portable {
+                println!("cargo:warning=building labrador24 with -march=native failed, retrying for x86-64-v4");
+            }
+            assert!(
+                compile(&src, &out, "x86-64-v4"),
+                "failed to build liblabrador24.a"
+            );
+        }
+        println!("cargo:rustc-link-search=native={}", out.display());
+        println!("cargo:rustc-link-lib=static=labrador24");
+
+        let bindings = bindgen::Builder::default()
+            .header(header.to_string_lossy())
+            .clang_arg(format!("-I{}", src.display()))
+            .generate()
+            .expect("Unable to generate bindings");
+        bindings
+            .write_to_file(out.join("bindings.rs"))
+            .expect("Couldn't write bindings.rs");
     }
-    cmd.arg("-o").arg(manifest.join("liblabrador24.so"));
-    let status = cmd.status().expect("failed to build liblabrador24.so");
-    assert!(status.success());
 
-    // 3) Tell Cargo to link
-    println!("cargo:rustc-link-search=native={}", src.display());
-    println!("cargo:rustc-link-lib=dylib=labrador24");
+    /// Compile the sources in `src` to `out/liblabrador24.a` for the given `-march`, and return whether it succeeded
+    fn compile(src: &Path, out: &Path, march: &str) -> bool {
+        let cc = env::var("CC").unwrap_or_else(|_| "gcc".to_string());
+        let mut objects = vec![];
+        for file in C_FILES {
+            let object = out.join(format!("{file}.o"));
+            let mut cmd = Command::new(&cc);
+            cmd.args([
+                "-std=gnu18",
+                "-Wall",
+                "-Wextra",
+                "-Wmissing-prototypes",
+                "-Wredundant-decls",
+            ]);
+            cmd.args([
+                "-Wshadow",
+                "-Wpointer-arith",
+                "-Wno-unused-function",
+                "-fmax-errors=1",
+                "-fwrapv",
+            ]);
+            cmd.arg(format!("-march={march}"));
+            if march == "native" {
+                cmd.arg("-mtune=native");
+            }
+            cmd.args(["-O3", "-fvisibility=hidden", "-fPIC", "-DLOGQ=24", "-I"]);
+            cmd.arg(src);
+            cmd.arg("-c").arg(src.join(file)).arg("-o").arg(&object);
+            match cmd.status() {
+                Ok(status) if status.success() => objects.push(object),
+                _ => return false,
+            }
+        }
 
-    // 4) Generate bindings as before...
-    let bindings = bindgen::Builder::default()
-        .header(manifest.join("labrador.h").to_string_lossy())
-        .clang_arg(format!("-I{}", src.display()))
-        .generate()
-        .expect("Unable to generate bindings");
-    bindings
-        .write_to_file(PathBuf::from(env::var("OUT_DIR").unwrap()).join("bindings.rs"))
-        .expect("Couldn't write bindings.rs");
+        let lib = out.join("liblabrador24.a");
+        let _ = std::fs::remove_file(&lib);
+        let ar = env::var("AR").unwrap_or_else(|_| "ar".to_string());
+        let status = Command::new(ar)
+            .arg("rcs")
+            .arg(&lib)
+            .args(&objects)
+            .status()
+            .expect("failed to run ar");
+        status.success()
+    }
 }
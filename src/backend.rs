@@ -0,0 +1,334 @@
+//! Backends that prove and verify principal relations, which callers can pick per call.
+//!
+//! `RustBackend` runs the recursive protocol of this crate, and supports every statement.
+//! With the `c-binding` feature, `CBackend` runs the `labrador24` C library instead, which only supports linear
+//! constraints over a ring of dimension `LABRADOR24_N` with modulus `LABRADOR24_Q`; `select_backend` falls back to
+//! Rust for all other statements.
+//! The proofs of the two backends are not interchangeable: a proof must be verified by the backend that produced it.
+
+use std::fmt::{Debug, Display, Formatter};
+
+use nimue::ProofError;
+#[cfg(feature = "c-binding")]
+use num_bigint::BigUint;
+#[cfg(feature = "c-binding")]
+use num_traits::ToPrimitive;
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use relations::principal_relation::{Index, Instance, Witness};
+
+use crate::common_reference_string::CommonReferenceString;
+use crate::iopattern::principal_relation_iopattern;
+#[cfg(feature = "c-binding")]
+use crate::prover::c_prover::{self, CBackendError, LABRADOR24_N, LABRADOR24_Q};
+use crate::prover::prove_principal_relation;
+use crate::verifier::verify_principal_relation;
+
+#[derive(Debug)]
+pub enum BackendError {
+    Proof(ProofError),
+    #[cfg(feature = "c-binding")]
+    C(CBackendError),
+}
+
+impl Display for BackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::Proof(e) => write!(f, "{e}"),
+            #[cfg(feature = "c-binding")]
+            BackendError::C(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<ProofError> for BackendError {
+    fn from(e: ProofError) -> Self {
+        BackendError::Proof(e)
+    }
+}
+
+#[cfg(feature = "c-binding")]
+impl From<CBackendError> for BackendError {
+    fn from(e: CBackendError) -> Self {
+        BackendError::C(e)
+    }
+}
+
+pub trait LabradorBackend<R: PolyRing> {
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend can prove and verify this statement
+    fn supports(&self, index: &Index<R>, instance: &Instance<R>) -> bool;
+
+    /// Prove that the witness satisfies the statement, and return the serialized proof
+    fn prove(
+        &self,
+        crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        witness: &Witness<R>,
+    ) -> Result<Vec<u8>, BackendError>;
+
+    fn verify(
+        &self,
+        crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        proof: &[u8],
+    ) -> Result<(), BackendError>;
+}
+
+/// The recursive protocol of this crate, where the proof is the Fiat-Shamir transcript
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RustBackend;
+
+impl<R: PolyRing> LabradorBackend<R> for RustBackend
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn supports(&self, _index: &Index<R>, _instance: &Instance<R>) -> bool {
+        true
+    }
+
+    fn prove(
+        &self,
+        crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        witness: &Witness<R>,
+    ) -> Result<Vec<u8>, BackendError> {
+        let io = principal_relation_iopattern(crs);
+        let mut merlin = io.to_merlin();
+        let transcript = prove_principal_relation(&mut merlin, crs, index, instance, witness)?;
+        Ok(transcript.to_vec())
+    }
+
+    fn verify(
+        &self,
+        crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        proof: &[u8],
+    ) -> Result<(), BackendError> {
+        let io = principal_relation_iopattern(crs);
+        let mut arthur = io.to_arthur(proof);
+        Ok(verify_principal_relation(
+            &mut arthur,
+            crs,
+            index,
+            instance,
+        )?)
+    }
+}
+
+/// The `labrador24` C library, which does not use the CRS and does its own Fiat-Shamir
+#[cfg(feature = "c-binding")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CBackend;
+
+#[cfg(feature = "c-binding")]
+impl<R: PolyRing> LabradorBackend<R> for CBackend
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative: ToPrimitive,
+{
+    fn name(&self) -> &'static str {
+        "c"
+    }
+
+    fn supports(&self, _index: &Index<R>, instance: &Instance<R>) -> bool {
+        R::dimension() == LABRADOR24_N as usize
+            && R::modulus() == BigUint::from(LABRADOR24_Q)
+            && instance
+                .quad_dot_prod_funcs
+                .iter()
+                .all(|constraint| constraint.A.is_none())
+            && instance.ct_quad_dot_prod_funcs.is_empty()
+    }
+
+    fn prove(
+        &self,
+        _crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        witness: &Witness<R>,
+    ) -> Result<Vec<u8>, BackendError> {
+        Ok(c_prover::prove_principal_relation(
+            index, instance, witness,
+        )?)
+    }
+
+    fn verify(
+        &self,
+        _crs: &CommonReferenceString<R>,
+        index: &Index<R>,
+        instance: &Instance<R>,
+        proof: &[u8],
+    ) -> Result<(), BackendError> {
+        Ok(c_prover::verify_principal_relation(index, instance, proof)?)
+    }
+}
+
+/// The C backend if it is compiled in and supports the statement, and the Rust backend otherwise
+#[cfg(feature = "c-binding")]
+pub fn select_backend<R: PolyRing + 'static>(
+    index: &Index<R>,
+    instance: &Instance<R>,
+) -> &'static dyn LabradorBackend<R>
+where
+    RustBackend: LabradorBackend<R>,
+    CBackend: LabradorBackend<R>,
+{
+    if CBackend.supports(index, instance) {
+        &CBackend
+    } else {
+        &RustBackend
+    }
+}
+
+/// The C backend if it is compiled in and supports the statement, and the Rust backend otherwise
+#[cfg(not(feature = "c-binding"))]
+pub fn select_backend<R: PolyRing + 'static>(
+    _index: &Index<R>,
+    _instance: &Instance<R>,
+) -> &'static dyn LabradorBackend<R>
+where
+    RustBackend: LabradorBackend<R>,
+{
+    &RustBackend
+}
+
+#[cfg(test)]
+mod tests {
+    use lattirust_arithmetic::linear_algebra::Vector;
+    use lattirust_arithmetic::ring::ntt::ntt_prime;
+    #[cfg(feature = "c-binding")]
+    use lattirust_arithmetic::ring::Pow2CyclotomicPolyRing;
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq1};
+    use num_traits::One;
+
+    use super::*;
+    use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+    use crate::test_util::ternary_vector;
+    use crate::util::to_ring;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq1<{ ntt_prime::<64>(24) }>, 64>;
+
+    /// $\langle \phi, s_1 \rangle + \langle \phi, s_2 \rangle = b$ for small s_1, s_2 and phi
+    fn linear_statement<R: PolyRing>(
+    ) -> (CommonReferenceString<R>, Index<R>, Instance<R>, Witness<R>) {
+        let n = 4;
+        let s = (0..2)
+            .map(|i| ternary_vector::<R>(n, i))
+            .collect::<Vec<_>>();
+        let phi = Vector::<R>::from_fn(n, |j, _| {
+            to_ring(
+                &(0..R::dimension())
+                    .map(|k| if k == 0 { (j + 1) as i64 } else { 0 })
+                    .collect::<Vec<_>>(),
+            )
+        });
+        let b = phi.dot(&s[0]) + phi.dot(&s[1]);
+
+        let mut builder = RelationBuilder::<R>::new(n, (2 * n * R::dimension()) as f64);
+        let (s_1, s_2) = (builder.witness("s_1"), builder.witness("s_2"));
+        builder.constrain(
+            QuadraticExpr::linear(phi.clone(), s_1) + QuadraticExpr::linear(phi, s_2),
+            b,
+        );
+        let mut assignment = builder.assignment();
+        assignment
+            .assign(s_1, s[0].clone())
+            .assign(s_2, s[1].clone());
+        let witness = assignment.witness().unwrap();
+        let crs = CommonReferenceString::<R>::new_for_size(builder.size());
+        let (index, instance) = builder.build();
+        (crs, index, instance, witness)
+    }
+
+    /// Prove and verify with the backend, and check that the proof does not verify for another right-hand side
+    fn check_backend<R: PolyRing>(backend: &dyn LabradorBackend<R>) {
+        let (crs, index, instance, witness) = linear_statement::<R>();
+        assert!(backend.supports(&index, &instance));
+        let proof = backend.prove(&crs, &index, &instance, &witness).unwrap();
+        backend.verify(&crs, &index, &instance, &proof).unwrap();
+
+        let mut other_instance = instance.clone();
+        other_instance.quad_dot_prod_funcs[0].b += R::one();
+        assert!(backend
+            .verify(&crs, &index, &other_instance, &proof)
+            .is_err());
+    }
+
+    /// The ring of the C library, which is not NTT-friendly
+    #[cfg(feature = "c-binding")]
+    type RC = Pow2CyclotomicPolyRing<Zq1<LABRADOR24_Q>, 64>;
+
+    #[test]
+    fn test_rust_backend() {
+        check_backend::<R>(&RustBackend);
+    }
+
+    #[cfg(feature = "c-binding")]
+    #[test]
+    fn test_c_backend() {
+        let (_, index, instance, _) = linear_statement::<RC>();
+        assert_eq!(select_backend(&index, &instance).name(), "c");
+        check_backend::<RC>(&CBackend);
+    }
+
+    #[cfg(feature = "c-binding")]
+    #[test]
+    fn test_backends_agree() {
+        // Both backends prove the same statement over the ring of the C library, and reject the same tampered instance
+        let (crs, index, instance, witness) = linear_statement::<RC>();
+        let mut other_instance = instance.clone();
+        other_instance.quad_dot_prod_funcs[0].b += RC::one();
+        for backend in [&RustBackend as &dyn LabradorBackend<RC>, &CBackend] {
+            let proof = backend.prove(&crs, &index, &instance, &witness).unwrap();
+            backend.verify(&crs, &index, &instance, &proof).unwrap();
+            assert!(
+                backend
+                    .verify(&crs, &index, &other_instance, &proof)
+                    .is_err(),
+                "the {} backend accepts a proof for another instance",
+                backend.name()
+            );
+        }
+    }
+
+    #[cfg(feature = "c-binding")]
+    #[test]
+    fn test_c_backend_rejects_other_modulus() {
+        // Same dimension and bit length as the ring of the C library, but a different modulus
+        let (_, index, instance, _) = linear_statement::<R>();
+        assert!(!CBackend.supports(&index, &instance));
+        assert_eq!(select_backend(&index, &instance).name(), "rust");
+    }
+
+    #[cfg(feature = "c-binding")]
+    #[test]
+    fn test_c_backend_concurrent() {
+        use rayon::prelude::*;
+
+        (0..8)
+            .into_par_iter()
+            .for_each(|_| check_backend::<RC>(&CBackend));
+    }
+}
@@ -1,4 +1,4 @@
-use nimue::{Arthur, IOPattern, Merlin, ProofResult};
+use nimue::{Arthur, ByteIOPattern, IOPattern, Merlin, ProofResult};
 
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
 use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
@@ -13,6 +13,8 @@ use relations::reduction::Reduction;
 use crate::binary_r1cs::prover::prove_reduction_binaryr1cs_labradorpr;
 use crate::binary_r1cs::util::BinaryR1CSCRS;
 use crate::binary_r1cs::verifier::verify_reduction_binaryr1cs_labradorpr;
+use crate::gf2::BitMatrix;
+use crate::sparse::STATEMENT_DIGEST_LEN;
 
 pub mod prover;
 #[cfg(test)]
@@ -41,10 +43,20 @@ where
         let k = pp.num_constraints;
         let secparam = pp.security_parameter;
         IOPattern::new("reduction_binaryr1cs_principalrelation")
+            .add_bytes(STATEMENT_DIGEST_LEN, "statement digest")
             .absorb_vector::<R>(pp.A.nrows(), "prover message 1 (t)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (alpha)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (beta)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (gamma)")
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (alpha)",
+            )
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (beta)",
+            )
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (gamma)",
+            )
             .absorb_vector_canonical::<R::BaseRing>(secparam, "prover message 2 (g)")
     }
 
@@ -1,6 +1,6 @@
 #![allow(non_snake_case)]
 
-use nimue::{Merlin, ProofResult};
+use nimue::{BytePublic, Merlin, ProofResult};
 use tracing::{event, instrument, Level};
 
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
@@ -8,16 +8,17 @@ use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChalle
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
 use lattirust_arithmetic::linear_algebra::Vector;
 use lattirust_arithmetic::nimue::merlin::SerMerlin;
-use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
-use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::ring::{PolyRing, Z2};
 use lattirust_arithmetic::traits::FromRandomBytes;
 use relations::{principal_relation, Relation};
 
 use crate::binary_r1cs::util::{reduce, BinaryR1CSCRS, BinaryR1CSTranscript};
 use crate::binary_r1cs::BinaryR1CS;
+use crate::gf2::{challenge_bit_matrix, embed_bit, lift_bits, sum_binary_products, BitVector};
 use crate::prover::prove_principal_relation;
-use crate::util::{concat, embed, lift};
+use crate::sparse::{statement_digest, SparseR1CSIndex};
+use crate::util::concat;
 
 #[instrument(
     name = "BinR1CS -> PR",
@@ -35,28 +36,63 @@ pub fn prove_reduction_binaryr1cs_labradorpr<'a, R: PolyRing>(
     principal_relation::Instance<R>,
     principal_relation::Witness<R>,
 )
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    prove_reduction_binaryr1cs_labradorpr_sparse(
+        pp,
+        merlin,
+        &SparseR1CSIndex::from(index),
+        instance,
+        witness,
+    )
+}
+
+/// Same as [`prove_reduction_binaryr1cs_labradorpr`], but for an index whose matrices are already in CSR format.
+pub fn prove_reduction_binaryr1cs_labradorpr_sparse<'a, R: PolyRing>(
+    pp: &BinaryR1CSCRS<R>,
+    merlin: &'a mut Merlin,
+    index: &SparseR1CSIndex<Z2>,
+    instance: &<BinaryR1CS as Relation>::Instance,
+    witness: &<BinaryR1CS as Relation>::Witness,
+) -> (
+    principal_relation::Index<R>,
+    principal_relation::Instance<R>,
+    principal_relation::Witness<R>,
+)
 where
     LabradorChallengeSet<R>: FromRandomBytes<R>,
     WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
 {
     let (A, B, C) = (&index.a, &index.b, &index.c);
 
-    let w = concat(vec![instance.0.as_slice(), witness.0.as_slice()].as_slice());
+    let x = BitVector::from(&instance.0);
+    let z = BitVector::concat(&[&x, &BitVector::from(&witness.0)]);
     let (k, n) = (pp.num_constraints, pp.num_variables);
     assert_eq!(k, n, "the current implementation only support k = n"); // TODO: remove this restriction by splitting a,b,c or w into multiple vectors
 
-    // TODO: add statement to merlin
+    merlin
+        .public_bytes(&statement_digest::<R, _>(
+            index,
+            &instance.0,
+            embed_bit::<R>,
+        ))
+        .unwrap();
+
+    let a = A.mul_bits(&z);
+    let b = B.mul_bits(&z);
+    let c = C.mul_bits(&z);
 
-    let a = A * &w;
-    let b = B * &w;
-    let c = C * &w;
+    // The public input is not committed to, the verifier accounts for it in the constraints on g
+    let w = BitVector::concat(&[&BitVector::zeros(x.len()), &BitVector::from(&witness.0)]);
 
     event!(Level::DEBUG, "computing A*w, B*w, C*w");
 
-    let a_R = lift::<R>(&a);
-    let b_R = lift::<R>(&b);
-    let c_R = lift::<R>(&c);
-    let w_R = lift::<R>(&w);
+    let a_R = lift_bits::<R>(&a);
+    let b_R = lift_bits::<R>(&b);
+    let c_R = lift_bits::<R>(&c);
+    let w_R = lift_bits::<R>(&w);
 
     let v = concat(&[
         a_R.as_slice(),
@@ -73,46 +109,36 @@ where
         "squeezing alpha in {{0,1}}^{}x{k}",
         pp.security_parameter
     );
-    let alpha = merlin
-        .challenge_binary_matrix(pp.security_parameter, k)
-        .unwrap();
+    let alpha = challenge_bit_matrix(merlin, pp.security_parameter, k).unwrap();
     event!(
         Level::DEBUG,
         "squeezing beta in {{0,1}}^{}x{k}",
         pp.security_parameter
     );
-    let beta = merlin
-        .challenge_binary_matrix(pp.security_parameter, k)
-        .unwrap();
+    let beta = challenge_bit_matrix(merlin, pp.security_parameter, k).unwrap();
     event!(
         Level::DEBUG,
         "squeezing gamma in {{0,1}}^{}x{k}",
         pp.security_parameter
     );
-    let gamma = merlin
-        .challenge_binary_matrix(pp.security_parameter, k)
-        .unwrap();
+    let gamma = challenge_bit_matrix(merlin, pp.security_parameter, k).unwrap();
 
     // delta_i is computed mod 2, i.e., over Z2
-    let delta = &alpha * A + &beta * B + &gamma * C;
-
-    // g_i is computed over Zq
-    let (a_BR, b_BR, c_BR) = (
-        // a.map(embed::<R::BaseRing>),
-        // b.map(embed::<R::BaseRing>),
-        // c.map(embed::<R::BaseRing>),
-        Vector::<R::BaseRing>::from(R::flattened_coeffs(&a_R)),
-        Vector::<R::BaseRing>::from(R::flattened_coeffs(&b_R)),
-        Vector::<R::BaseRing>::from(R::flattened_coeffs(&c_R)),
-    );
-    let (alpha_BR, beta_BR, gamma_BR) = (
-        alpha.map(embed::<R::BaseRing>),
-        beta.map(embed::<R::BaseRing>),
-        gamma.map(embed::<R::BaseRing>),
+    let delta = sum_binary_products(&[(&alpha, A), (&beta, B), (&gamma, C)]);
+
+    // g_i is computed over Zq; since all vectors are binary, each inner product is the popcount of a bitwise AND
+    let g = Vector::<R::BaseRing>::from_vec(
+        (0..pp.security_parameter)
+            .map(|i| {
+                let pos = alpha.row(i).and_popcount(&a)
+                    + beta.row(i).and_popcount(&b)
+                    + gamma.row(i).and_popcount(&c);
+                let neg = delta.row(i).and_popcount(&z);
+                R::BaseRing::try_from(pos as u128).unwrap()
+                    - R::BaseRing::try_from(neg as u128).unwrap()
+            })
+            .collect(),
     );
-    let (delta_BR, w_BR) = (delta.map(embed::<R::BaseRing>), w.map(embed::<R::BaseRing>));
-    assert_eq!(w_BR, Vector::<R::BaseRing>::from(R::flattened_coeffs(&w_R)));
-    let g = &alpha_BR * &a_BR + &beta_BR * &b_BR + &gamma_BR * &c_BR - &delta_BR * &w_BR;
 
     debug_assert_eq!(
         g.len(),
@@ -138,7 +164,7 @@ where
         delta,
     };
 
-    let (index_pr, instance_pr) = reduce(pp, &transcript);
+    let (index_pr, instance_pr) = reduce(pp, &x, &transcript);
 
     let witness_pr = principal_relation::Witness::<R>::new(vec![
         a_R, b_R, c_R, w_R, a_tilde, b_tilde, c_tilde, w_tilde,
@@ -147,10 +173,12 @@ where
     (index_pr, instance_pr, witness_pr)
 }
 
+/// Prove an R1CS over Z2 whose index is in CSR format, which callers build once with [`SparseR1CSIndex::from`] or
+/// directly from the non-zero entries of the matrices, e.g., with `ImportedR1CS::to_binary_r1cs`
 pub fn prove_binary_r1cs<'a, R: PolyRing>(
     pp: &BinaryR1CSCRS<R>,
     merlin: &'a mut Merlin,
-    index: &<BinaryR1CS as Relation>::Index,
+    index: &SparseR1CSIndex<Z2>,
     instance: &<BinaryR1CS as Relation>::Instance,
     witness: &<BinaryR1CS as Relation>::Witness,
 ) -> ProofResult<&'a [u8]>
@@ -162,7 +190,7 @@ where
         DecompositionFriendlySignedRepresentative,
 {
     let (index_pr, instance_pr, witness_pr) =
-        prove_reduction_binaryr1cs_labradorpr(pp, merlin, index, instance, witness);
+        prove_reduction_binaryr1cs_labradorpr_sparse(pp, merlin, index, instance, witness);
 
     merlin.ratchet()?;
 
@@ -1,11 +1,17 @@
 use tracing_subscriber::fmt::format;
 use tracing_subscriber::fmt::format::FmtSpan;
 
+use ark_std::rand::{thread_rng, Rng};
+use num_traits::{One, Zero};
+
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
 use lattirust_arithmetic::ring::ntt::ntt_prime;
 use lattirust_arithmetic::ring::Pow2CyclotomicPolyRingNTT;
-use lattirust_arithmetic::ring::Zq1;
-use relations::r1cs::Size;
+use lattirust_arithmetic::ring::{Zq1, Z2};
+use relations::principal_relation::PrincipalRelation;
+use relations::r1cs::{Index, Instance, Size, Witness};
 use relations::reduction::Reduction;
+use relations::Relation;
 use relations::{test_completeness_with_init, test_soundness_with_init};
 
 use crate::binary_r1cs::util::BinaryR1CSCRS;
@@ -50,3 +56,58 @@ test_soundness_with_init!(
     TEST_SIZE,
     init
 );
+
+#[test]
+fn test_binary_r1cs_binds_instance() {
+    init();
+    let rng = &mut thread_rng();
+    let (k, n, n_x) = (D, D, 1);
+    // z_i * z_i = z_i for all i, with public input z_0 = 1
+    let identity = Matrix::<Z2>::from_fn(k, n, |i, j| if i == j { Z2::one() } else { Z2::zero() });
+    let index = Index {
+        a: identity.clone(),
+        b: identity.clone(),
+        c: identity,
+    };
+    let instance = Instance(Vector::<Z2>::from_element(n_x, Z2::one()));
+    let witness = Witness(Vector::<Z2>::from_fn(n - n_x, |_, _| {
+        if rng.gen::<bool>() {
+            Z2::one()
+        } else {
+            Z2::zero()
+        }
+    }));
+
+    let crs = BinaryR1CSCRS::<R>::new(k, n);
+    let io = ReductionBinaryR1CSPrincipalRelation::<R>::iopattern(&crs, &index, &instance);
+    let mut merlin = io.to_merlin();
+    let (_, _, witness_pr) = ReductionBinaryR1CSPrincipalRelation::<R>::prove(
+        &crs,
+        &index,
+        &instance,
+        &witness,
+        &mut merlin,
+    )
+    .unwrap();
+    let proof = merlin.transcript().to_vec();
+
+    let mut arthur = io.to_arthur(&proof);
+    let (index_pr, instance_pr) =
+        ReductionBinaryR1CSPrincipalRelation::<R>::verify(&crs, &index, &instance, &mut arthur)
+            .unwrap();
+    assert!(PrincipalRelation::<R>::is_satisfied_err(&index_pr, &instance_pr, &witness_pr).is_ok());
+
+    // The reduced statement for another public input is not satisfied by the witness
+    let other_instance = Instance(Vector::<Z2>::from_element(n_x, Z2::zero()));
+    let mut arthur = io.to_arthur(&proof);
+    let (index_pr, instance_pr) = ReductionBinaryR1CSPrincipalRelation::<R>::verify(
+        &crs,
+        &index,
+        &other_instance,
+        &mut arthur,
+    )
+    .unwrap();
+    assert!(
+        PrincipalRelation::<R>::is_satisfied_err(&index_pr, &instance_pr, &witness_pr).is_err()
+    );
+}
@@ -5,19 +5,19 @@ use std::fmt::Debug;
 use ark_std::rand;
 use derive_more::Display;
 use num_bigint::BigUint;
-use num_traits::{ToPrimitive, Zero};
+use num_traits::{One, ToPrimitive, Zero};
 
 use lattice_estimator::msis::{msis_h_128_linf, MSIS};
 use lattice_estimator::norms::Norm;
+use lattirust_arithmetic::linear_algebra::Matrix;
 use lattirust_arithmetic::linear_algebra::Vector;
-use lattirust_arithmetic::linear_algebra::{Matrix, SymmetricMatrix};
-use lattirust_arithmetic::ring::{PolyRing, Z2};
-use relations::principal_relation::{
-    ConstantQuadraticConstraint, Index, Instance, QuadraticConstraint, Size,
-};
+use lattirust_arithmetic::ring::PolyRing;
+use relations::principal_relation::{Index, Instance, Size};
 
 use crate::common_reference_string::CommonReferenceString;
-use crate::util::{basis_vector, embed};
+use crate::gf2::{BitMatrix, BitVector};
+use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+use crate::util::basis_vector;
 
 const SECURITY_PARAMETER: usize = 128;
 
@@ -156,15 +156,15 @@ impl<R: PolyRing> BinaryR1CSCRS<R> {
 #[derive(Clone, Debug)]
 pub struct BinaryR1CSTranscript<R: PolyRing> {
     pub t: Vector<R>,
-    pub alpha: Matrix<Z2>,
-    pub beta: Matrix<Z2>,
-    pub gamma: Matrix<Z2>,
+    pub alpha: BitMatrix,
+    pub beta: BitMatrix,
+    pub gamma: BitMatrix,
     pub g: Vector<R::BaseRing>,
-    pub delta: Matrix<Z2>, // Not technically part of the transcript, but computed by prover and verifier
+    pub delta: BitMatrix, // Not technically part of the transcript, but computed by prover and verifier
 }
 
 /// Express the constraint <alpha_i, a> = 0 as a constraint on the polynomial <alphaR_i, a_R> = 0, where alphaR_i is the element of R such that the constant term of alphaR_i * a_R (as polynomial multiplication over R) is equal to <alpha_i, a>
-fn embed_Zqlinear_Rqlinear<R: PolyRing>(alpha_i: &Vector<Z2>, k: usize, n_pr: usize) -> Vector<R> {
+fn embed_Zqlinear_Rqlinear<R: PolyRing>(alpha_i: &BitVector, k: usize, n_pr: usize) -> Vector<R> {
     let mut phi_a_idx = Vec::<R>::with_capacity(n_pr);
     let d = R::dimension();
     let k_ = k.div_ceil(d);
@@ -173,17 +173,26 @@ fn embed_Zqlinear_Rqlinear<R: PolyRing>(alpha_i: &Vector<Z2>, k: usize, n_pr: us
     for j in 0..k_ {
         // Embed alpha_i as an element alphaR_i of R such that the constant term of alphaR_i * a_R (as polynomial multiplication over R) is equal to <alpha_i, a>
         let mut coeffs = vec![R::BaseRing::zero(); d];
-        coeffs[0] = embed::<R::BaseRing>(alpha_i[j * d]);
+        let bit = |l: usize| {
+            if alpha_i.get(j * d + l) {
+                R::BaseRing::one()
+            } else {
+                R::BaseRing::zero()
+            }
+        };
+        coeffs[0] = bit(0);
         for l in 1..d {
-            coeffs[d - 1 - l] = -embed::<R::BaseRing>(alpha_i[j * d + l]);
+            coeffs[d - 1 - l] = -bit(l);
         }
         phi_a_idx.push(R::from(coeffs));
     }
     Vector::<R>::from(phi_a_idx)
 }
 
+/// Reduce to a principal relation, where `x` is the public input and the committed `w` holds zeros at its positions
 pub fn reduce<R: PolyRing>(
     pp: &BinaryR1CSCRS<R>,
+    x: &BitVector,
     transcript: &BinaryR1CSTranscript<R>,
 ) -> (Index<R>, Instance<R>)
 where
@@ -192,10 +201,7 @@ where
     let d = R::dimension();
     assert_eq!(k, n, "the current implementation only support k = n"); // TODO: remove this restriction by splitting a,b,c or w into multiple vectors
 
-    let r_pr: usize = 8;
     let n_pr = n.div_ceil(d);
-    let [a_idx, b_idx, c_idx, w_idx, a_tilde_idx, b_tilde_idx, c_tilde_idx, w_tilde_idx] =
-        [0, 1, 2, 3, 4, 5, 6, 7];
     let (t, alpha, beta, gamma, g, delta) = (
         &transcript.t,
         &transcript.alpha,
@@ -205,115 +211,86 @@ where
         &transcript.delta,
     );
 
+    let norm_bound_sq =
+        BinaryR1CSCRS::<R>::pr_index(n, pp.commitment_output_size).norm_bound_squared;
+    let mut builder = RelationBuilder::<R>::new(n_pr, norm_bound_sq);
+    let [a, b, c, w] = ["a", "b", "c", "w"].map(|name| builder.witness(name));
+    let [a_tilde, b_tilde, c_tilde, w_tilde] =
+        ["ã", "~b", "~c", "~w"].map(|name| builder.witness(name));
+
     // F_1 = {A_i * (a || b || c || w) = t_i}_{i in [m/d]}
-    let mut quad_dot_prod_funcs =
-        Vec::<QuadraticConstraint<R>>::with_capacity(pp.commitment_output_size + 3 * n_pr);
     for i in 0..t.len() {
-        let mut phi =
+        let expr =
             pp.A.row(i)
                 .transpose()
                 .as_slice()
                 .chunks(n_pr)
-                .map(|v| Vector::<R>::from_slice(v))
-                .collect::<Vec<Vector<R>>>();
-        phi.append(&mut vec![Vector::<R>::zeros(n_pr); r_pr / 2]); // pad with zeros for "tilde witnesses"
-        quad_dot_prod_funcs.push(QuadraticConstraint::<R>::new_linear(phi, t[i]))
+                .zip([a, b, c, w])
+                .fold(QuadraticExpr::zero(), |expr, (A_iv, v)| {
+                    expr + QuadraticExpr::linear(Vector::<R>::from_slice(A_iv), v)
+                });
+        builder.constrain(expr, t[i]);
     }
 
     // ã = sigma_{-1}(a) <=>
     // ã_0 = a_0 and -ã_{n-i} = a_i for i in [n] <=>
     // <e_0, a> - <e_0, ã> = 0 and <e_i, a> + <e_{n-i}, ã> = 0, where e_i denote the i-th standard basis vector
     for i in 0..n_pr {
-        for (idx, tilde_idx) in [
-            (a_idx, a_tilde_idx),
-            (b_idx, b_tilde_idx),
-            (c_idx, c_tilde_idx),
-        ] {
-            let mut phis = vec![Vector::<R>::zeros(n_pr); r_pr];
-            phis[idx] = basis_vector(i, n_pr);
-            phis[tilde_idx] = if i == 0 {
+        for (v, v_tilde) in [(a, a_tilde), (b, b_tilde), (c, c_tilde)] {
+            let e_tilde = if i == 0 {
                 -basis_vector(0, n_pr)
             } else {
                 basis_vector(n_pr - i, n_pr)
             };
-            quad_dot_prod_funcs.push(QuadraticConstraint::<R>::new_linear(phis, R::zero()));
+            builder.constrain(
+                QuadraticExpr::linear(basis_vector(i, n_pr), v)
+                    + QuadraticExpr::linear(e_tilde, v_tilde),
+                R::zero(),
+            );
         }
     }
 
     // F_2
-    let mut ct_quad_dot_prod_funcs =
-        Vec::<ConstantQuadraticConstraint<R>>::with_capacity(5 + pp.security_parameter);
-    // <a, ã - 1> = 0 <=>
-    // <a, ã> + <ã, a> - <2, a> = 0
-    for (idx, tilde_idx) in [
-        (a_idx, a_tilde_idx),
-        (b_idx, b_tilde_idx),
-        (c_idx, c_tilde_idx),
-        (w_idx, w_tilde_idx),
-    ] {
-        let mut A = SymmetricMatrix::<R>::zero(r_pr);
-        A[(idx, tilde_idx)] = R::one();
-        let mut phi = vec![Vector::<R>::zeros(n_pr); r_pr];
-        phi[idx] =
-            Vector::<R>::from_element(n_pr, -R::from_scalar(R::BaseRing::try_from(2u128).unwrap()));
-        ct_quad_dot_prod_funcs.push(ConstantQuadraticConstraint::<R>::new(
-            A,
-            phi,
+    // <a, ã - 1> = 0 <=> <a, ã> - <1, a> = 0
+    for (v, v_tilde) in [(a, a_tilde), (b, b_tilde), (c, c_tilde), (w, w_tilde)] {
+        builder.constrain_ct(
+            QuadraticExpr::inner(v, v_tilde) - QuadraticExpr::linear(builder.ones(), v),
             R::BaseRing::zero(),
-        ));
+        );
     }
 
     // <a + b - 2c, ã + ~b - 2~c - 1> = 0 <=>
     // <a, ã> + <a, ~b> -2*<a, ~c> + <-1, a> +
     // <b, ã> + <b, ~b> -2*<b, ~c> + <-1, b> +
     // -2*<c, ã> -2*<c, ~b> +4*<c, ~c> + <2, c> = 0
-    // => double everything to make sure a is symmetric
-    let mut A = SymmetricMatrix::<R>::zero(r_pr);
-    let min_two = -R::try_from(2u128).unwrap();
-    let vals = [
-        (a_idx, a_tilde_idx, R::one()),
-        (a_idx, b_tilde_idx, R::one()),
-        (a_idx, c_tilde_idx, min_two),
-        (b_idx, a_tilde_idx, R::one()),
-        (b_idx, b_tilde_idx, R::one()),
-        (b_idx, c_tilde_idx, min_two),
-        (c_idx, a_tilde_idx, min_two),
-        (c_idx, b_tilde_idx, min_two),
-        (c_idx, c_tilde_idx, R::try_from(4u128).unwrap()),
-    ];
-    for (i, j, v) in vals {
-        debug_assert!(A[(i, j)].is_zero());
-        debug_assert!(A[(j, i)].is_zero());
-        A[(i, j)] = v;
-        A[(j, i)] = v;
-    }
-    let mut phi = vec![Vector::<R>::zeros(n_pr); r_pr];
-    phi[a_idx] = Vector::<R>::from_element(n_pr, min_two);
-    phi[b_idx] = Vector::<R>::from_element(n_pr, min_two);
-    phi[c_idx] = Vector::<R>::from_element(n_pr, R::try_from(4u128).unwrap());
-    ct_quad_dot_prod_funcs.push(ConstantQuadraticConstraint::<R>::new(
-        A,
-        phi,
-        R::BaseRing::zero(),
-    ));
+    let two = R::try_from(2u128).unwrap();
+    let expr = [(a, R::one()), (b, R::one()), (c, -two)]
+        .into_iter()
+        .flat_map(|(v, x)| {
+            [(a_tilde, R::one()), (b_tilde, R::one()), (c_tilde, -two)]
+                .map(|(v_tilde, y)| QuadraticExpr::inner(v, v_tilde) * (x * y))
+        })
+        .fold(QuadraticExpr::zero(), |expr, term| expr + term)
+        - QuadraticExpr::linear(builder.ones(), a)
+        - QuadraticExpr::linear(builder.ones(), b)
+        + QuadraticExpr::linear(builder.ones(), c) * two;
+    builder.constrain_ct(expr, R::BaseRing::zero());
 
+    let x_padded = BitVector::concat(&[x, &BitVector::zeros(n - x.len())]);
     for i in 0..pp.security_parameter {
-        // Constrain <alpha_i, a_i> + <beta_i, b_i> + <gamma_i, c_i> - <delta_i, w_i> = g_i (over the constant coefficients)
-        let mut phi = vec![Vector::<R>::zeros(n_pr); r_pr];
-        phi[a_idx] = embed_Zqlinear_Rqlinear(&alpha.row(i).transpose(), k, n_pr);
-        phi[b_idx] = embed_Zqlinear_Rqlinear(&beta.row(i).transpose(), k, n_pr);
-        phi[c_idx] = embed_Zqlinear_Rqlinear(&gamma.row(i).transpose(), k, n_pr);
-        phi[w_idx] = -embed_Zqlinear_Rqlinear(&delta.row(i).transpose(), k, n_pr);
-
-        ct_quad_dot_prod_funcs.push(ConstantQuadraticConstraint::<R>::new_linear(phi, g[i]));
+        // Constrain <alpha_i, a_i> + <beta_i, b_i> + <gamma_i, c_i> - <delta_i, w_i> = g_i + <delta_i, x> (over the constant coefficients),
+        // where the part of delta_i acting on x is moved to the right-hand side
+        let mut delta_w = delta.row(i).clone();
+        for j in 0..x.len() {
+            delta_w.set(j, false);
+        }
+        let expr = QuadraticExpr::linear(embed_Zqlinear_Rqlinear(alpha.row(i), k, n_pr), a)
+            + QuadraticExpr::linear(embed_Zqlinear_Rqlinear(beta.row(i), k, n_pr), b)
+            + QuadraticExpr::linear(embed_Zqlinear_Rqlinear(gamma.row(i), k, n_pr), c)
+            - QuadraticExpr::linear(embed_Zqlinear_Rqlinear(&delta_w, k, n_pr), w);
+        let delta_x = R::BaseRing::try_from(delta.row(i).and_popcount(&x_padded) as u128).unwrap();
+        builder.constrain_ct(expr, g[i] + delta_x);
     }
 
-    let new_index = BinaryR1CSCRS::<R>::pr_index(pp.num_variables, pp.commitment_output_size);
-
-    let new_instance = Instance::<R> {
-        quad_dot_prod_funcs,
-        ct_quad_dot_prod_funcs,
-    };
-
-    (new_index, new_instance)
+    builder.build()
 }
@@ -1,15 +1,14 @@
 #![allow(non_snake_case)]
 
-use nimue::{Arthur, ProofError, ProofResult};
+use nimue::{Arthur, BytePublic, ProofError, ProofResult};
 use num_traits::Zero;
 
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
 use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
 use lattirust_arithmetic::nimue::arthur::SerArthur;
-use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
-use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::ring::{PolyRing, Z2};
 use lattirust_arithmetic::traits::FromRandomBytes;
 use lattirust_util::check;
 use relations::principal_relation::{Index, Instance};
@@ -17,6 +16,8 @@ use relations::Relation;
 
 use crate::binary_r1cs::util::{reduce, BinaryR1CSCRS, BinaryR1CSTranscript};
 use crate::binary_r1cs::BinaryR1CS;
+use crate::gf2::{challenge_bit_matrix, embed_bit, sum_binary_products, BitVector};
+use crate::sparse::{statement_digest, SparseR1CSIndex};
 use crate::verifier::verify_principal_relation;
 
 pub fn verify_reduction_binaryr1cs_labradorpr<R: PolyRing>(
@@ -25,6 +26,24 @@ pub fn verify_reduction_binaryr1cs_labradorpr<R: PolyRing>(
     index: &<BinaryR1CS as Relation>::Index,
     instance: &<BinaryR1CS as Relation>::Instance,
 ) -> ProofResult<(Index<R>, Instance<R>)>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+{
+    verify_reduction_binaryr1cs_labradorpr_sparse(
+        arthur,
+        crs,
+        &SparseR1CSIndex::from(index),
+        instance,
+    )
+}
+
+/// Same as [`verify_reduction_binaryr1cs_labradorpr`], but for an index whose matrices are already in CSR format.
+pub fn verify_reduction_binaryr1cs_labradorpr_sparse<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &BinaryR1CSCRS<R>,
+    index: &SparseR1CSIndex<Z2>,
+    instance: &<BinaryR1CS as Relation>::Instance,
+) -> ProofResult<(Index<R>, Instance<R>)>
 where
     <R as PolyRing>::BaseRing: WithSignedRepresentative,
 {
@@ -32,14 +51,20 @@ where
 
     let (k, n) = (crs.num_constraints, crs.num_variables);
 
+    arthur.public_bytes(&statement_digest::<R, _>(
+        index,
+        &instance.0,
+        embed_bit::<R>,
+    ))?;
+
     let t = arthur.next_vector(crs.A.nrows())?;
 
-    let alpha = arthur.challenge_binary_matrix(crs.security_parameter, k)?;
-    let beta = arthur.challenge_binary_matrix(crs.security_parameter, n)?;
-    let gamma = arthur.challenge_binary_matrix(crs.security_parameter, n)?;
+    let alpha = challenge_bit_matrix(arthur, crs.security_parameter, k)?;
+    let beta = challenge_bit_matrix(arthur, crs.security_parameter, n)?;
+    let gamma = challenge_bit_matrix(arthur, crs.security_parameter, n)?;
 
     // delta_i is computed mod 2, i.e., over Z2
-    let delta = &alpha * A + &beta * B + &gamma * C;
+    let delta = sum_binary_products(&[(&alpha, A), (&beta, B), (&gamma, C)]);
 
     let g = arthur.next_vector_canonical::<R::BaseRing>(crs.security_parameter)?;
 
@@ -61,14 +86,15 @@ where
         delta,
     };
 
-    let (index_pr, instance_pr) = reduce(crs, &transcript);
+    let (index_pr, instance_pr) = reduce(crs, &BitVector::from(&instance.0), &transcript);
     Ok((index_pr, instance_pr))
 }
 
+/// Verify a proof of an R1CS over Z2 whose index is in CSR format, see [`crate::binary_r1cs::prover::prove_binary_r1cs`].
 pub fn verify_binary_r1cs<R: PolyRing>(
     arthur: &mut Arthur,
     crs: &BinaryR1CSCRS<R>,
-    index: &<BinaryR1CS as Relation>::Index,
+    index: &SparseR1CSIndex<Z2>,
     instance: &<BinaryR1CS as Relation>::Instance,
 ) -> Result<(), ProofError>
 where
@@ -78,9 +104,9 @@ where
     <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
         DecompositionFriendlySignedRepresentative,
 {
-    //TODO: add crs and statement to transcript
+    //TODO: add crs to transcript
     let (index_pr, instance_pr) =
-        verify_reduction_binaryr1cs_labradorpr(arthur, crs, index, instance)?;
+        verify_reduction_binaryr1cs_labradorpr_sparse(arthur, crs, index, instance)?;
 
     arthur.ratchet()?;
 
@@ -0,0 +1,181 @@
+//! Commitments to a witness under the first-level and outer commitment matrices of a CRS, about which several principal
+//! relations can be proven later with `prover::prove_principal_relation_committed`.
+//!
+//! The committer computes the first-level commitments $t_i = A s_i$, keeps them, and publishes the outer commitment
+//! $u_1 = B \cdot t$ to their decomposition. In a proof about the committed witness, the prover then only sends
+//! the outer commitment $C \cdot g$ to the inner products of the witness, and the verifier adds the published $u_1$,
+//! so the proof binds to the commitment.
+//! Relations with different numbers of constraints can be proven about the same commitment with the CRSs given by
+//! `CommonReferenceString::for_constraints`, which share the commitment matrices.
+
+use num_traits::ToPrimitive;
+use rayon::prelude::*;
+
+use lattirust_arithmetic::decomposition::balanced_decomposition::decompose_vec_vector_dimfirst;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::WithL2Norm;
+use relations::principal_relation::Witness;
+
+use crate::common_reference_string::CommonReferenceString;
+use crate::util::{commit, flatten_vec_vec_vector};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commitment<R: PolyRing> {
+    /// First-level commitments $t_i = A s_i$ to the witness vectors, which the committer keeps to prove relations
+    pub t: Vec<Vector<R>>,
+    /// Outer commitment to the decomposed first-level commitments, which is published
+    pub u_1: Vector<R>,
+}
+
+impl<R: PolyRing> Commitment<R>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    /// Commit to a witness of `crs.r` vectors of length `crs.n`
+    pub fn new(crs: &CommonReferenceString<R>, witness: &Witness<R>) -> Self {
+        assert_eq!(
+            witness.s.len(),
+            crs.r,
+            "the witness must have crs.r vectors"
+        );
+        let t: Vec<Vector<R>> = witness
+            .s
+            .par_iter()
+            .map(|s_i| commit(&crs.A, s_i))
+            .collect();
+        let u_1 = Self::outer_commitment(crs, &t);
+        Commitment { t, u_1 }
+    }
+
+    /// $B \cdot t$, for the decomposition of the first-level commitments in basis `b1`
+    pub(crate) fn outer_commitment(
+        crs: &CommonReferenceString<R>,
+        t: &Vec<Vector<R>>,
+    ) -> Vector<R> {
+        let t_decomp = decompose_vec_vector_dimfirst(t, crs.b1, Some(crs.t1)); // t1 x r x k
+        commit(&crs.B, &flatten_vec_vec_vector(&t_decomp))
+    }
+
+    /// Check that the witness opens the published outer commitment u_1: it has the right dimensions, its norm is within
+    /// the bound of the CRS, and it commits to u_1
+    pub fn verify_opening(
+        crs: &CommonReferenceString<R>,
+        u_1: &Vector<R>,
+        witness: &Witness<R>,
+    ) -> bool {
+        if witness.s.len() != crs.r || witness.s.iter().any(|s_i| s_i.len() != crs.n) {
+            return false;
+        }
+        let norm_sq = witness
+            .s
+            .iter()
+            .map(|s_i| R::flattened(s_i).l2_norm_squared().to_f64().unwrap())
+            .sum::<f64>();
+        norm_sq <= crs.norm_bound_squared && Self::new(crs, witness).u_1 == *u_1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nimue::ProofError;
+
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+
+    use super::*;
+    use crate::iopattern::commit_and_prove_iopattern;
+    use crate::prover::prove_principal_relation_committed;
+    use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+    use crate::test_util::ternary_vector;
+    use crate::verifier::verify_principal_relation_committed;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    #[test]
+    fn test_commit_and_prove() {
+        let n = 8;
+        let (value_a, value_b) = (ternary_vector::<R>(n, 0), ternary_vector::<R>(n, 1));
+        let norm_bound_sq = (2 * n * R::dimension()) as f64;
+
+        // <a, b> + <1, a> = b_1
+        let mut builder_1 = RelationBuilder::<R>::new(n, norm_bound_sq);
+        let (a_1, b_1) = (builder_1.witness("a"), builder_1.witness("b"));
+        builder_1.constrain(
+            QuadraticExpr::inner(a_1, b_1) + QuadraticExpr::linear(builder_1.ones(), a_1),
+            value_a.dot(&value_b) + builder_1.ones().dot(&value_a),
+        );
+        // <a, a> = b_2 and <1, b> = b_3
+        let mut builder_2 = RelationBuilder::<R>::new(n, norm_bound_sq);
+        let (a_2, b_2) = (builder_2.witness("a"), builder_2.witness("b"));
+        builder_2.constrain(QuadraticExpr::inner(a_2, a_2), value_a.dot(&value_a));
+        builder_2.constrain(
+            QuadraticExpr::linear(builder_2.ones(), b_2),
+            builder_2.ones().dot(&value_b),
+        );
+
+        let mut assignment = builder_1.assignment();
+        assignment.assign(a_1, value_a).assign(b_1, value_b);
+        let witness = assignment.witness().unwrap();
+        let (size_1, size_2) = (builder_1.size(), builder_2.size());
+        let (index_1, instance_1) = builder_1.build();
+        let (index_2, instance_2) = builder_2.build();
+
+        let crs_1 = CommonReferenceString::<R>::new_for_size(size_1);
+        let crs_2 = crs_1.for_constraints(size_2.num_constraints, size_2.num_constant_constraints);
+
+        let commitment = Commitment::new(&crs_1, &witness);
+        assert!(Commitment::verify_opening(
+            &crs_1,
+            &commitment.u_1,
+            &witness
+        ));
+        let mut other_witness = witness.clone();
+        other_witness.s.swap(0, 1);
+        assert!(!Commitment::verify_opening(
+            &crs_1,
+            &commitment.u_1,
+            &other_witness
+        ));
+        assert_eq!(Commitment::new(&crs_2, &witness), commitment);
+
+        for (crs, index, instance) in [
+            (&crs_1, &index_1, &instance_1),
+            (&crs_2, &index_2, &instance_2),
+        ] {
+            let io = commit_and_prove_iopattern(crs);
+            let mut merlin = io.to_merlin();
+            let proof = prove_principal_relation_committed(
+                &mut merlin,
+                crs,
+                &commitment,
+                index,
+                instance,
+                &witness,
+            )
+            .unwrap()
+            .to_vec();
+
+            let mut arthur = io.to_arthur(&proof);
+            verify_principal_relation_committed(&mut arthur, crs, &commitment.u_1, index, instance)
+                .unwrap();
+
+            // The proof does not verify against another commitment
+            let other_commitment = Commitment::new(crs, &other_witness);
+            let mut arthur = io.to_arthur(&proof);
+            assert!(matches!(
+                verify_principal_relation_committed(
+                    &mut arthur,
+                    crs,
+                    &other_commitment.u_1,
+                    index,
+                    instance
+                ),
+                Err(ProofError::InvalidProof)
+            ));
+        }
+    }
+}
@@ -15,6 +15,8 @@ use relations::principal_relation::Size;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use crate::shared::{FoldingCache, FoldingPrecomputation};
+
 /// Common reference string for one round of the LaBRADOR protocol
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct CommonReferenceString<R: PolyRing> {
@@ -59,6 +61,9 @@ pub struct CommonReferenceString<R: PolyRing> {
     pub b2: u128,
     /// A reference to the CRS for the next recursive round, or `None` if this is the CRS for the last round
     pub next_crs: Option<Box<CommonReferenceString<R>>>,
+    /// The parts of a folded instance that only depend on this CRS, shared by all proofs and verifications under it
+    #[serde(skip, default = "FoldingCache::default")]
+    folding: FoldingCache<R>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -272,11 +277,33 @@ impl<R: PolyRing> CommonReferenceString<R> {
             b1,
             b2,
             next_crs: None,
+            folding: FoldingCache::default(),
         };
         crs.next_crs = crs.next_crs().map(Box::new);
         crs
     }
 
+    /// The parts of a folded instance that only depend on this CRS, computed on the first call
+    pub fn folding_precomputation(&self) -> &FoldingPrecomputation<R> {
+        self.folding.get_or_build(self)
+    }
+
+    /// The same CRS for statements with other numbers of constraints, which shares the commitment matrices, so that
+    /// several statements can be proven about one committed witness (see `crate::commitment`)
+    pub fn for_constraints(
+        &self,
+        num_constraints: usize,
+        num_constant_constraints: usize,
+    ) -> CommonReferenceString<R> {
+        // The next rounds only depend on the number of constraints through the size of the folded instance, which
+        // does not change
+        CommonReferenceString {
+            num_constraints,
+            num_constant_constraints,
+            ..self.clone()
+        }
+    }
+
     /// Compute the squared norm bound for the next folded instance (cf. Section 5.4 of the Labrador paper)
     pub fn next_norm_bound_sq(
         r: usize,
@@ -0,0 +1,206 @@
+//! Encodings of Falcon public keys and signatures, as specified in Section 3.11 of the Falcon specification.
+
+use crate::falcon_agg::{
+    FalconError, FalconParameters, FalconPublicKey, FalconSignature, FALCON_RING_MODULUS, SALT_LEN,
+};
+
+const PUBLIC_KEY_HEADER: u8 = 0x00;
+const SIGNATURE_HEADER: u8 = 0x30;
+const COEFF_BITS_PUBLIC_KEY: usize = 14;
+/// Largest absolute value of a coefficient of s_2 that can be compressed
+const MAX_COMPRESSED_COEFF: u32 = 2047;
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    num_bits: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: vec![],
+            num_bits: 0,
+        }
+    }
+
+    /// Append the `len` least significant bits of `value`, most significant bit first
+    fn write(&mut self, value: u32, len: usize) {
+        for i in (0..len).rev() {
+            if self.num_bits == 8 * self.bytes.len() {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.num_bits % 8);
+            }
+            self.num_bits += 1;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn read_bit(&mut self) -> Result<u32, FalconError> {
+        let byte = self
+            .bytes
+            .get(self.pos / 8)
+            .ok_or(FalconError::InvalidEncoding("unexpected end of input"))?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit as u32)
+    }
+
+    fn read(&mut self, len: usize) -> Result<u32, FalconError> {
+        let mut value = 0;
+        for _ in 0..len {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    /// Check that all remaining bits are zero
+    fn finish(self) -> Result<(), FalconError> {
+        let first_unused_byte = self.pos.div_ceil(8);
+        let offset = self.pos % 8;
+        if offset > 0 && self.bytes[self.pos / 8] & (0xff >> offset) != 0 {
+            return Err(FalconError::InvalidEncoding("non-zero padding bits"));
+        }
+        if self.bytes[first_unused_byte..].iter().any(|b| *b != 0) {
+            return Err(FalconError::InvalidEncoding("non-zero padding bytes"));
+        }
+        Ok(())
+    }
+}
+
+fn params_from_header(header: u8, expected_type: u8) -> Result<FalconParameters, FalconError> {
+    if header & 0xf0 != expected_type {
+        return Err(FalconError::InvalidEncoding("unexpected header type"));
+    }
+    FalconParameters::from_log_n(header & 0x0f)
+        .ok_or(FalconError::InvalidEncoding("unsupported degree in header"))
+}
+
+fn params_from_len(what: &'static str, len: usize) -> Result<FalconParameters, FalconError> {
+    [FalconParameters::Falcon512, FalconParameters::Falcon1024]
+        .into_iter()
+        .find(|params| params.n() == len)
+        .ok_or(FalconError::InvalidLength {
+            what,
+            expected: FalconParameters::Falcon512.n(),
+            found: len,
+        })
+}
+
+impl FalconPublicKey {
+    /// Decode a public key: the header byte 0x00 + log n, followed by the coefficients of h as 14-bit values, most significant bit first
+    pub fn decode(bytes: &[u8]) -> Result<(FalconParameters, Self), FalconError> {
+        let (header, packed) = bytes
+            .split_first()
+            .ok_or(FalconError::InvalidEncoding("empty public key"))?;
+        let params = params_from_header(*header, PUBLIC_KEY_HEADER)?;
+        let expected_len = (params.n() * COEFF_BITS_PUBLIC_KEY).div_ceil(8);
+        if packed.len() != expected_len {
+            return Err(FalconError::InvalidLength {
+                what: "public key encoding",
+                expected: 1 + expected_len,
+                found: bytes.len(),
+            });
+        }
+
+        let mut reader = BitReader::new(packed);
+        let mut h = Vec::<u16>::with_capacity(params.n());
+        for _ in 0..params.n() {
+            let h_i = reader.read(COEFF_BITS_PUBLIC_KEY)?;
+            if h_i >= FALCON_RING_MODULUS {
+                return Err(FalconError::InvalidEncoding(
+                    "public key coefficient is not reduced mod q",
+                ));
+            }
+            h.push(h_i as u16);
+        }
+        reader.finish()?;
+        Ok((params, Self { h }))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, FalconError> {
+        let params = params_from_len("public key", self.h.len())?;
+        let mut writer = BitWriter::new();
+        writer.write((PUBLIC_KEY_HEADER | params.log_n()) as u32, 8);
+        for h_i in &self.h {
+            if *h_i as u32 >= FALCON_RING_MODULUS {
+                return Err(FalconError::InvalidEncoding(
+                    "public key coefficient is not reduced mod q",
+                ));
+            }
+            writer.write(*h_i as u32, COEFF_BITS_PUBLIC_KEY);
+        }
+        Ok(writer.finish())
+    }
+}
+
+impl FalconSignature {
+    /// Decode a signature in the compressed format: the header byte 0x30 + log n, the salt, and the compressed coefficients of s_2.
+    /// Each coefficient is encoded as a sign bit, its 7 low bits, and its remaining high bits in unary; trailing zero padding is accepted.
+    pub fn decode(bytes: &[u8]) -> Result<(FalconParameters, Self), FalconError> {
+        if bytes.len() < 1 + SALT_LEN {
+            return Err(FalconError::InvalidEncoding("signature is too short"));
+        }
+        let params = params_from_header(bytes[0], SIGNATURE_HEADER)?;
+        let salt: [u8; SALT_LEN] = bytes[1..1 + SALT_LEN].try_into().unwrap();
+
+        let mut reader = BitReader::new(&bytes[1 + SALT_LEN..]);
+        let mut s2 = Vec::<i16>::with_capacity(params.n());
+        for _ in 0..params.n() {
+            let sign = reader.read_bit()?;
+            let mut abs = reader.read(7)?;
+            while reader.read_bit()? == 0 {
+                abs += 1 << 7;
+                if abs > MAX_COMPRESSED_COEFF {
+                    return Err(FalconError::InvalidEncoding(
+                        "signature coefficient is out of range",
+                    ));
+                }
+            }
+            if sign == 1 && abs == 0 {
+                // -0 is not a valid encoding, so that the encoding is unique
+                return Err(FalconError::InvalidEncoding("negative zero in signature"));
+            }
+            s2.push(if sign == 1 { -(abs as i16) } else { abs as i16 });
+        }
+        reader.finish()?;
+        Ok((params, Self { salt, s2 }))
+    }
+
+    /// Encode a signature in the compressed format, without padding
+    pub fn encode(&self) -> Result<Vec<u8>, FalconError> {
+        let params = params_from_len("signature", self.s2.len())?;
+        let mut writer = BitWriter::new();
+        writer.write((SIGNATURE_HEADER | params.log_n()) as u32, 8);
+        for b in self.salt {
+            writer.write(b as u32, 8);
+        }
+        for s in &self.s2 {
+            let abs = s.unsigned_abs() as u32;
+            if abs > MAX_COMPRESSED_COEFF {
+                return Err(FalconError::InvalidEncoding(
+                    "signature coefficient is out of range",
+                ));
+            }
+            writer.write((*s < 0) as u32, 1);
+            writer.write(abs & 0x7f, 7);
+            writer.write(1, (abs >> 7) as usize + 1);
+        }
+        Ok(writer.finish())
+    }
+}
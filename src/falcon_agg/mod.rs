@@ -0,0 +1,531 @@
+use std::fmt::{Debug, Display, Formatter};
+
+use nimue::{IOPattern, ProofError};
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::nimue::iopattern::{
+    RatchetIOPattern, SerIOPattern, SqueezeFromRandomBytes,
+};
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use lattirust_arithmetic::{linear_algebra::Vector, ring::PolyRing};
+use relations::principal_relation::{Index, Instance, PrincipalRelation, Witness};
+use relations::Relation;
+
+use crate::falcon_agg::prover::prove_falcon_aggregation;
+use crate::falcon_agg::reduction::{reduce, FalconAggregationCRS, FalconTranscript};
+use crate::falcon_agg::subring::split;
+use crate::falcon_agg::util::{
+    hash_to_point, l2_norm_sq, slack, verification_witness, FalconWitnessCoeffs,
+};
+use crate::falcon_agg::verifier::verify_falcon_aggregation;
+use crate::iopattern::LabradorIOPattern;
+
+pub mod encoding;
+pub mod prover;
+pub mod reduction;
+pub mod subring;
+#[cfg(test)]
+mod test;
+pub mod util;
+pub mod verifier;
+
+pub const FALCON_RING_MODULUS: u32 = 12289;
+
+/// Length in bytes of the salt r in a Falcon signature
+pub const SALT_LEN: usize = 40;
+
+/// Falcon parameter sets, see Table 3.3 of the Falcon specification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FalconParameters {
+    Falcon512,
+    Falcon1024,
+}
+
+impl FalconParameters {
+    pub const fn from_log_n(log_n: u8) -> Option<Self> {
+        match log_n {
+            9 => Some(FalconParameters::Falcon512),
+            10 => Some(FalconParameters::Falcon1024),
+            _ => None,
+        }
+    }
+
+    pub const fn log_n(&self) -> u8 {
+        match self {
+            FalconParameters::Falcon512 => 9,
+            FalconParameters::Falcon1024 => 10,
+        }
+    }
+
+    pub const fn n(&self) -> usize {
+        match self {
+            FalconParameters::Falcon512 => 512,
+            FalconParameters::Falcon1024 => 1024,
+        }
+    }
+
+    /// $\lfloor\beta^2\rfloor$, the acceptance bound on $\|(s_1, s_2)\|_2^2$
+    pub const fn signature_bound_sq(&self) -> u64 {
+        match self {
+            FalconParameters::Falcon512 => 34034726,
+            FalconParameters::Falcon1024 => 70265242,
+        }
+    }
+
+    /// Bound on $\|v\|_2^2$ for the quotient $v = (c - s_1 - h s_2) / q$ of a valid signature, where $c$ and $h$ are centered mod q.
+    /// Every coefficient satisfies $|v_i| \leq (|c_i - s_{1,i}| + \|h\|_\infty \|s_2\|_1) / q \leq 1 + \sqrt{n} \beta / 2$.
+    pub fn quotient_bound_sq(&self) -> u64 {
+        let n = self.n() as u64;
+        let v_inf = (n * self.signature_bound_sq()).isqrt() / 2 + 2;
+        n * v_inf * v_inf
+    }
+}
+
+/// A Falcon public key h, with coefficients in $[0, q)$
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FalconPublicKey {
+    pub h: Vec<u16>,
+}
+
+/// A Falcon signature (r, s_2), with the salt r and the signed coefficients of s_2
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FalconSignature {
+    pub salt: [u8; SALT_LEN],
+    pub s2: Vec<i16>,
+}
+
+#[derive(Debug)]
+pub enum FalconError {
+    InvalidLength {
+        what: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    SignatureTooLong {
+        norm_sq: u64,
+        bound_sq: u64,
+    },
+    QuotientTooLong {
+        norm_sq: u64,
+        bound_sq: u64,
+    },
+    UnsupportedRingDimension {
+        dimension: usize,
+        n: usize,
+    },
+    PublicKeyMismatch {
+        index: usize,
+    },
+    InvalidEncoding(&'static str),
+    ParameterMismatch {
+        public_key: FalconParameters,
+        signature: FalconParameters,
+    },
+    InvalidProof(ProofError),
+}
+
+impl Display for FalconError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FalconError::InvalidLength {
+                what,
+                expected,
+                found,
+            } => write!(f, "{what} has length {found}, expected {expected}"),
+            FalconError::SignatureTooLong { norm_sq, bound_sq } => write!(
+                f,
+                "signature has squared norm {norm_sq}, which exceeds the bound {bound_sq}"
+            ),
+            FalconError::QuotientTooLong { norm_sq, bound_sq } => write!(
+                f,
+                "quotient has squared norm {norm_sq}, which exceeds the bound {bound_sq}"
+            ),
+            FalconError::UnsupportedRingDimension { dimension, n } => write!(
+                f,
+                "cannot embed the Falcon ring of degree {n} into a ring of dimension {dimension}"
+            ),
+            FalconError::PublicKeyMismatch { index } => write!(
+                f,
+                "signature {index} is under another public key than the previous signatures"
+            ),
+            FalconError::InvalidEncoding(reason) => write!(f, "invalid encoding: {reason}"),
+            FalconError::ParameterMismatch {
+                public_key,
+                signature,
+            } => write!(
+                f,
+                "public key is for {public_key:?}, but signature is for {signature:?}"
+            ),
+            FalconError::InvalidProof(e) => write!(f, "invalid aggregate proof: {e:?}"),
+        }
+    }
+}
+
+impl std::error::Error for FalconError {}
+
+impl From<ProofError> for FalconError {
+    fn from(e: ProofError) -> Self {
+        FalconError::InvalidProof(e)
+    }
+}
+
+/// The witness for one Falcon signature, with every polynomial of the Falcon ring split into pieces in R, see `subring`
+#[derive(Clone, Debug)]
+pub struct FalconVerificationWitness<R: PolyRing> {
+    pub signature: (Vec<R>, Vec<R>),
+    pub v: Vec<R>,
+    /// Slack y such that $\|(s_1, s_2, y)\|_2^2 = \beta^2$
+    pub signature_slack: R,
+    /// Slack z such that $\|(v, z)\|_2^2 = \beta_v^2$
+    pub quotient_slack: R,
+}
+
+impl<R: PolyRing> FalconVerificationWitness<R> {
+    /// Compute the witness (s_1, s_2, v) for a Falcon signature, and the pieces of the hashed message c, failing if the signature does not verify.
+    /// The dimension of R must divide n, and be at least 4 to fit the slack for the norm bounds.
+    pub fn new(
+        params: FalconParameters,
+        public_key: &FalconPublicKey,
+        message: &[u8],
+        signature: &FalconSignature,
+    ) -> Result<(Self, Vec<R>), FalconError> {
+        check_dimension::<R>(params)?;
+        let h = centered_public_key(public_key);
+        let c = centered_hash(params, &signature.salt, message);
+        let s2 = signature.s2.iter().map(|x| *x as i64).collect::<Vec<_>>();
+        let w = verification_witness(params, &h, &c, &s2)?;
+        Ok((Self::from_coeffs(params, &w)?, split(&c)))
+    }
+
+    /// Map an integer witness to R, and compute the slack for its norm bounds, which fails if the signature or the quotient is too long
+    pub fn from_coeffs(
+        params: FalconParameters,
+        coeffs: &FalconWitnessCoeffs,
+    ) -> Result<Self, FalconError> {
+        let norm_sq = l2_norm_sq(&coeffs.s1) + l2_norm_sq(&coeffs.s2);
+        if norm_sq > params.signature_bound_sq() {
+            return Err(FalconError::SignatureTooLong {
+                norm_sq,
+                bound_sq: params.signature_bound_sq(),
+            });
+        }
+        let quotient_norm_sq = l2_norm_sq(&coeffs.v);
+        if quotient_norm_sq > params.quotient_bound_sq() {
+            return Err(FalconError::QuotientTooLong {
+                norm_sq: quotient_norm_sq,
+                bound_sq: params.quotient_bound_sq(),
+            });
+        }
+        Ok(Self {
+            signature: (split(&coeffs.s1), split(&coeffs.s2)),
+            v: split(&coeffs.v),
+            signature_slack: slack(params.signature_bound_sq() - norm_sq),
+            quotient_slack: slack(params.quotient_bound_sq() - quotient_norm_sq),
+        })
+    }
+
+    /// Same as `new`, for a public key and a compressed signature encoded as in the Falcon specification
+    pub fn from_encoded(
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(Self, Vec<R>), FalconError> {
+        let (pk_params, public_key) = FalconPublicKey::decode(public_key)?;
+        let (sig_params, signature) = FalconSignature::decode(signature)?;
+        if pk_params != sig_params {
+            return Err(FalconError::ParameterMismatch {
+                public_key: pk_params,
+                signature: sig_params,
+            });
+        }
+        Self::new(pk_params, &public_key, message, &signature)
+    }
+}
+
+/// The witnesses and public inputs for aggregating a batch of Falcon signatures, whose size is only known at runtime
+pub struct FalconAggregator<R: PolyRing> {
+    params: FalconParameters,
+    witnesses: Vec<FalconVerificationWitness<R>>,
+    messages: Vec<Vec<R>>,
+    is_same_pk: bool,
+    public_key: Vec<Vec<R>>,
+}
+
+impl<R: PolyRing> FalconAggregator<R> {
+    pub fn new(
+        params: FalconParameters,
+        witnesses: Vec<FalconVerificationWitness<R>>,
+        messages: Vec<Vec<R>>,
+        is_same_pk: bool,
+        public_key: Vec<Vec<R>>,
+    ) -> Self {
+        assert_eq!(messages.len(), witnesses.len());
+        if is_same_pk {
+            assert_eq!(public_key.len(), 1);
+        } else {
+            assert_eq!(public_key.len(), witnesses.len());
+        }
+        Self {
+            params,
+            witnesses,
+            messages,
+            is_same_pk,
+            public_key,
+        }
+    }
+
+    /// An aggregator without signatures, see `add_signature`.
+    /// If `is_same_pk` is set, all signatures must be under the same public key, which is then only part of the statement once.
+    pub fn empty(params: FalconParameters, is_same_pk: bool) -> Self {
+        Self {
+            params,
+            witnesses: vec![],
+            messages: vec![],
+            is_same_pk,
+            public_key: vec![],
+        }
+    }
+
+    /// Add the signature `sig` on `msg` under `pk`, failing if it does not verify
+    pub fn add_signature(
+        &mut self,
+        pk: &FalconPublicKey,
+        msg: &[u8],
+        sig: &FalconSignature,
+    ) -> Result<&mut Self, FalconError> {
+        let (w, c) = FalconVerificationWitness::<R>::new(self.params, pk, msg, sig)?;
+        let public_key = public_key_to_ring(pk);
+        if !self.is_same_pk || self.public_key.is_empty() {
+            self.public_key.push(public_key);
+        } else if self.public_key[0] != public_key {
+            return Err(FalconError::PublicKeyMismatch {
+                index: self.num_signatures(),
+            });
+        }
+        self.witnesses.push(w);
+        self.messages.push(c);
+        Ok(self)
+    }
+
+    pub fn num_signatures(&self) -> usize {
+        self.witnesses.len()
+    }
+
+    /// Public parameters sized for the signatures added so far
+    pub fn crs(&self) -> FalconAggregationCRS<R> {
+        FalconAggregationCRS::new(self.params, self.num_signatures())
+    }
+
+    pub fn witness(&self) -> Witness<R> {
+        Witness::new(
+            self.witnesses
+                .iter()
+                .flat_map(|w| {
+                    let m = w.v.len();
+                    let sig = Vector::from_vec(
+                        [
+                            w.signature.0.as_slice(),
+                            w.signature.1.as_slice(),
+                            &[w.signature_slack],
+                        ]
+                        .concat(),
+                    );
+                    let quot = Vector::from_vec(
+                        [
+                            w.v.as_slice(),
+                            &[w.quotient_slack],
+                            vec![R::ZERO; m].as_slice(),
+                        ]
+                        .concat(),
+                    );
+                    let sig_conj = R::apply_automorphism_vec(&sig);
+                    let quot_conj = R::apply_automorphism_vec(&quot);
+                    [sig, sig_conj, quot, quot_conj]
+                })
+                .collect(),
+        ) // see FalconAggregationCRS::{signature, signature_conj, quotient, quotient_conj}_idx for the layout
+    }
+
+    pub fn generate_falcon_verification_principal_relation(
+        &self,
+        crs: &FalconAggregationCRS<R>,
+        transcript: &FalconTranscript<R>,
+    ) -> (Index<R>, Instance<R>) {
+        assert_eq!(crs.params, self.params);
+        assert_eq!(crs.num_signatures, self.num_signatures());
+        let (index, instance) = reduce(
+            crs,
+            &self.public_key,
+            &self.messages,
+            self.is_same_pk,
+            transcript,
+        );
+        debug_assert!(
+            PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &self.witness()).is_ok()
+        );
+        (index, instance)
+    }
+}
+
+/// An aggregate of Falcon signatures: their salts, which the verifier needs to recompute the hashed messages, and a LaBRADOR proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateProof {
+    pub salts: Vec<[u8; SALT_LEN]>,
+    pub transcript: Vec<u8>,
+}
+
+pub fn falcon_aggregation_crs<R: PolyRing>(
+    params: FalconParameters,
+    num_signatures: usize,
+) -> FalconAggregationCRS<R> {
+    FalconAggregationCRS::new(params, num_signatures)
+}
+
+pub fn falcon_aggregation_iopattern<R: PolyRing>(crs: &FalconAggregationCRS<R>) -> IOPattern
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    let l = crs.num_repetitions;
+    IOPattern::new("falcon_aggregation")
+        .absorb_vector::<R>(crs.A.nrows(), "prover message 1 (t)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(crs.eps_len(), l, "verifier message 1 (eps)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(
+            2 * crs.num_signatures,
+            l,
+            "verifier message 1 (rho)",
+        )
+        .ratchet()
+        .labrador_recursive_io(&crs.core_crs)
+}
+
+/// Aggregate the Falcon signatures `sigs[i]` on `msgs[i]` under `pks[i]`, or all under `pks[0]` if a single public key is given.
+/// Fails if any signature does not verify, or if the CRS is not for this number of signatures.
+pub fn aggregate_falcon_signatures<R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    params: FalconParameters,
+    pks: &[FalconPublicKey],
+    msgs: &[&[u8]],
+    sigs: &[FalconSignature],
+) -> Result<AggregateProof, FalconError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    check_lengths(crs.num_signatures, pks.len(), msgs.len(), sigs.len())?;
+
+    let mut aggregator = FalconAggregator::<R>::empty(params, pks.len() == 1);
+    for i in 0..sigs.len() {
+        let pk = if pks.len() == 1 { &pks[0] } else { &pks[i] };
+        aggregator.add_signature(pk, msgs[i], &sigs[i])?;
+    }
+
+    let io = falcon_aggregation_iopattern(crs);
+    let mut merlin = io.to_merlin();
+    let transcript = prove_falcon_aggregation(crs, &mut merlin, &aggregator)?;
+
+    Ok(AggregateProof {
+        salts: sigs.iter().map(|sig| sig.salt).collect(),
+        transcript: transcript.to_vec(),
+    })
+}
+
+/// Verify an aggregate of Falcon signatures on `msgs[i]` under `pks[i]`, or all under `pks[0]` if a single public key is given.
+pub fn verify_aggregate_falcon_signatures<R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    params: FalconParameters,
+    pks: &[FalconPublicKey],
+    msgs: &[&[u8]],
+    proof: &AggregateProof,
+) -> Result<(), FalconError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    check_dimension::<R>(params)?;
+    check_lengths(crs.num_signatures, pks.len(), msgs.len(), proof.salts.len())?;
+
+    let messages = msgs
+        .iter()
+        .zip(proof.salts.iter())
+        .map(|(msg, salt)| split::<R>(&centered_hash(params, salt, msg)))
+        .collect::<Vec<_>>();
+    for pk in pks {
+        if pk.h.len() != params.n() {
+            return Err(FalconError::InvalidLength {
+                what: "public key",
+                expected: params.n(),
+                found: pk.h.len(),
+            });
+        }
+    }
+    let public_key = pks.iter().map(public_key_to_ring).collect::<Vec<_>>();
+
+    let io = falcon_aggregation_iopattern(crs);
+    let mut arthur = io.to_arthur(&proof.transcript);
+    verify_falcon_aggregation(&mut arthur, crs, &public_key, &messages, pks.len() == 1)?;
+    Ok(())
+}
+
+fn check_dimension<R: PolyRing>(params: FalconParameters) -> Result<(), FalconError> {
+    let d = R::dimension();
+    if d < 4 || d > params.n() || params.n() % d != 0 {
+        return Err(FalconError::UnsupportedRingDimension {
+            dimension: d,
+            n: params.n(),
+        });
+    }
+    Ok(())
+}
+
+fn check_lengths(
+    num_signatures: usize,
+    num_pks: usize,
+    num_msgs: usize,
+    num_sigs: usize,
+) -> Result<(), FalconError> {
+    if num_pks != 1 && num_pks != num_signatures {
+        return Err(FalconError::InvalidLength {
+            what: "public keys",
+            expected: num_signatures,
+            found: num_pks,
+        });
+    }
+    for (what, len) in [("messages", num_msgs), ("signatures", num_sigs)] {
+        if len != num_signatures {
+            return Err(FalconError::InvalidLength {
+                what,
+                expected: num_signatures,
+                found: len,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn centered_public_key(pk: &FalconPublicKey) -> Vec<i64> {
+    pk.h.iter()
+        .map(|h_i| util::center_mod_q(*h_i as i64))
+        .collect()
+}
+
+fn centered_hash(params: FalconParameters, salt: &[u8], message: &[u8]) -> Vec<i64> {
+    hash_to_point(salt, message, params.n())
+        .into_iter()
+        .map(|c_i| util::center_mod_q(c_i as i64))
+        .collect()
+}
+
+fn public_key_to_ring<R: PolyRing>(pk: &FalconPublicKey) -> Vec<R> {
+    split(&centered_public_key(pk))
+}
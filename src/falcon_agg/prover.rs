@@ -0,0 +1,60 @@
+use nimue::{Merlin, ProofResult};
+use tracing::instrument;
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::nimue::merlin::SerMerlin;
+use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use relations::principal_relation::{Index, Instance, Witness};
+
+use crate::falcon_agg::reduction::{commit, FalconAggregationCRS, FalconTranscript};
+use crate::falcon_agg::FalconAggregator;
+use crate::prover::prove_principal_relation;
+
+#[instrument(name = "Falcon -> PR", level = "info", skip(crs, merlin, aggregator))]
+pub fn prove_reduction_falcon_labradorpr<R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    merlin: &mut Merlin,
+    aggregator: &FalconAggregator<R>,
+) -> ProofResult<(Index<R>, Instance<R>, Witness<R>)> {
+    let witness = aggregator.witness();
+    let t = commit(crs, &witness);
+
+    merlin.absorb_vector(&t)?;
+
+    let eps =
+        merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(crs.eps_len(), crs.num_repetitions)?;
+    let rho = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(
+        2 * crs.num_signatures,
+        crs.num_repetitions,
+    )?;
+
+    let transcript = FalconTranscript { t, eps, rho };
+    let (index, instance) =
+        aggregator.generate_falcon_verification_principal_relation(crs, &transcript);
+
+    Ok((index, instance, witness))
+}
+
+pub fn prove_falcon_aggregation<'a, R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    merlin: &'a mut Merlin,
+    aggregator: &FalconAggregator<R>,
+) -> ProofResult<&'a [u8]>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    let (index, instance, witness) = prove_reduction_falcon_labradorpr(crs, merlin, aggregator)?;
+
+    merlin.ratchet()?;
+
+    prove_principal_relation(merlin, &crs.core_crs, &index, &instance, &witness)
+}
@@ -0,0 +1,292 @@
+#![allow(non_snake_case)]
+
+use ark_std::rand;
+use num_traits::{ToPrimitive, Zero};
+
+use lattice_estimator::msis::{msis_h_128_l2, MSIS};
+use lattice_estimator::norms::Norm;
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
+use lattirust_arithmetic::ring::PolyRing;
+use relations::principal_relation::{Index, Instance, Size, Witness};
+
+use crate::common_reference_string::CommonReferenceString;
+use crate::falcon_agg::subring::{mul_matrix, num_pieces};
+use crate::falcon_agg::{FalconParameters, FALCON_RING_MODULUS};
+use crate::r1cs::util::{embed_Zqlinear_Rqlinear, encode};
+use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+use crate::util::{concat, smallest_prime_factor_bound};
+
+const SECURITY_PARAMETER: usize = 128;
+
+/// Number of witness vectors per signature: (s_1, s_2, y) and (v, z, 0), where y and z are slack for the norm bounds, and their conjugates
+const WITNESSES_PER_SIGNATURE: usize = 4;
+
+/// Public parameters for aggregating Falcon signatures.
+/// The prover commits to the witness before the verifier sends the challenges that enforce the norm bound of each signature, see `reduce`.
+#[derive(Clone, Debug)]
+pub struct FalconAggregationCRS<R: PolyRing> {
+    pub params: FalconParameters,
+    pub num_signatures: usize,
+    /// Number of elements of R that each polynomial of the Falcon ring is split into, see `subring`
+    pub num_pieces: usize,
+    /// Commitment matrix for the witness vectors
+    pub A: Matrix<R>,
+    /// Number of parallel repetitions of each check, each of which has soundness error 1/p for the smallest prime factor p of q
+    pub num_repetitions: usize,
+    pub core_crs: CommonReferenceString<R>,
+}
+
+impl<R: PolyRing> FalconAggregationCRS<R> {
+    pub fn new(params: FalconParameters, num_signatures: usize) -> Self {
+        let d = R::dimension();
+        assert!(
+            d >= 4,
+            "the slack for the norm bounds needs 4 coefficients, but R has dimension {d}"
+        );
+        let num_pieces = num_pieces::<R>(params.n());
+        let q = R::modulus();
+        let log2_p_min = (smallest_prime_factor_bound(&q) as f64).log2().floor() as usize;
+        let num_repetitions = SECURITY_PARAMETER.div_ceil(log2_p_min);
+
+        let size = Self::size(params, num_signatures, num_pieces, 0, num_repetitions);
+        let num_variables = size.num_witnesses * size.witness_len;
+
+        // Ensure MSIS_{d, q, 2 beta, m} is hard for the l_2 norm, where beta is the norm bound on the whole witness
+        let msis = MSIS {
+            h: 0, // dummy value, will be set later
+            d,
+            q: q.clone(),
+            length_bound: 2. * size.norm_bound_sq.sqrt(),
+            w: num_variables,
+            norm: Norm::L2,
+        };
+        let h = msis_h_128_l2(&msis).unwrap();
+
+        let rng = &mut rand::thread_rng();
+        let A = Matrix::<R>::rand(h, num_variables, rng);
+        let core_crs = CommonReferenceString::<R>::new_for_size(Self::size(
+            params,
+            num_signatures,
+            num_pieces,
+            h,
+            num_repetitions,
+        ));
+        // The norm bound of each signature is a constant constraint, which holds modulo q. It only implies the bound over
+        // the integers if the squared norm of no witness accepted by the core protocol can wrap around q. Its norm bound
+        // is 2N(beta^2 + beta_v^2), inflated by the slack that the core protocol loses over its recursion.
+        assert!(
+            core_crs.norm_bound_squared < q.to_f64().unwrap(),
+            "the squared norm bound {} of the aggregated witness, including the slack of the recursion, must be smaller than q = {q}",
+            core_crs.norm_bound_squared
+        );
+        Self {
+            params,
+            num_signatures,
+            num_pieces,
+            A,
+            num_repetitions,
+            core_crs,
+        }
+    }
+
+    fn size(
+        params: FalconParameters,
+        num_signatures: usize,
+        num_pieces: usize,
+        commitment_size: usize,
+        num_repetitions: usize,
+    ) -> Size {
+        // The norms of all witness vectors are fixed by the constant constraints, see `reduce`
+        let norm_bound_sq = 2.
+            * num_signatures as f64
+            * (params.signature_bound_sq() + params.quotient_bound_sq()) as f64;
+        Size {
+            num_witnesses: WITNESSES_PER_SIGNATURE * num_signatures,
+            witness_len: 2 * num_pieces + 1,
+            norm_bound_sq,
+            num_constraints: num_signatures * num_pieces + commitment_size,
+            num_constant_constraints: 2 * num_repetitions,
+        }
+    }
+
+    pub fn pr_size(&self) -> Size {
+        Self::size(
+            self.params,
+            self.num_signatures,
+            self.num_pieces,
+            self.A.nrows(),
+            self.num_repetitions,
+        )
+    }
+
+    pub fn pr_index(&self) -> Index<R> {
+        Index::<R>::new(&self.pr_size())
+    }
+
+    /// Index of the witness vector (s_1, s_2, y) for the i-th signature
+    #[inline(always)]
+    pub(crate) fn signature_idx(&self, i: usize) -> usize {
+        WITNESSES_PER_SIGNATURE * i
+    }
+
+    #[inline(always)]
+    pub(crate) fn signature_conj_idx(&self, i: usize) -> usize {
+        WITNESSES_PER_SIGNATURE * i + 1
+    }
+
+    /// Index of the witness vector (v, z, 0) for the i-th signature
+    #[inline(always)]
+    pub(crate) fn quotient_idx(&self, i: usize) -> usize {
+        WITNESSES_PER_SIGNATURE * i + 2
+    }
+
+    #[inline(always)]
+    pub(crate) fn quotient_conj_idx(&self, i: usize) -> usize {
+        WITNESSES_PER_SIGNATURE * i + 3
+    }
+
+    /// Number of Z_q-elements in the challenges for the conjugation check of one repetition
+    pub(crate) fn eps_len(&self) -> usize {
+        2 * self.num_signatures * (2 * self.num_pieces + 1) * R::dimension()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FalconTranscript<R: PolyRing> {
+    /// Commitment to the witness vectors
+    pub t: Vector<R>,
+    /// Challenges for the check that the conjugated witness vectors are σ_{-1} of the original ones
+    pub eps: Vec<Vector<R::BaseRing>>,
+    /// Challenges for aggregating the norm constraints of all signatures
+    pub rho: Vec<Vector<R::BaseRing>>,
+}
+
+/// Commit to all witness vectors, which binds the prover before the challenges of the norm checks
+pub fn commit<R: PolyRing>(crs: &FalconAggregationCRS<R>, witness: &Witness<R>) -> Vector<R> {
+    let w = concat(
+        witness
+            .s
+            .iter()
+            .map(|s_i| s_i.as_slice())
+            .collect::<Vec<_>>()
+            .as_slice(),
+    );
+    &crs.A * &w
+}
+
+/// The principal relation for verifying one Falcon signature per pair of witness vectors (s_1, s_2, y) and (v, z, 0), where s_1, s_2 and v each consist of m pieces in R and (v, z, 0) is padded with zeros to length 2m + 1:
+/// - $s_1 + h s_2 + q v = c$ piece by piece, see `subring`, which is equivalent to $s_1 + h s_2 = c \bmod q$ as long as the witness is short enough for this not to wrap around mod the modulus of R,
+/// - $\|(s_1, s_2, y)\|_2^2 = \beta^2$ and $\|(v, z)\|_2^2 = \beta_v^2$, so that every signature and every quotient satisfies its own norm bound,
+/// - the commitment to all witness vectors opens to t.
+///
+/// The norm of a witness vector w is $ct(\langle w, \sigma_{-1}(w) \rangle)$, so the witness also contains the conjugate $\tilde{w}$ of every w.
+/// The verifier only checks $\tilde{w} = \sigma_{-1}(w)$ on random linear combinations, with challenges sampled after the commitment, and aggregates the norm constraints of all signatures in the same way.
+pub fn reduce<R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    public_key: &[Vec<R>],
+    messages: &[Vec<R>],
+    is_same_pk: bool,
+    transcript: &FalconTranscript<R>,
+) -> (Index<R>, Instance<R>) {
+    let num_signatures = crs.num_signatures;
+    let m = crs.num_pieces;
+    assert_eq!(messages.len(), num_signatures);
+    let size = crs.pr_size();
+    let n_pr = size.witness_len;
+    let d = R::dimension();
+    let (t, eps, rho) = (&transcript.t, &transcript.eps, &transcript.rho);
+
+    // See FalconAggregationCRS::{signature, signature_conj, quotient, quotient_conj}_idx for the layout
+    let mut builder = RelationBuilder::<R>::new(n_pr, size.norm_bound_sq);
+    let vars = (0..num_signatures)
+        .map(|i| {
+            [
+                builder.witness(&format!("(s_1, s_2, y)_{i}")),
+                builder.witness(&format!("(s_1, s_2, y)~_{i}")),
+                builder.witness(&format!("(v, z, 0)_{i}")),
+                builder.witness(&format!("(v, z, 0)~_{i}")),
+            ]
+        })
+        .collect::<Vec<_>>();
+    debug_assert!(
+        (0..num_signatures).all(|i| vars[i][0].index() == crs.signature_idx(i)
+            && vars[i][1].index() == crs.signature_conj_idx(i)
+            && vars[i][2].index() == crs.quotient_idx(i)
+            && vars[i][3].index() == crs.quotient_conj_idx(i))
+    );
+
+    // (s_1)_l + sum_k H_{l,k} (s_2)_k + q v_l = c_l for every piece l, where H is the matrix of multiplication by h
+    let falcon_ring_modulus = R::try_from(FALCON_RING_MODULUS).unwrap();
+    let pk_mul_matrices = public_key
+        .iter()
+        .map(|pk| mul_matrix(pk))
+        .collect::<Vec<_>>();
+    for (i, [sig, _, quot, _]) in vars.iter().enumerate() {
+        let pk_mul = if is_same_pk {
+            &pk_mul_matrices[0]
+        } else {
+            &pk_mul_matrices[i]
+        };
+        assert_eq!(messages[i].len(), m);
+        for l in 0..m {
+            let mut phi_sig = Vector::<R>::zeros(n_pr);
+            phi_sig[l] = R::ONE;
+            phi_sig.as_mut_slice()[m..2 * m].copy_from_slice(&pk_mul[l]);
+            let mut phi_quot = Vector::<R>::zeros(n_pr);
+            phi_quot[l] = falcon_ring_modulus;
+            builder.constrain(
+                QuadraticExpr::linear(phi_sig, *sig) + QuadraticExpr::linear(phi_quot, *quot),
+                messages[i][l],
+            );
+        }
+    }
+
+    // A * (w_1 || ... || w_r) = t
+    for i in 0..t.len() {
+        let expr = crs
+            .A
+            .row(i)
+            .transpose()
+            .as_slice()
+            .chunks(n_pr)
+            .zip(vars.iter().flatten())
+            .fold(QuadraticExpr::zero(), |expr, (A_iw, w)| {
+                expr + QuadraticExpr::linear(Vector::<R>::from_slice(A_iw), *w)
+            });
+        builder.constrain(expr, t[i]);
+    }
+
+    // sum_w <eps_w, w~> - <eps_w, σ_{-1}(w)> = 0, which ensures that w~ = σ_{-1}(w) for every witness vector w
+    let conjugate_pairs = vars
+        .iter()
+        .flat_map(|[sig, sig_conj, quot, quot_conj]| [(*sig, *sig_conj), (*quot, *quot_conj)])
+        .collect::<Vec<_>>();
+    for eps_l in eps {
+        let mut expr = QuadraticExpr::zero();
+        for (p, (w, w_conj)) in conjugate_pairs.iter().enumerate() {
+            let eps_w = &eps_l.as_slice()[p * n_pr * d..(p + 1) * n_pr * d];
+            // ct(<σ_{-1}(e), w~>) = <eps_w, w~> and ct(<e, w>) = ct(<σ_{-1}(e), σ_{-1}(w)>) = <eps_w, σ_{-1}(w)>, where e = encode(eps_w)
+            expr = expr + QuadraticExpr::linear(embed_Zqlinear_Rqlinear::<R>(eps_w), *w_conj)
+                - QuadraticExpr::linear(encode::<R>(eps_w), *w);
+        }
+        builder.constrain_ct(expr, R::BaseRing::zero());
+    }
+
+    // sum_i rho_i (ct(<a_i, a~_i>) - beta^2) + rho'_i (ct(<b_i, b~_i>) - beta_v^2) = 0, where a_i = (s_1, s_2, y) and b_i = (v, z, 0), since the embedding of the Falcon ring is an isometry
+    let beta_sq = R::BaseRing::try_from(crs.params.signature_bound_sq() as u128).unwrap();
+    let beta_v_sq = R::BaseRing::try_from(crs.params.quotient_bound_sq() as u128).unwrap();
+    for rho_l in rho {
+        let mut expr = QuadraticExpr::zero();
+        let mut rhs = R::BaseRing::zero();
+        for (i, [sig, sig_conj, quot, quot_conj]) in vars.iter().enumerate() {
+            let (rho_sig, rho_quot) = (rho_l[2 * i], rho_l[2 * i + 1]);
+            expr = expr
+                + QuadraticExpr::inner(*sig, *sig_conj) * R::from_scalar(rho_sig)
+                + QuadraticExpr::inner(*quot, *quot_conj) * R::from_scalar(rho_quot);
+            rhs += rho_sig * beta_sq + rho_quot * beta_v_sq;
+        }
+        builder.constrain_ct(expr, rhs);
+    }
+
+    builder.build()
+}
@@ -0,0 +1,43 @@
+//! Embedding of the Falcon ring $\mathbb{Z}[X]/(X^n + 1)$ into $m = n/d$ elements of the proving ring $R = \mathbb{Z}[Y]/(Y^d + 1)$.
+//!
+//! Every $f \in \mathbb{Z}[X]/(X^n + 1)$ can be written uniquely as $f = \sum_{j < m} X^j f_j(X^m)$, and $Y \mapsto X^m$ is a ring isomorphism from R to a subring of $\mathbb{Z}[X]/(X^n + 1)$, since $X^{md} = X^n = -1$.
+//! The pieces of a product are R-linear in the pieces of each factor: $(hf)_l = \sum_{k \leq l} h_{l-k} f_k + Y \sum_{k > l} h_{l-k+m} f_k$.
+//! Finally, the embedding is an isometry, $\|f\|_2^2 = \sum_j \|f_j\|_2^2$, so norm bounds on f carry over to its pieces.
+
+use lattirust_arithmetic::ring::PolyRing;
+
+use crate::util::to_ring;
+
+/// Number of elements of R that a polynomial of degree n is split into
+pub fn num_pieces<R: PolyRing>(n: usize) -> usize {
+    let d = R::dimension();
+    assert_eq!(
+        n % d,
+        0,
+        "ring dimension {d} does not divide the degree {n} of the Falcon ring"
+    );
+    n / d
+}
+
+/// Split f into its pieces $f_0, \ldots, f_{m-1}$, where the i-th coefficient of $f_j$ is the $(j + mi)$-th coefficient of f
+pub fn split<R: PolyRing>(f: &[i64]) -> Vec<R> {
+    let m = num_pieces::<R>(f.len());
+    (0..m)
+        .map(|j| to_ring(&f.iter().skip(j).step_by(m).copied().collect::<Vec<_>>()))
+        .collect()
+}
+
+/// The m x m matrix over R of multiplication by h on the pieces, such that $(hf)_l = \sum_k M_{l,k} f_k$
+pub fn mul_matrix<R: PolyRing>(h: &[R]) -> Vec<Vec<R>> {
+    let m = h.len();
+    let mut y = vec![0i64; R::dimension()];
+    y[1] = 1;
+    let y = to_ring::<R>(&y);
+    (0..m)
+        .map(|l| {
+            (0..m)
+                .map(|k| if k <= l { h[l - k] } else { y * h[l + m - k] })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,622 @@
+use ark_std::rand::{thread_rng, Rng};
+
+use nimue::IOPattern;
+
+use lattirust_arithmetic::nimue::iopattern::{SerIOPattern, SqueezeFromRandomBytes};
+use lattirust_arithmetic::nimue::merlin::SerMerlin;
+use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
+use lattirust_arithmetic::ring::ntt::ntt_prime;
+use lattirust_arithmetic::ring::{
+    PolyRing, Pow2CyclotomicPolyRing, Pow2CyclotomicPolyRingNTT, Zq1, Zq2,
+};
+use relations::principal_relation::{PrincipalRelation, Witness};
+use relations::Relation;
+
+use crate::util::to_ring;
+
+use super::aggregate_falcon_signatures;
+use super::reduction::{commit, reduce, FalconAggregationCRS, FalconTranscript};
+use super::subring::{mul_matrix, split};
+use super::util::{
+    center_mod_q, four_squares, hash_to_point, l2_norm_sq, negacyclic_mul, verification_witness,
+    FalconWitnessCoeffs,
+};
+use super::{
+    centered_hash, centered_public_key, falcon_aggregation_crs, public_key_to_ring, slack,
+    verify_aggregate_falcon_signatures, FalconAggregator, FalconError, FalconParameters,
+    FalconPublicKey, FalconSignature, FalconVerificationWitness, FALCON_RING_MODULUS, SALT_LEN,
+};
+
+const NUM_SIGS: usize = 8;
+const Q1: u64 = 274177;
+const Q2: u64 = 67280421310721;
+pub type Z64 = Zq2<Q1, Q2>;
+const D: usize = 64;
+
+type R = Pow2CyclotomicPolyRingNTT<Z64, D>;
+
+const Q: i64 = FALCON_RING_MODULUS as i64;
+
+/// Message signed in the test vectors in `testdata`, which were produced by the reference implementation of Falcon
+const TEST_VECTOR_MESSAGE: &[u8] = b"LaBRADOR aggregates Falcon signatures";
+const TEST_VECTORS: [(FalconParameters, &str); 2] = [
+    (
+        FalconParameters::Falcon512,
+        include_str!("testdata/falcon512.hex"),
+    ),
+    (
+        FalconParameters::Falcon1024,
+        include_str!("testdata/falcon1024.hex"),
+    ),
+];
+
+fn decode_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The encoded public key and signature of a test vector
+fn test_vector(hex: &str) -> (Vec<u8>, Vec<u8>) {
+    let mut lines = hex.lines();
+    let pk = decode_hex(lines.next().unwrap());
+    let sig = decode_hex(lines.next().unwrap());
+    (pk, sig)
+}
+
+fn pow_mod(mut b: i64, mut e: i64) -> i64 {
+    let mut res = 1;
+    b = b.rem_euclid(Q);
+    while e > 0 {
+        if e & 1 == 1 {
+            res = res * b % Q;
+        }
+        b = b * b % Q;
+        e >>= 1;
+    }
+    res
+}
+
+/// Evaluate a at the n primitive 2n-th roots of unity mod q, or interpolate if `inverse` is set
+fn negacyclic_transform(a: &[i64], inverse: bool) -> Vec<i64> {
+    let n = a.len() as i64;
+    // 11 generates Z_q^*, so psi is a primitive 2n-th root of unity
+    let psi = pow_mod(11, (Q - 1) / (2 * n));
+    let (psi, scale) = if inverse {
+        (pow_mod(psi, Q - 2), pow_mod(n, Q - 2))
+    } else {
+        (psi, 1)
+    };
+    (0..n)
+        .map(|k| {
+            // forward: A_k = sum_j a_j psi^((2k+1)j), inverse: a_k = n^-1 sum_j A_j psi^-((2j+1)k)
+            let mut res = 0;
+            for (j, a_j) in a.iter().enumerate() {
+                let j = j as i64;
+                let e = if inverse {
+                    (2 * j + 1) * k
+                } else {
+                    (2 * k + 1) * j
+                };
+                res = (res + a_j.rem_euclid(Q) * pow_mod(psi, e)) % Q;
+            }
+            res * scale % Q
+        })
+        .collect()
+}
+
+/// Sample a key and signature that pass Falcon verification: pick short (s_1, s_2) and set h = (c - s_1) / s_2 mod q
+fn synthetic_signature(
+    params: FalconParameters,
+    message: &[u8],
+) -> (FalconPublicKey, FalconSignature) {
+    let rng = &mut thread_rng();
+    let n = params.n();
+    loop {
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt[..]);
+        let s1 = (0..n)
+            .map(|_| rng.gen_range(-100..=100))
+            .collect::<Vec<i64>>();
+        let s2 = (0..n)
+            .map(|_| rng.gen_range(-100..=100))
+            .collect::<Vec<i64>>();
+        let c = hash_to_point(&salt, message, n)
+            .into_iter()
+            .map(|c_i| c_i as i64)
+            .collect::<Vec<_>>();
+
+        let s2_hat = negacyclic_transform(&s2, false);
+        if s2_hat.iter().any(|x| *x == 0) {
+            continue; // s2 is not invertible
+        }
+        let c_min_s1_hat = negacyclic_transform(
+            &c.iter()
+                .zip(s1.iter())
+                .map(|(c_i, s1_i)| c_i - s1_i)
+                .collect::<Vec<_>>(),
+            false,
+        );
+        let h_hat = c_min_s1_hat
+            .iter()
+            .zip(s2_hat.iter())
+            .map(|(a, b)| a * pow_mod(*b, Q - 2) % Q)
+            .collect::<Vec<_>>();
+        let h = negacyclic_transform(&h_hat, true);
+
+        return (
+            FalconPublicKey {
+                h: h.into_iter().map(|h_i| h_i.rem_euclid(Q) as u16).collect(),
+            },
+            FalconSignature {
+                salt,
+                s2: s2.into_iter().map(|s| s as i16).collect(),
+            },
+        );
+    }
+}
+
+#[test]
+fn test_hash_to_point() {
+    let salt = (0..SALT_LEN as u8).collect::<Vec<_>>();
+    let c = hash_to_point(&salt, b"labrador", 512);
+    assert_eq!(c.len(), 512);
+    assert_eq!(&c[..8], &[11705, 4088, 7254, 9958, 1272, 9223, 4300, 634]);
+    assert_eq!(&c[508..], &[4087, 9300, 9169, 2703]);
+    assert!(c.iter().all(|c_i| (*c_i as u32) < FALCON_RING_MODULUS));
+}
+
+#[test]
+fn test_negacyclic_mul() {
+    let n = 8;
+    let mut x = vec![0i64; n];
+    x[1] = 1;
+    let mut x_n_1 = vec![0i64; n];
+    x_n_1[n - 1] = 3;
+    let mut expected = vec![0i64; n];
+    expected[0] = -3;
+    assert_eq!(negacyclic_mul(&x, &x_n_1), expected);
+
+    let a = (0..n as i64).collect::<Vec<_>>();
+    let a_hat = negacyclic_transform(&a, false);
+    assert_eq!(negacyclic_transform(&a_hat, true), a);
+}
+
+#[test]
+fn test_verification_witness() {
+    for params in [FalconParameters::Falcon512, FalconParameters::Falcon1024] {
+        let (pk, sig) = synthetic_signature(params, b"message");
+        let h =
+            pk.h.iter()
+                .map(|h_i| center_mod_q(*h_i as i64))
+                .collect::<Vec<_>>();
+        let c = hash_to_point(&sig.salt, b"message", params.n())
+            .into_iter()
+            .map(|c_i| center_mod_q(c_i as i64))
+            .collect::<Vec<_>>();
+        let s2 = sig.s2.iter().map(|s| *s as i64).collect::<Vec<_>>();
+
+        let w = verification_witness(params, &h, &c, &s2).unwrap();
+        // s1 + h*s2 + q*v = c over the integers
+        let h_s2 = negacyclic_mul(&h, &w.s2);
+        for i in 0..params.n() {
+            assert_eq!(w.s1[i] + h_s2[i] + Q * w.v[i], c[i]);
+        }
+        assert!(l2_norm_sq(&w.v) <= params.quotient_bound_sq());
+
+        // The same signature on another message does not verify
+        let c_other = hash_to_point(&sig.salt, b"other message", params.n())
+            .into_iter()
+            .map(|c_i| center_mod_q(c_i as i64))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            verification_witness(params, &h, &c_other, &s2),
+            Err(FalconError::SignatureTooLong { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_decode_test_vectors() {
+    for (params, hex) in TEST_VECTORS {
+        let (pk_bytes, sig_bytes) = test_vector(hex);
+        assert_eq!(pk_bytes.len(), 1 + params.n() * 14 / 8);
+
+        let (pk_params, pk) = FalconPublicKey::decode(&pk_bytes).unwrap();
+        let (sig_params, sig) = FalconSignature::decode(&sig_bytes).unwrap();
+        assert_eq!(pk_params, params);
+        assert_eq!(sig_params, params);
+        assert_eq!(pk.encode().unwrap(), pk_bytes);
+        assert_eq!(sig.encode().unwrap(), sig_bytes);
+
+        let h =
+            pk.h.iter()
+                .map(|h_i| center_mod_q(*h_i as i64))
+                .collect::<Vec<_>>();
+        let c = hash_to_point(&sig.salt, TEST_VECTOR_MESSAGE, params.n())
+            .into_iter()
+            .map(|c_i| center_mod_q(c_i as i64))
+            .collect::<Vec<_>>();
+        let s2 = sig.s2.iter().map(|s| *s as i64).collect::<Vec<_>>();
+        let w = verification_witness(params, &h, &c, &s2).unwrap();
+
+        // Each Falcon polynomial is split into n / 64 pieces of R
+        let (witness, c_pieces) = FalconVerificationWitness::<R>::from_encoded(
+            &pk_bytes,
+            TEST_VECTOR_MESSAGE,
+            &sig_bytes,
+        )
+        .unwrap();
+        let expected = FalconVerificationWitness::<R>::from_coeffs(params, &w).unwrap();
+        assert_eq!(witness.signature, expected.signature);
+        assert_eq!(witness.v, expected.v);
+        assert_eq!(witness.signature_slack, expected.signature_slack);
+        assert_eq!(witness.quotient_slack, expected.quotient_slack);
+        assert_eq!(c_pieces, split::<R>(&c));
+        assert_eq!(c_pieces.len(), params.n() / D);
+    }
+
+    type R512 = Pow2CyclotomicPolyRing<Z64, 512>;
+    let (pk_bytes, sig_bytes) = test_vector(TEST_VECTORS[0].1);
+    assert!(FalconVerificationWitness::<R512>::from_encoded(
+        &pk_bytes,
+        TEST_VECTOR_MESSAGE,
+        &sig_bytes
+    )
+    .is_ok());
+    assert!(matches!(
+        FalconVerificationWitness::<R512>::from_encoded(&pk_bytes, b"forged", &sig_bytes),
+        Err(FalconError::SignatureTooLong { .. })
+    ));
+    let (_, sig_bytes_1024) = test_vector(TEST_VECTORS[1].1);
+    assert!(matches!(
+        FalconVerificationWitness::<R512>::from_encoded(
+            &pk_bytes,
+            TEST_VECTOR_MESSAGE,
+            &sig_bytes_1024
+        ),
+        Err(FalconError::ParameterMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_decode_rejects_malformed_encodings() {
+    let (pk_bytes, sig_bytes) = test_vector(TEST_VECTORS[0].1);
+
+    // Wrong header, truncated, and unreduced coefficients in the public key
+    let mut pk = pk_bytes.clone();
+    pk[0] = 0x30 | 9;
+    assert!(FalconPublicKey::decode(&pk).is_err());
+    assert!(FalconPublicKey::decode(&pk_bytes[..pk_bytes.len() - 1]).is_err());
+    let mut pk = pk_bytes.clone();
+    pk[1] = 0xff;
+    pk[2] |= 0xfc;
+    assert!(matches!(
+        FalconPublicKey::decode(&pk),
+        Err(FalconError::InvalidEncoding(_))
+    ));
+
+    // Zero padding after the signature is accepted, anything else is not
+    let (_, sig) = FalconSignature::decode(&sig_bytes).unwrap();
+    let mut padded = sig_bytes.clone();
+    padded.extend([0u8; 8]);
+    assert_eq!(FalconSignature::decode(&padded).unwrap().1, sig);
+    padded.push(1);
+    assert!(FalconSignature::decode(&padded).is_err());
+    assert!(FalconSignature::decode(&sig_bytes[..sig_bytes.len() - 4]).is_err());
+
+    // -0 is rejected, so that every s_2 has a single encoding
+    let mut sig_bytes = FalconSignature {
+        salt: [0u8; SALT_LEN],
+        s2: vec![0; 512],
+    }
+    .encode()
+    .unwrap();
+    assert_eq!(
+        FalconSignature::decode(&sig_bytes).unwrap().0,
+        FalconParameters::Falcon512
+    );
+    sig_bytes[1 + SALT_LEN] |= 0x80;
+    assert!(matches!(
+        FalconSignature::decode(&sig_bytes),
+        Err(FalconError::InvalidEncoding(_))
+    ));
+
+    // Coefficients above 2047 cannot be encoded
+    let mut s2 = vec![0; 512];
+    s2[0] = -2048;
+    assert!(FalconSignature {
+        salt: [0u8; SALT_LEN],
+        s2,
+    }
+    .encode()
+    .is_err());
+}
+
+#[test]
+fn test_four_squares() {
+    for m in (0..1000).chain([
+        FalconParameters::Falcon512.signature_bound_sq(),
+        FalconParameters::Falcon1024.quotient_bound_sq(),
+        (1 << 40) + 7,
+    ]) {
+        let squares = four_squares(m);
+        assert_eq!(
+            squares.iter().map(|x| (x * x) as u64).sum::<u64>(),
+            m,
+            "{squares:?} is not a four-square decomposition of {m}"
+        );
+    }
+}
+
+/// Compute the commitment and the challenges of the aggregation for a witness, without the bounds on R that the full protocol needs
+fn falcon_transcript<R: PolyRing>(
+    crs: &FalconAggregationCRS<R>,
+    witness: &Witness<R>,
+) -> FalconTranscript<R> {
+    let l = crs.num_repetitions;
+    let io = IOPattern::new("falcon_aggregation_test")
+        .absorb_vector::<R>(crs.A.nrows(), "t")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(crs.eps_len(), l, "eps")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(2 * crs.num_signatures, l, "rho");
+    let mut merlin = io.to_merlin();
+    let t = commit(crs, witness);
+    merlin.absorb_vector(&t).unwrap();
+    let eps = merlin
+        .challenge_vectors::<R::BaseRing, R::BaseRing>(crs.eps_len(), l)
+        .unwrap();
+    let rho = merlin
+        .challenge_vectors::<R::BaseRing, R::BaseRing>(2 * crs.num_signatures, l)
+        .unwrap();
+    FalconTranscript { t, eps, rho }
+}
+
+/// Integer witnesses for synthetic signatures on distinct messages, with the hashed messages
+fn synthetic_witnesses(
+    params: FalconParameters,
+    num_signatures: usize,
+) -> (
+    Vec<FalconPublicKey>,
+    Vec<FalconWitnessCoeffs>,
+    Vec<Vec<i64>>,
+) {
+    let mut pks = vec![];
+    let mut coeffs = vec![];
+    let mut messages = vec![];
+    for i in 0..num_signatures {
+        let msg = format!("message {i}").into_bytes();
+        let (pk, sig) = synthetic_signature(params, &msg);
+        let c = centered_hash(params, &sig.salt, &msg);
+        let s2 = sig.s2.iter().map(|s| *s as i64).collect::<Vec<_>>();
+        coeffs.push(verification_witness(params, &centered_public_key(&pk), &c, &s2).unwrap());
+        pks.push(pk);
+        messages.push(c);
+    }
+    (pks, coeffs, messages)
+}
+
+#[test]
+fn test_subring_embedding() {
+    let rng = &mut thread_rng();
+    let n = FalconParameters::Falcon512.n();
+    let h = (0..n)
+        .map(|_| rng.gen_range(-(Q / 2)..=Q / 2))
+        .collect::<Vec<i64>>();
+    let f = (0..n)
+        .map(|_| rng.gen_range(-100..=100))
+        .collect::<Vec<i64>>();
+
+    let h_pieces = split::<R>(&h);
+    let f_pieces = split::<R>(&f);
+    assert_eq!(f_pieces.len(), n / D);
+    assert_eq!(
+        f_pieces[1],
+        to_ring(&f.iter().skip(1).step_by(n / D).copied().collect::<Vec<_>>())
+    );
+
+    let h_mul = mul_matrix(&h_pieces);
+    let hf_pieces = (0..n / D)
+        .map(|l| {
+            h_mul[l]
+                .iter()
+                .zip(f_pieces.iter())
+                .map(|(h_lk, f_k)| *h_lk * *f_k)
+                .fold(R::ZERO, |acc, x| acc + x)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(hf_pieces, split::<R>(&negacyclic_mul(&h, &f)));
+}
+
+fn check_falcon_relation_is_satisfied<R: PolyRing>() {
+    let params = FalconParameters::Falcon512;
+    let num_signatures = 4;
+
+    let (pks, coeffs, messages) = synthetic_witnesses(params, num_signatures);
+    let aggregator = FalconAggregator::<R>::new(
+        params,
+        coeffs
+            .iter()
+            .map(|w| FalconVerificationWitness::<R>::from_coeffs(params, w).unwrap())
+            .collect(),
+        messages.iter().map(|c| split(c)).collect(),
+        false,
+        pks.iter().map(public_key_to_ring).collect(),
+    );
+    let crs = FalconAggregationCRS::<R>::new(params, num_signatures);
+    let witness = aggregator.witness();
+    let transcript = falcon_transcript(&crs, &witness);
+    let (index, instance) =
+        aggregator.generate_falcon_verification_principal_relation(&crs, &transcript);
+    assert!(PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &witness).is_ok());
+
+    // Swapping two signatures verifies them against the wrong messages
+    let mut s = witness.s.clone();
+    for (idx, other_idx) in [
+        (crs.signature_idx(0), crs.signature_idx(1)),
+        (crs.signature_conj_idx(0), crs.signature_conj_idx(1)),
+        (crs.quotient_idx(0), crs.quotient_idx(1)),
+        (crs.quotient_conj_idx(0), crs.quotient_conj_idx(1)),
+    ] {
+        s.swap(idx, other_idx);
+    }
+    let swapped = Witness::new(s);
+    let (index, instance) = reduce(
+        &crs,
+        &aggregator.public_key,
+        &aggregator.messages,
+        false,
+        &falcon_transcript(&crs, &swapped),
+    );
+    assert!(PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &swapped).is_err());
+}
+
+#[test]
+fn test_falcon_relation_is_satisfied() {
+    // One piece per Falcon polynomial
+    check_falcon_relation_is_satisfied::<Pow2CyclotomicPolyRing<Z64, 512>>();
+    // Eight pieces per Falcon polynomial
+    check_falcon_relation_is_satisfied::<R>();
+}
+
+#[test]
+fn test_falcon_relation_bounds_each_signature() {
+    let params = FalconParameters::Falcon512;
+    let num_signatures = 4;
+
+    let (pks, coeffs, messages) = synthetic_witnesses(params, num_signatures);
+    let mut witnesses = coeffs
+        .iter()
+        .map(|w| FalconVerificationWitness::<R>::from_coeffs(params, w).unwrap())
+        .collect::<Vec<_>>();
+
+    // s_1 + q and v - 1 still satisfy s_1 + h s_2 + q v = c, but the signature is too long
+    let mut forged = coeffs[0].clone();
+    forged.s1[0] += Q;
+    forged.v[0] -= 1;
+    let forged_norm_sq = l2_norm_sq(&forged.s1) + l2_norm_sq(&forged.s2);
+    assert!(forged_norm_sq > params.signature_bound_sq());
+    assert!(matches!(
+        FalconVerificationWitness::<R>::from_coeffs(params, &forged),
+        Err(FalconError::SignatureTooLong { .. })
+    ));
+    witnesses[0] = FalconVerificationWitness {
+        signature: (split(&forged.s1), split(&forged.s2)),
+        v: split(&forged.v),
+        signature_slack: R::ZERO,
+        quotient_slack: witnesses[0].quotient_slack,
+    };
+
+    // Take the excess norm from the slack of another quotient, so that the norm of the whole witness does not change
+    let excess = (forged_norm_sq - params.signature_bound_sq()) as i64
+        + l2_norm_sq(&forged.v) as i64
+        - l2_norm_sq(&coeffs[0].v) as i64;
+    assert!(excess > 0);
+    witnesses[1].quotient_slack =
+        slack(params.quotient_bound_sq() - l2_norm_sq(&coeffs[1].v) - excess as u64);
+
+    let aggregator = FalconAggregator::<R>::new(
+        params,
+        witnesses,
+        messages.iter().map(|c| split(c)).collect(),
+        false,
+        pks.iter().map(public_key_to_ring).collect(),
+    );
+    let crs = FalconAggregationCRS::<R>::new(params, num_signatures);
+    let witness = aggregator.witness();
+    let (index, instance) = reduce(
+        &crs,
+        &aggregator.public_key,
+        &aggregator.messages,
+        false,
+        &falcon_transcript(&crs, &witness),
+    );
+    assert!(PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &witness).is_err());
+}
+
+#[test]
+#[should_panic(expected = "must be smaller than q")]
+fn test_falcon_aggregation_crs_rejects_wrapping_norms() {
+    // A 40-bit q is large enough for the core protocol, but 2N(beta^2 + beta_v^2) with the slack of the recursion
+    // exceeds it, so the norm of a signature could wrap around q
+    type SmallR = Pow2CyclotomicPolyRingNTT<Zq1<{ ntt_prime::<64>(40) }>, D>;
+    let _ = FalconAggregationCRS::<SmallR>::new(FalconParameters::Falcon512, 1);
+}
+
+#[test]
+fn test_add_signature() {
+    let params = FalconParameters::Falcon512;
+    let (pk_bytes, sig_bytes) = test_vector(TEST_VECTORS[0].1);
+    let (_, pk) = FalconPublicKey::decode(&pk_bytes).unwrap();
+    let (_, sig) = FalconSignature::decode(&sig_bytes).unwrap();
+
+    let mut aggregator = FalconAggregator::<R>::empty(params, true);
+    aggregator
+        .add_signature(&pk, TEST_VECTOR_MESSAGE, &sig)
+        .unwrap()
+        .add_signature(&pk, TEST_VECTOR_MESSAGE, &sig)
+        .unwrap();
+    assert_eq!(aggregator.num_signatures(), 2);
+
+    let (other_pk, other_sig) = synthetic_signature(params, b"message");
+    assert!(matches!(
+        aggregator.add_signature(&other_pk, b"message", &other_sig),
+        Err(FalconError::PublicKeyMismatch { index: 2 })
+    ));
+    assert!(matches!(
+        aggregator.add_signature(&pk, b"message", &sig),
+        Err(FalconError::SignatureTooLong { .. })
+    ));
+    assert_eq!(aggregator.num_signatures(), 2);
+    assert_eq!(aggregator.crs().num_signatures, 2);
+
+    let mut aggregator = FalconAggregator::<R>::empty(params, false);
+    aggregator
+        .add_signature(&pk, TEST_VECTOR_MESSAGE, &sig)
+        .unwrap()
+        .add_signature(&other_pk, b"message", &other_sig)
+        .unwrap();
+    assert_eq!(aggregator.public_key.len(), 2);
+}
+
+#[test]
+fn test_falcon_aggregation_with_same_pk() {
+    let params = FalconParameters::Falcon512;
+
+    // The same signature from the reference implementation, NUM_SIGS times
+    let (pk_bytes, sig_bytes) = test_vector(TEST_VECTORS[0].1);
+    let (_, pk) = FalconPublicKey::decode(&pk_bytes).unwrap();
+    let (_, sig) = FalconSignature::decode(&sig_bytes).unwrap();
+    let msgs = [TEST_VECTOR_MESSAGE; NUM_SIGS];
+    let sigs = vec![sig; NUM_SIGS];
+
+    let crs = falcon_aggregation_crs::<R>(params, NUM_SIGS);
+
+    let proof = aggregate_falcon_signatures(&crs, params, &[pk.clone()], &msgs, &sigs).unwrap();
+
+    verify_aggregate_falcon_signatures(&crs, params, &[pk.clone()], &msgs, &proof).unwrap();
+
+    let mut other_msgs = msgs;
+    other_msgs[NUM_SIGS - 1] = b"another message";
+    assert!(verify_aggregate_falcon_signatures(&crs, params, &[pk], &other_msgs, &proof).is_err());
+}
+
+#[test]
+fn test_falcon_aggregation_with_distinct_pks() {
+    let params = FalconParameters::Falcon512;
+
+    let msgs = (0..NUM_SIGS)
+        .map(|i| format!("message {i}").into_bytes())
+        .collect::<Vec<_>>();
+    let msgs = msgs.iter().map(|m| m.as_slice()).collect::<Vec<_>>();
+    let (pks, sigs): (Vec<_>, Vec<_>) = msgs.iter().map(|m| synthetic_signature(params, m)).unzip();
+
+    let crs = falcon_aggregation_crs::<R>(params, NUM_SIGS);
+    let proof = aggregate_falcon_signatures(&crs, params, &pks, &msgs, &sigs).unwrap();
+    verify_aggregate_falcon_signatures(&crs, params, &pks, &msgs, &proof).unwrap();
+
+    // The proof is bound to the public keys
+    let mut other_pks = pks.clone();
+    other_pks.swap(0, 1);
+    assert!(verify_aggregate_falcon_signatures(&crs, params, &other_pks, &msgs, &proof).is_err());
+}
@@ -0,0 +1,168 @@
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+
+use lattirust_arithmetic::ring::PolyRing;
+
+use crate::falcon_agg::{FalconError, FalconParameters, FALCON_RING_MODULUS};
+use crate::util::to_ring;
+
+const Q: i64 = FALCON_RING_MODULUS as i64;
+
+/// HashToPoint from the Falcon specification (Algorithm 3): hash `salt || message` with SHAKE256 to a polynomial in $\mathbb{Z}_q[X]/(X^n + 1)$, with coefficients in $[0, q)$
+pub fn hash_to_point(salt: &[u8], message: &[u8], n: usize) -> Vec<u16> {
+    let mut shake = Shake256::default();
+    shake.update(salt);
+    shake.update(message);
+    let mut reader = shake.finalize_xof();
+
+    let mut c = Vec::<u16>::with_capacity(n);
+    let mut buf = [0u8; 2];
+    while c.len() < n {
+        reader.read(&mut buf);
+        let t = u16::from_be_bytes(buf) as u32;
+        // Rejection sampling, keeping the distribution of t mod q close to uniform
+        if t < 5 * FALCON_RING_MODULUS {
+            c.push((t % FALCON_RING_MODULUS) as u16);
+        }
+    }
+    c
+}
+
+/// Representative of x mod q in $(-q/2, q/2]$
+pub fn center_mod_q(x: i64) -> i64 {
+    let x = x.rem_euclid(Q);
+    if x > Q / 2 {
+        x - Q
+    } else {
+        x
+    }
+}
+
+/// Multiply two polynomials in $\mathbb{Z}[X]/(X^n + 1)$, without modular reduction of the coefficients
+pub fn negacyclic_mul(a: &[i64], b: &[i64]) -> Vec<i64> {
+    let n = a.len();
+    assert_eq!(b.len(), n);
+    let mut res = vec![0i64; n];
+    for (i, a_i) in a.iter().enumerate() {
+        if *a_i == 0 {
+            continue;
+        }
+        for (j, b_j) in b.iter().enumerate() {
+            if i + j < n {
+                res[i + j] += a_i * b_j;
+            } else {
+                res[i + j - n] -= a_i * b_j;
+            }
+        }
+    }
+    res
+}
+
+pub fn l2_norm_sq(v: &[i64]) -> u64 {
+    v.iter().map(|v_i| (v_i * v_i) as u64).sum()
+}
+
+/// Write m as a sum of four squares, which is always possible by Lagrange's four-square theorem
+pub fn four_squares(m: u64) -> [i64; 4] {
+    // Greedily take the largest squares for the first two terms, such that the rest is small enough to search exhaustively
+    for a in (0..=m.isqrt()).rev() {
+        let m_a = m - a * a;
+        if !is_sum_of_three_squares(m_a) {
+            continue;
+        }
+        for b in (0..=m_a.isqrt()).rev() {
+            if let Some([c, e]) = two_squares(m_a - b * b) {
+                return [a as i64, b as i64, c as i64, e as i64];
+            }
+        }
+    }
+    unreachable!("every non-negative integer is a sum of four squares")
+}
+
+/// Legendre's three-square theorem: m is a sum of three squares unless it is of the form 4^k (8l + 7)
+fn is_sum_of_three_squares(mut m: u64) -> bool {
+    while m > 0 && m & 3 == 0 {
+        m /= 4;
+    }
+    m % 8 != 7
+}
+
+fn two_squares(m: u64) -> Option<[u64; 2]> {
+    let mut c = m.isqrt();
+    while 2 * c * c >= m {
+        let e = (m - c * c).isqrt();
+        if c * c + e * e == m {
+            return Some([c, e]);
+        }
+        c -= 1;
+    }
+    None
+}
+
+/// The integer witness for one Falcon signature, satisfying $s_1 + h s_2 + q v = c$ over $\mathbb{Z}[X]/(X^n + 1)$ for centered $h$ and $c$
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FalconWitnessCoeffs {
+    pub s1: Vec<i64>,
+    pub s2: Vec<i64>,
+    pub v: Vec<i64>,
+}
+
+/// Recompute $s_1 = c - h s_2 \bmod q$ as a Falcon verifier does, check $\|(s_1, s_2)\|_2^2 \leq \lfloor\beta^2\rfloor$, and compute the quotient $v = (c - s_1 - h s_2) / q$ over the integers.
+pub fn verification_witness(
+    params: FalconParameters,
+    h: &[i64],
+    c: &[i64],
+    s2: &[i64],
+) -> Result<FalconWitnessCoeffs, FalconError> {
+    let n = params.n();
+    for (what, len) in [
+        ("public key", h.len()),
+        ("hashed message", c.len()),
+        ("signature", s2.len()),
+    ] {
+        if len != n {
+            return Err(FalconError::InvalidLength {
+                what,
+                expected: n,
+                found: len,
+            });
+        }
+    }
+
+    let h_s2 = negacyclic_mul(h, s2);
+    let s1 = c
+        .iter()
+        .zip(h_s2.iter())
+        .map(|(c_i, hs_i)| center_mod_q(c_i - hs_i))
+        .collect::<Vec<_>>();
+
+    let norm_sq = l2_norm_sq(&s1) + l2_norm_sq(s2);
+    if norm_sq > params.signature_bound_sq() {
+        return Err(FalconError::SignatureTooLong {
+            norm_sq,
+            bound_sq: params.signature_bound_sq(),
+        });
+    }
+
+    let v = (0..n)
+        .map(|i| {
+            let e = c[i] - s1[i] - h_s2[i];
+            debug_assert_eq!(e % Q, 0);
+            e / Q
+        })
+        .collect::<Vec<_>>();
+    debug_assert!(l2_norm_sq(&v) <= params.quotient_bound_sq());
+
+    Ok(FalconWitnessCoeffs {
+        s1,
+        s2: s2.to_vec(),
+        v,
+    })
+}
+
+/// A ring element whose first four coefficients are squares summing to m, so that its squared norm is m
+pub fn slack<R: PolyRing>(m: u64) -> R {
+    let mut coeffs = vec![0i64; R::dimension()];
+    coeffs[..4].copy_from_slice(&four_squares(m));
+    to_ring(&coeffs)
+}
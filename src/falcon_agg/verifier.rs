@@ -0,0 +1,56 @@
+use nimue::{Arthur, ProofError, ProofResult};
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::nimue::arthur::SerArthur;
+use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use relations::principal_relation::{Index, Instance};
+
+use crate::falcon_agg::reduction::{reduce, FalconAggregationCRS, FalconTranscript};
+use crate::verifier::verify_principal_relation;
+
+pub fn verify_reduction_falcon_labradorpr<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &FalconAggregationCRS<R>,
+    public_key: &[R],
+    messages: &[R],
+    is_same_pk: bool,
+) -> ProofResult<(Index<R>, Instance<R>)> {
+    let t = arthur.next_vector(crs.A.nrows())?;
+
+    let eps =
+        arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(crs.eps_len(), crs.num_repetitions)?;
+    let rho = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(
+        2 * crs.num_signatures,
+        crs.num_repetitions,
+    )?;
+
+    let transcript = FalconTranscript { t, eps, rho };
+    Ok(reduce(crs, public_key, messages, is_same_pk, &transcript))
+}
+
+pub fn verify_falcon_aggregation<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &FalconAggregationCRS<R>,
+    public_key: &[R],
+    messages: &[R],
+    is_same_pk: bool,
+) -> Result<(), ProofError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    let (index, instance) =
+        verify_reduction_falcon_labradorpr(arthur, crs, public_key, messages, is_same_pk)?;
+
+    arthur.ratchet()?;
+
+    verify_principal_relation(arthur, &crs.core_crs, &index, &instance)
+}
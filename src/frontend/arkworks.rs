@@ -0,0 +1,70 @@
+//! Conversion of arkworks constraint systems, enabled with the `arkworks` feature.
+
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSystemRef, Matrix as ArkMatrix};
+use num_bigint::BigUint;
+
+use crate::frontend::{ImportError, ImportedR1CS, SparseRow};
+
+fn to_biguint<F: PrimeField>(x: &F) -> BigUint {
+    x.into_bigint().into()
+}
+
+fn to_sparse_rows<F: PrimeField>(m: &ArkMatrix<F>) -> Vec<SparseRow> {
+    m.iter()
+        .map(|row| {
+            row.iter()
+                .map(|(coeff, i)| (*i, to_biguint(coeff)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Convert an arkworks constraint system, together with its assignment, into a field-agnostic constraint system.
+///
+/// The constraint system is finalized (i.e., all linear combinations are inlined) before extracting its matrices.
+/// Arkworks orders variables as (1, public inputs, witnesses), which matches the (1, x, w) variable order of
+/// [`ImportedR1CS`].
+pub fn import_arkworks<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+) -> Result<(ImportedR1CS, Vec<BigUint>), ImportError> {
+    // `to_matrices` returns `None` both for `ConstraintSystemRef::None` and for constraint systems in prove mode
+    // without `construct_matrices`, so tell these apart beforehand
+    if cs.is_none() {
+        return Err(ImportError::Malformed(
+            "constraint system is `ConstraintSystemRef::None`".to_string(),
+        ));
+    }
+    if !cs.should_construct_matrices() {
+        return Err(ImportError::Malformed(
+            "constraint system is in prove mode with `construct_matrices: false`, and has no matrices".to_string(),
+        ));
+    }
+    cs.finalize();
+    let matrices = cs.to_matrices().unwrap();
+    let cs = cs.borrow().unwrap();
+
+    let assignment = cs
+        .instance_assignment
+        .iter()
+        .chain(cs.witness_assignment.iter())
+        .map(to_biguint)
+        .collect::<Vec<_>>();
+
+    let imported = ImportedR1CS {
+        modulus: F::MODULUS.into(),
+        num_instance_variables: matrices.num_instance_variables,
+        num_witness_variables: matrices.num_witness_variables,
+        a: to_sparse_rows(&matrices.a),
+        b: to_sparse_rows(&matrices.b),
+        c: to_sparse_rows(&matrices.c),
+    };
+    imported.validate()?;
+    if assignment.len() != imported.num_variables() {
+        return Err(ImportError::InvalidAssignment {
+            expected: imported.num_variables(),
+            found: assignment.len(),
+        });
+    }
+    Ok((imported, assignment))
+}
@@ -0,0 +1,268 @@
+//! Parsers for the binary `.r1cs` and `.wtns` formats produced by circom, see
+//! <https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md>.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use num_bigint::BigUint;
+
+use crate::frontend::{ImportError, ImportedR1CS, SparseRow};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+const R1CS_HEADER_SECTION: u32 = 1;
+const R1CS_CONSTRAINTS_SECTION: u32 = 2;
+const WTNS_HEADER_SECTION: u32 = 1;
+const WTNS_DATA_SECTION: u32 = 2;
+
+/// Header of a circom `.r1cs` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircomHeader {
+    /// Size of a field element in bytes
+    pub field_size: usize,
+    pub prime: BigUint,
+    /// Number of wires, including the constant wire 0
+    pub num_wires: usize,
+    pub num_pub_out: usize,
+    pub num_pub_in: usize,
+    pub num_prv_in: usize,
+    pub num_labels: u64,
+    pub num_constraints: usize,
+}
+
+/// A constraint system read from a circom `.r1cs` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircomR1CS {
+    pub header: CircomHeader,
+    /// Constraints <a_i, w> * <b_i, w> = <c_i, w>
+    pub constraints: Vec<(SparseRow, SparseRow, SparseRow)>,
+}
+
+/// A witness read from a circom `.wtns` file, containing the values of all wires (starting with the constant 1)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircomWitness {
+    pub prime: BigUint,
+    pub values: Vec<BigUint>,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ImportError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(ImportError::Malformed(format!(
+                "unexpected end of input at byte {}",
+                self.pos
+            )));
+        }
+        let res = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(res)
+    }
+
+    fn u32(&mut self) -> Result<u32, ImportError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ImportError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn field_element(&mut self, field_size: usize) -> Result<BigUint, ImportError> {
+        Ok(BigUint::from_bytes_le(self.take(field_size)?))
+    }
+}
+
+/// Read the magic number, version and section table of a circom binary file, returning the byte range of each section
+fn read_sections<'a>(
+    bytes: &'a [u8],
+    magic: &[u8; 4],
+) -> Result<HashMap<u32, &'a [u8]>, ImportError> {
+    let mut reader = Reader::new(bytes);
+    if reader.take(4)? != magic {
+        return Err(ImportError::Malformed(format!(
+            "expected magic number {:?}",
+            String::from_utf8_lossy(magic)
+        )));
+    }
+    let _version = reader.u32()?;
+    let num_sections = reader.u32()?;
+    let mut sections = HashMap::with_capacity(num_sections as usize);
+    for _ in 0..num_sections {
+        let section_type = reader.u32()?;
+        let section_size = reader.u64()? as usize;
+        sections.insert(section_type, reader.take(section_size)?);
+    }
+    Ok(sections)
+}
+
+fn section<'a>(
+    sections: &HashMap<u32, &'a [u8]>,
+    section_type: u32,
+) -> Result<&'a [u8], ImportError> {
+    sections
+        .get(&section_type)
+        .copied()
+        .ok_or(ImportError::Malformed(format!(
+            "missing section of type {section_type}"
+        )))
+}
+
+fn check_field_element(x: &BigUint, prime: &BigUint) -> Result<(), ImportError> {
+    if x >= prime {
+        return Err(ImportError::Malformed(format!(
+            "{x} is not reduced modulo {prime}"
+        )));
+    }
+    Ok(())
+}
+
+impl CircomR1CS {
+    pub fn parse(bytes: &[u8]) -> Result<Self, ImportError> {
+        let sections = read_sections(bytes, R1CS_MAGIC)?;
+
+        let mut reader = Reader::new(section(&sections, R1CS_HEADER_SECTION)?);
+        let field_size = reader.u32()? as usize;
+        let prime = reader.field_element(field_size)?;
+        let header = CircomHeader {
+            field_size,
+            prime,
+            num_wires: reader.u32()? as usize,
+            num_pub_out: reader.u32()? as usize,
+            num_pub_in: reader.u32()? as usize,
+            num_prv_in: reader.u32()? as usize,
+            num_labels: reader.u64()?,
+            num_constraints: reader.u32()? as usize,
+        };
+        if 1 + header.num_pub_out + header.num_pub_in > header.num_wires {
+            return Err(ImportError::Malformed(format!(
+                "{} public wires, but there are only {} wires",
+                1 + header.num_pub_out + header.num_pub_in,
+                header.num_wires
+            )));
+        }
+
+        let mut reader = Reader::new(section(&sections, R1CS_CONSTRAINTS_SECTION)?);
+        let mut read_lc = || -> Result<SparseRow, ImportError> {
+            let num_factors = reader.u32()?;
+            (0..num_factors)
+                .map(|_| {
+                    let wire = reader.u32()? as usize;
+                    let coeff = reader.field_element(header.field_size)?;
+                    if wire >= header.num_wires {
+                        return Err(ImportError::Malformed(format!(
+                            "wire {wire} out of range, there are only {} wires",
+                            header.num_wires
+                        )));
+                    }
+                    check_field_element(&coeff, &header.prime)?;
+                    Ok((wire, coeff))
+                })
+                .collect()
+        };
+        let constraints = (0..header.num_constraints)
+            .map(|_| Ok((read_lc()?, read_lc()?, read_lc()?)))
+            .collect::<Result<Vec<_>, ImportError>>()?;
+
+        Ok(Self {
+            header,
+            constraints,
+        })
+    }
+
+    pub fn read<Rd: Read>(mut reader: Rd) -> Result<Self, ImportError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::parse(&bytes)
+    }
+
+    /// Number of public wires, including the constant wire 0
+    pub fn num_public_wires(&self) -> usize {
+        1 + self.header.num_pub_out + self.header.num_pub_in
+    }
+
+    /// Convert into a field-agnostic constraint system. Circom orders wires as (1, public outputs, public inputs,
+    /// private inputs, intermediate wires), which matches the (1, x, w) variable order of [`ImportedR1CS`].
+    pub fn into_imported(self) -> ImportedR1CS {
+        let num_instance_variables = self.num_public_wires();
+        let num_witness_variables = self.header.num_wires - num_instance_variables;
+        let (mut a, mut b, mut c) = (
+            Vec::with_capacity(self.constraints.len()),
+            Vec::with_capacity(self.constraints.len()),
+            Vec::with_capacity(self.constraints.len()),
+        );
+        for (a_i, b_i, c_i) in self.constraints {
+            a.push(a_i);
+            b.push(b_i);
+            c.push(c_i);
+        }
+        ImportedR1CS {
+            modulus: self.header.prime,
+            num_instance_variables,
+            num_witness_variables,
+            a,
+            b,
+            c,
+        }
+    }
+}
+
+impl CircomWitness {
+    pub fn parse(bytes: &[u8]) -> Result<Self, ImportError> {
+        let sections = read_sections(bytes, WTNS_MAGIC)?;
+
+        let mut reader = Reader::new(section(&sections, WTNS_HEADER_SECTION)?);
+        let field_size = reader.u32()? as usize;
+        let prime = reader.field_element(field_size)?;
+        let num_values = reader.u32()? as usize;
+
+        let mut reader = Reader::new(section(&sections, WTNS_DATA_SECTION)?);
+        let values = (0..num_values)
+            .map(|_| {
+                let x = reader.field_element(field_size)?;
+                check_field_element(&x, &prime)?;
+                Ok(x)
+            })
+            .collect::<Result<Vec<_>, ImportError>>()?;
+
+        Ok(Self { prime, values })
+    }
+
+    pub fn read<Rd: Read>(mut reader: Rd) -> Result<Self, ImportError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::parse(&bytes)
+    }
+}
+
+/// Read a circom constraint system and witness, checking that both are defined over the same field
+pub fn import_circom(
+    r1cs: &[u8],
+    wtns: &[u8],
+) -> Result<(ImportedR1CS, Vec<BigUint>), ImportError> {
+    let r1cs = CircomR1CS::parse(r1cs)?;
+    let witness = CircomWitness::parse(wtns)?;
+    if witness.prime != r1cs.header.prime {
+        return Err(ImportError::IncompatibleModulus {
+            expected: r1cs.header.prime,
+            found: witness.prime,
+        });
+    }
+    if witness.values.len() != r1cs.header.num_wires {
+        return Err(ImportError::InvalidAssignment {
+            expected: r1cs.header.num_wires,
+            found: witness.values.len(),
+        });
+    }
+    let imported = r1cs.into_imported();
+    imported.validate()?;
+    Ok((imported, witness.values))
+}
@@ -0,0 +1,242 @@
+//! Frontends that import R1CS constraint systems produced by existing tooling (circom, arkworks) and convert them to
+//! the instance, index and witness types consumed by the R1CS reductions in this crate.
+//!
+//! A constraint system is only converted if it is defined modulo the modulus q of the target ring, or modulo 2 for
+//! R1CS over Z2, and its coefficients are converted through `u128`. Circuits over the scalar fields of pairing-friendly
+//! curves such as BN254 or BLS12-381, whose moduli have 254 and 255 bits, can thus be imported but not converted, since
+//! the rings of this crate have moduli of at most 128 bits; they have to be compiled for a field of modulus q instead.
+
+use std::fmt::{Debug, Display, Formatter};
+
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::ring::{PolyRing, Ring, Z2};
+use relations::r1cs::{Instance as R1CSInstance, Witness as R1CSWitness};
+
+use crate::sparse::{SparseMatrix, SparseR1CSIndex};
+
+#[cfg(feature = "arkworks")]
+pub mod arkworks;
+pub mod circom;
+#[cfg(test)]
+mod test;
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The input file is truncated or malformed
+    Malformed(String),
+    /// The modulus of the imported constraint system differs from the modulus of the target field
+    IncompatibleModulus {
+        expected: BigUint,
+        found: BigUint,
+    },
+    /// The assignment does not have one value per variable of the constraint system
+    InvalidAssignment {
+        expected: usize,
+        found: usize,
+    },
+    Io(std::io::Error),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Malformed(msg) => write!(f, "malformed input: {msg}"),
+            ImportError::IncompatibleModulus { expected, found } => write!(
+                f,
+                "constraint system is defined modulo {found}, but the target field has modulus {expected}"
+            ),
+            ImportError::InvalidAssignment { expected, found } => write!(
+                f,
+                "assignment has {found} values, but the constraint system has {expected} variables"
+            ),
+            ImportError::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+/// A sparse row of a constraint matrix, given as (variable index, coefficient) pairs
+pub type SparseRow = Vec<(usize, BigUint)>;
+
+/// A field-agnostic R1CS constraint system, with coefficients given as integers modulo `modulus`.
+///
+/// Variables are ordered as (1, x, w), i.e., variable 0 is the constant 1, followed by the remaining
+/// `num_instance_variables - 1` public variables and the `num_witness_variables` private variables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedR1CS {
+    pub modulus: BigUint,
+    /// Number of public variables, including the constant 1
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub a: Vec<SparseRow>,
+    pub b: Vec<SparseRow>,
+    pub c: Vec<SparseRow>,
+}
+
+/// The output of an import: an index, with its matrices in CSR format, an instance and a witness of the same R1CS
+/// relation
+pub type SparseR1CSTriple<F> = (SparseR1CSIndex<F>, R1CSInstance<F>, R1CSWitness<F>);
+
+impl ImportedR1CS {
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.num_instance_variables + self.num_witness_variables
+    }
+
+    /// Check that the matrices a, b and c have the same number of rows, and only reference existing variables
+    pub fn validate(&self) -> Result<(), ImportError> {
+        if self.b.len() != self.a.len() || self.c.len() != self.a.len() {
+            return Err(ImportError::Malformed(format!(
+                "a, b and c have {}, {} and {} rows",
+                self.a.len(),
+                self.b.len(),
+                self.c.len()
+            )));
+        }
+        for (i, row) in self.a.iter().chain(&self.b).chain(&self.c).enumerate() {
+            if let Some((j, _)) = row.iter().find(|(j, _)| *j >= self.num_variables()) {
+                return Err(ImportError::Malformed(format!(
+                    "constraint {} references variable {j}, but there are only {} variables",
+                    i % self.num_constraints(),
+                    self.num_variables()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `assignment` (ordered as (1, x, w)) satisfies all constraints, over the integers modulo `modulus`.
+    /// Constraint systems that are not valid, see `validate`, are not satisfied by any assignment.
+    pub fn is_satisfied(&self, assignment: &[BigUint]) -> bool {
+        let eval = |row: &SparseRow| {
+            row.iter().fold(BigUint::zero(), |acc, (i, coeff)| {
+                acc + coeff * &assignment[*i]
+            }) % &self.modulus
+        };
+        self.validate().is_ok()
+            && assignment.len() == self.num_variables()
+            && (0..self.num_constraints())
+                .all(|i| (eval(&self.a[i]) * eval(&self.b[i])) % &self.modulus == eval(&self.c[i]))
+    }
+
+    fn check_modulus(&self, expected: &BigUint) -> Result<(), ImportError> {
+        if &self.modulus != expected {
+            return Err(ImportError::IncompatibleModulus {
+                expected: expected.clone(),
+                found: self.modulus.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Convert `x` to an element of `F` through its representative modulo `modulus`, which must fit in a `u128`
+    fn to_field<F: Ring + TryFrom<u128>>(&self, x: &BigUint) -> Result<F, ImportError> {
+        (x % &self.modulus)
+            .to_u128()
+            .and_then(|x| F::try_from(x).ok())
+            .ok_or(ImportError::Malformed(format!(
+                "{x} cannot be converted to a field element"
+            )))
+    }
+
+    /// The reductions to the principal relation require the number of constraints to be equal to the number of
+    /// variables, and both to be a multiple of `d`, so the constraint system is padded to this size with all-zero
+    /// constraints and zero-valued witness variables.
+    fn padded_size(&self, d: usize) -> usize {
+        self.num_constraints().max(self.num_variables()).div_ceil(d) * d
+    }
+
+    /// Split `assignment` into an instance and a witness over `F`, padding the witness to `size - num_instance_variables`
+    fn convert_assignment<F: Ring + TryFrom<u128>>(
+        &self,
+        assignment: &[BigUint],
+        size: usize,
+    ) -> Result<(R1CSInstance<F>, R1CSWitness<F>), ImportError> {
+        self.validate()?;
+        if assignment.len() != self.num_variables() {
+            return Err(ImportError::InvalidAssignment {
+                expected: self.num_variables(),
+                found: assignment.len(),
+            });
+        }
+        let n_x = self.num_instance_variables;
+        let x = assignment[..n_x]
+            .iter()
+            .map(|x| self.to_field(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut w = assignment[n_x..]
+            .iter()
+            .map(|x| self.to_field(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        w.resize(size - n_x, F::zero());
+        Ok((
+            R1CSInstance(Vector::<F>::from_vec(x)),
+            R1CSWitness(Vector::<F>::from_vec(w)),
+        ))
+    }
+
+    /// Convert to an R1CS over `F`, whose modulus must be equal to `self.modulus`, padded to a multiple of `d`.
+    /// The matrices are built in CSR format directly from the sparse rows, in time linear in their number of non-zero
+    /// entries.
+    fn convert<F: Ring + TryFrom<u128>>(
+        &self,
+        assignment: &[BigUint],
+        d: usize,
+    ) -> Result<SparseR1CSTriple<F>, ImportError> {
+        let size = self.padded_size(d);
+        let (instance, witness) = self.convert_assignment(assignment, size)?;
+
+        let to_matrix = |rows: &Vec<SparseRow>| -> Result<SparseMatrix<F>, ImportError> {
+            let triplets = rows
+                .iter()
+                .enumerate()
+                .flat_map(|(i, row)| row.iter().map(move |(j, coeff)| (i, *j, coeff)))
+                .map(|(i, j, coeff)| Ok((i, j, self.to_field(coeff)?)))
+                .collect::<Result<Vec<_>, ImportError>>()?;
+            Ok(SparseMatrix::from_triplets(size, size, &triplets))
+        };
+
+        Ok((
+            SparseR1CSIndex {
+                a: to_matrix(&self.a)?,
+                b: to_matrix(&self.b)?,
+                c: to_matrix(&self.c)?,
+            },
+            instance,
+            witness,
+        ))
+    }
+
+    /// Convert to an R1CS over the base ring Z_q of `R`, as consumed by [`crate::r1cs::prover::prove_r1cs`].
+    /// This fails with `ImportError::IncompatibleModulus` unless the constraint system is defined modulo q, see the
+    /// module documentation.
+    pub fn to_zq_r1cs<R: PolyRing>(
+        &self,
+        assignment: &[BigUint],
+    ) -> Result<SparseR1CSTriple<R::BaseRing>, ImportError> {
+        self.check_modulus(&R::modulus())?;
+        self.convert::<R::BaseRing>(assignment, R::dimension())
+    }
+
+    /// Convert to an R1CS over Z_2, as consumed by [`crate::binary_r1cs::prover::prove_binary_r1cs`]
+    pub fn to_binary_r1cs<R: PolyRing>(
+        &self,
+        assignment: &[BigUint],
+    ) -> Result<SparseR1CSTriple<Z2>, ImportError> {
+        self.check_modulus(&BigUint::from(2u32))?;
+        self.convert::<Z2>(assignment, R::dimension())
+    }
+}
@@ -0,0 +1,302 @@
+use num_bigint::BigUint;
+
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::ring::ntt::ntt_prime;
+use lattirust_arithmetic::ring::{PolyRing, Pow2CyclotomicPolyRingNTT, Zq1};
+
+use crate::frontend::circom::{import_circom, CircomR1CS, CircomWitness};
+use crate::frontend::ImportError;
+use crate::r1cs::prover::prove_r1cs;
+use crate::r1cs::r1cs_iopattern;
+use crate::r1cs::util::R1CSCRS;
+use crate::r1cs::verifier::verify_r1cs;
+use crate::util::concat;
+
+const Q: u64 = ntt_prime::<64>(32);
+const D: usize = 64;
+type F = Zq1<Q>;
+
+type R = Pow2CyclotomicPolyRingNTT<F, D>;
+
+const FIELD_SIZE: usize = 8;
+
+fn field_element(x: u64) -> Vec<u8> {
+    let mut bytes = BigUint::from(x).to_bytes_le();
+    bytes.resize(FIELD_SIZE, 0);
+    bytes
+}
+
+fn file(magic: &[u8; 4], sections: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend((sections.len() as u32).to_le_bytes());
+    for (section_type, section) in sections {
+        bytes.extend(section_type.to_le_bytes());
+        bytes.extend((section.len() as u64).to_le_bytes());
+        bytes.extend(section);
+    }
+    bytes
+}
+
+fn linear_combination(factors: &[(u32, u64)]) -> Vec<u8> {
+    let mut bytes = (factors.len() as u32).to_le_bytes().to_vec();
+    for (wire, coeff) in factors {
+        bytes.extend(wire.to_le_bytes());
+        bytes.extend(field_element(*coeff));
+    }
+    bytes
+}
+
+/// Circuit with one public output `out` (wire 1) and two private inputs `x`, `y` (wires 2 and 3), enforcing
+/// `x * y = out` and `(x + y) * 1 = out - 1`
+fn circom_r1cs(prime: u64) -> Vec<u8> {
+    let mut header = (FIELD_SIZE as u32).to_le_bytes().to_vec();
+    header.extend(field_element(prime));
+    for x in [4u32, 1, 0, 2] {
+        // num_wires, num_pub_out, num_pub_in, num_prv_in
+        header.extend(x.to_le_bytes());
+    }
+    header.extend(4u64.to_le_bytes()); // num_labels
+    header.extend(2u32.to_le_bytes()); // num_constraints
+
+    let mut constraints = vec![];
+    constraints.extend(linear_combination(&[(2, 1)]));
+    constraints.extend(linear_combination(&[(3, 1)]));
+    constraints.extend(linear_combination(&[(1, 1)]));
+    constraints.extend(linear_combination(&[(2, 1), (3, 1)]));
+    constraints.extend(linear_combination(&[(0, 1)]));
+    constraints.extend(linear_combination(&[(1, 1), (0, prime - 1)]));
+
+    file(b"r1cs", vec![(1, header), (2, constraints)])
+}
+
+fn circom_wtns(prime: u64, values: &[u64]) -> Vec<u8> {
+    let mut header = (FIELD_SIZE as u32).to_le_bytes().to_vec();
+    header.extend(field_element(prime));
+    header.extend((values.len() as u32).to_le_bytes());
+    let data = values.iter().flat_map(|x| field_element(*x)).collect();
+    file(b"wtns", vec![(1, header), (2, data)])
+}
+
+#[test]
+fn test_parse_circom() {
+    let r1cs = CircomR1CS::parse(&circom_r1cs(Q)).unwrap();
+    assert_eq!(r1cs.header.prime, BigUint::from(Q));
+    assert_eq!(r1cs.header.num_wires, 4);
+    assert_eq!(r1cs.num_public_wires(), 2);
+    assert_eq!(r1cs.constraints.len(), 2);
+    assert_eq!(
+        r1cs.constraints[1].0,
+        vec![(2, BigUint::from(1u32)), (3, BigUint::from(1u32))]
+    );
+
+    let witness = CircomWitness::parse(&circom_wtns(Q, &[1, 6, 2, 3])).unwrap();
+    assert_eq!(witness.values.len(), 4);
+}
+
+#[test]
+fn test_import_circom_zq() {
+    // 2 * 3 = 6 and (2 + 3) * 1 = 6 - 1
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(Q), &circom_wtns(Q, &[1, 6, 2, 3])).unwrap();
+    assert!(imported.is_satisfied(&assignment));
+
+    let (index, instance, witness) = imported.to_zq_r1cs::<R>(&assignment).unwrap();
+    assert_eq!(instance.0.len(), 2);
+    assert_eq!(instance.0.len() + witness.0.len(), D);
+    assert_eq!((index.a.nrows(), index.a.ncols()), (D, D));
+
+    let z = concat(&[instance.0.as_slice(), witness.0.as_slice()]);
+    let (a, b, c): (Vector<F>, Vector<F>, Vector<F>) =
+        (&index.a * &z, &index.b * &z, &index.c * &z);
+    for i in 0..D {
+        assert_eq!(a[i] * b[i], c[i]);
+    }
+}
+
+#[test]
+fn test_import_circom_prove_verify() {
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(Q), &circom_wtns(Q, &[1, 6, 2, 3])).unwrap();
+    let (index, instance, witness) = imported.to_zq_r1cs::<R>(&assignment).unwrap();
+
+    let crs = R1CSCRS::<R>::new(index.a.nrows(), index.a.ncols());
+    let io = r1cs_iopattern(&crs);
+    let mut merlin = io.to_merlin();
+    let proof = prove_r1cs(&crs, &mut merlin, &index, &instance, &witness)
+        .unwrap()
+        .to_vec();
+
+    let mut arthur = io.to_arthur(&proof);
+    verify_r1cs(&mut arthur, &crs, &index, &instance).unwrap();
+
+    // The circuit is not satisfied for out = 7
+    let (_, other_instance, _) = imported
+        .to_zq_r1cs::<R>(&[1u64, 7, 2, 3].map(BigUint::from))
+        .unwrap();
+    let mut arthur = io.to_arthur(&proof);
+    assert!(verify_r1cs(&mut arthur, &crs, &index, &other_instance).is_err());
+}
+
+#[test]
+fn test_import_circom_binary() {
+    // Over Z2, x = 1, y = 0 and out = 0 satisfy 1 * 0 = 0 and (1 + 0) * 1 = 0 + 1
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(2), &circom_wtns(2, &[1, 0, 1, 0])).unwrap();
+    assert!(imported.is_satisfied(&assignment));
+
+    let (index, instance, witness) = imported.to_binary_r1cs::<R>(&assignment).unwrap();
+    assert_eq!(instance.0.len() + witness.0.len(), D);
+    assert_eq!((index.a.nrows(), index.a.ncols()), (D, D));
+    // Only the entries of the constraints are stored, not those of the padding
+    assert_eq!(index.a.nnz() + index.b.nnz() + index.c.nnz(), 6);
+
+    let z = concat(&[instance.0.as_slice(), witness.0.as_slice()]);
+    let (a, b, c) = (&index.a * &z, &index.b * &z, &index.c * &z);
+    for i in 0..D {
+        assert_eq!(a[i] * b[i], c[i]);
+    }
+}
+
+#[test]
+fn test_imported_invalid() {
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(Q), &circom_wtns(Q, &[1, 6, 2, 3])).unwrap();
+    assert!(imported.validate().is_ok());
+
+    let mut out_of_range = imported.clone();
+    out_of_range.a[0].push((4, BigUint::from(1u64)));
+    assert!(matches!(
+        out_of_range.validate(),
+        Err(ImportError::Malformed(_))
+    ));
+    assert!(!out_of_range.is_satisfied(&assignment));
+    assert!(matches!(
+        out_of_range.to_zq_r1cs::<R>(&assignment),
+        Err(ImportError::Malformed(_))
+    ));
+
+    let mut missing_row = imported;
+    missing_row.b.pop();
+    assert!(matches!(
+        missing_row.validate(),
+        Err(ImportError::Malformed(_))
+    ));
+    assert!(!missing_row.is_satisfied(&assignment));
+}
+
+#[test]
+fn test_import_circom_unsatisfied() {
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(Q), &circom_wtns(Q, &[1, 7, 2, 3])).unwrap();
+    assert!(!imported.is_satisfied(&assignment));
+}
+
+#[test]
+fn test_import_circom_incompatible_modulus() {
+    let p = 2305843009213693951; // 2^61 - 1
+    let (imported, assignment) =
+        import_circom(&circom_r1cs(p), &circom_wtns(p, &[1, 6, 2, 3])).unwrap();
+    assert!(imported.is_satisfied(&assignment));
+    assert!(matches!(
+        imported.to_zq_r1cs::<R>(&assignment),
+        Err(ImportError::IncompatibleModulus { .. })
+    ));
+    assert_ne!(BigUint::from(p), R::modulus());
+
+    assert!(matches!(
+        import_circom(&circom_r1cs(Q), &circom_wtns(p, &[1, 6, 2, 3])),
+        Err(ImportError::IncompatibleModulus { .. })
+    ));
+}
+
+#[test]
+fn test_parse_circom_malformed() {
+    let r1cs = circom_r1cs(Q);
+    assert!(matches!(
+        CircomR1CS::parse(&r1cs[..r1cs.len() - 1]),
+        Err(ImportError::Malformed(_))
+    ));
+    assert!(matches!(
+        CircomR1CS::parse(&circom_wtns(Q, &[1])),
+        Err(ImportError::Malformed(_))
+    ));
+}
+
+#[cfg(feature = "arkworks")]
+#[test]
+fn test_import_arkworks() {
+    use ark_relations::lc;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use crate::frontend::arkworks::import_arkworks;
+
+    type Fr = ark_bls12_381::Fr;
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let x = cs.new_witness_variable(|| Ok(Fr::from(2u64))).unwrap();
+    let y = cs.new_witness_variable(|| Ok(Fr::from(3u64))).unwrap();
+    let z = cs.new_input_variable(|| Ok(Fr::from(6u64))).unwrap();
+    cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + z)
+        .unwrap();
+
+    let (imported, assignment) = import_arkworks(&cs).unwrap();
+    assert_eq!(imported.num_instance_variables, 2);
+    assert_eq!(imported.num_witness_variables, 2);
+    assert!(imported.is_satisfied(&assignment));
+
+    // BLS12-381's scalar field is not the base ring of R
+    assert!(matches!(
+        imported.to_zq_r1cs::<R>(&assignment),
+        Err(ImportError::IncompatibleModulus { .. })
+    ));
+}
+
+#[cfg(feature = "arkworks")]
+#[test]
+fn test_import_arkworks_prove_verify() {
+    use ark_ff::fields::{Fp64, MontBackend, MontConfig};
+    use ark_relations::lc;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use crate::frontend::arkworks::import_arkworks;
+
+    /// A prime p = 1 mod 2 * D, so that Z_p[X]/(X^D + 1) supports the NTT
+    const P: u64 = 4294966657;
+
+    #[derive(MontConfig)]
+    #[modulus = "4294966657"]
+    #[generator = "5"]
+    struct FpConfig;
+    type Fp = Fp64<MontBackend<FpConfig, 1>>;
+
+    type RP = Pow2CyclotomicPolyRingNTT<Zq1<P>, D>;
+
+    // x * y = z and (x + y) * 1 = z - 1, for the public input z = 6
+    let cs = ConstraintSystem::<Fp>::new_ref();
+    let x = cs.new_witness_variable(|| Ok(Fp::from(2u64))).unwrap();
+    let y = cs.new_witness_variable(|| Ok(Fp::from(3u64))).unwrap();
+    let z = cs.new_input_variable(|| Ok(Fp::from(6u64))).unwrap();
+    cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + z)
+        .unwrap();
+    cs.enforce_constraint(
+        lc!() + x + y,
+        lc!() + ark_relations::r1cs::Variable::One,
+        lc!() + z - ark_relations::r1cs::Variable::One,
+    )
+    .unwrap();
+
+    let (imported, assignment) = import_arkworks(&cs).unwrap();
+    assert_eq!(imported.modulus, RP::modulus());
+    let (index, instance, witness) = imported.to_zq_r1cs::<RP>(&assignment).unwrap();
+
+    let crs = R1CSCRS::<RP>::new(index.a.nrows(), index.a.ncols());
+    let io = r1cs_iopattern(&crs);
+    let mut merlin = io.to_merlin();
+    let proof = prove_r1cs(&crs, &mut merlin, &index, &instance, &witness)
+        .unwrap()
+        .to_vec();
+
+    let mut arthur = io.to_arthur(&proof);
+    verify_r1cs(&mut arthur, &crs, &index, &instance).unwrap();
+}
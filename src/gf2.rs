@@ -0,0 +1,506 @@
+#![allow(non_snake_case)]
+
+use std::ops::{BitXor, BitXorAssign};
+
+use nimue::{ByteChallenges, IOPatternError};
+use num_traits::{One, Zero};
+
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
+use lattirust_arithmetic::ring::{PolyRing, Z2};
+
+use crate::sparse::SparseMatrix;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A vector over $\mathbb{Z}_2$, packed into 64-bit words.
+/// Bits beyond `len` in the last word are always zero.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitVector {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitVector {
+    pub fn zeros(len: usize) -> Self {
+        Self {
+            len,
+            words: vec![0u64; len.div_ceil(WORD_BITS)],
+        }
+    }
+
+    pub fn from_bits(bits: &[bool]) -> Self {
+        Self {
+            len: bits.len(),
+            words: bits
+                .chunks(WORD_BITS)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u64, |word, (i, b)| word | ((*b as u64) << i))
+                })
+                .collect(),
+        }
+    }
+
+    /// Read `len` bits from `bytes` in little-endian order, ignoring any bits beyond `len`.
+    pub fn from_le_bytes(len: usize, bytes: &[u8]) -> Self {
+        assert!(
+            bytes.len() * 8 >= len,
+            "{} bytes cannot hold {len} bits",
+            bytes.len()
+        );
+        let mut words = bytes
+            .chunks(WORD_BITS / 8)
+            .take(len.div_ceil(WORD_BITS))
+            .map(|chunk| {
+                let mut word = [0u8; WORD_BITS / 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            })
+            .collect::<Vec<_>>();
+        if len % WORD_BITS != 0 {
+            *words.last_mut().unwrap() &= (1u64 << (len % WORD_BITS)) - 1;
+        }
+        Self { len, words }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        debug_assert!(
+            i < self.len,
+            "index {} out of bounds for length {}",
+            i,
+            self.len
+        );
+        (self.words[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, bit: bool) {
+        debug_assert!(
+            i < self.len,
+            "index {} out of bounds for length {}",
+            i,
+            self.len
+        );
+        let mask = 1u64 << (i % WORD_BITS);
+        if bit {
+            self.words[i / WORD_BITS] |= mask;
+        } else {
+            self.words[i / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Number of ones, i.e., the Hamming weight.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Compute $\sum_i u_i v_i$ over the integers, i.e., the Hamming weight of $u \wedge v$.
+    pub fn and_popcount(&self, other: &Self) -> usize {
+        assert_eq!(self.len, other.len);
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Compute $\langle u, v \rangle$ over $\mathbb{Z}_2$.
+    pub fn dot(&self, other: &Self) -> bool {
+        self.and_popcount(other) % 2 == 1
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.words
+            .iter()
+            .flat_map(|word| (0..WORD_BITS).map(move |i| (word >> i) & 1 == 1))
+            .take(self.len)
+    }
+
+    /// Concatenate bit vectors, shifting each one into place a word at a time.
+    pub fn concat(vecs: &[&Self]) -> Self {
+        let len = vecs.iter().map(|v| v.len).sum::<usize>();
+        let mut words = Vec::<u64>::with_capacity(len.div_ceil(WORD_BITS));
+        let mut offset = 0;
+        for v in vecs {
+            let shift = offset % WORD_BITS;
+            if shift == 0 {
+                words.extend_from_slice(&v.words);
+            } else {
+                for word in &v.words {
+                    *words.last_mut().unwrap() |= word << shift;
+                    words.push(word >> (WORD_BITS - shift));
+                }
+            }
+            offset += v.len;
+            // The bits beyond `len` are zero, so the words past the end of the concatenation so far are all zero
+            words.truncate(offset.div_ceil(WORD_BITS));
+        }
+        Self { len, words }
+    }
+
+    pub fn to_dense(&self) -> Vector<Z2> {
+        Vector::<Z2>::from_vec(self.iter().map(to_z2).collect())
+    }
+}
+
+impl From<&Vector<Z2>> for BitVector {
+    fn from(v: &Vector<Z2>) -> Self {
+        Self::from_bits(&v.iter().map(|v_i| !v_i.is_zero()).collect::<Vec<_>>())
+    }
+}
+
+impl BitXorAssign<&BitVector> for BitVector {
+    fn bitxor_assign(&mut self, rhs: &BitVector) {
+        assert_eq!(self.len, rhs.len);
+        for (a, b) in self.words.iter_mut().zip(rhs.words.iter()) {
+            *a ^= b;
+        }
+    }
+}
+
+impl BitXor<&BitVector> for &BitVector {
+    type Output = BitVector;
+
+    fn bitxor(self, rhs: &BitVector) -> Self::Output {
+        let mut res = self.clone();
+        res ^= rhs;
+        res
+    }
+}
+
+/// A matrix over $\mathbb{Z}_2$, stored as packed rows.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitMatrix {
+    ncols: usize,
+    rows: Vec<BitVector>,
+}
+
+impl BitMatrix {
+    pub fn zeros(nrows: usize, ncols: usize) -> Self {
+        Self {
+            ncols,
+            rows: vec![BitVector::zeros(ncols); nrows],
+        }
+    }
+
+    /// Build a matrix from rows of equal length; no rows give the empty $0 \times 0$ matrix.
+    pub fn from_rows(rows: Vec<BitVector>) -> Self {
+        let ncols = rows.first().map_or(0, |r| r.len());
+        assert!(rows.iter().all(|r| r.len() == ncols));
+        Self { ncols, rows }
+    }
+
+    /// Number of bytes needed by [`Self::from_le_bytes`] for an `nrows` x `ncols` matrix.
+    pub fn num_bytes(nrows: usize, ncols: usize) -> usize {
+        nrows * ncols.div_ceil(8)
+    }
+
+    /// Read a matrix from `bytes`, row by row, where each row takes `ncols.div_ceil(8)` bytes in little-endian order.
+    pub fn from_le_bytes(nrows: usize, ncols: usize, bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), Self::num_bytes(nrows, ncols));
+        Self {
+            ncols,
+            rows: (0..nrows)
+                .map(|i| {
+                    let row_bytes = ncols.div_ceil(8);
+                    BitVector::from_le_bytes(ncols, &bytes[i * row_bytes..(i + 1) * row_bytes])
+                })
+                .collect(),
+        }
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    pub fn row(&self, i: usize) -> &BitVector {
+        &self.rows[i]
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> bool {
+        self.rows[i].get(j)
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, bit: bool) {
+        self.rows[i].set(j, bit)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut res = Self::zeros(self.ncols, self.nrows());
+        for (i, row) in self.rows.iter().enumerate() {
+            for (w, word) in row.words.iter().enumerate() {
+                let mut word = *word;
+                while word != 0 {
+                    let j = w * WORD_BITS + word.trailing_zeros() as usize;
+                    res.rows[j].set(i, true);
+                    word &= word - 1;
+                }
+            }
+        }
+        res
+    }
+
+    /// Compute $M \cdot v$ over $\mathbb{Z}_2$ with one AND-popcount per row.
+    pub fn mul_vector(&self, v: &BitVector) -> BitVector {
+        assert_eq!(self.ncols, v.len());
+        BitVector::from_bits(&self.rows.iter().map(|r| r.dot(v)).collect::<Vec<_>>())
+    }
+
+    /// Compute $X \cdot M$ over $\mathbb{Z}_2$ for a sparse $M$, in time linear in the number of non-zeros of $M$.
+    pub fn mul_sparse(&self, M: &SparseMatrix<Z2>) -> Self {
+        sum_binary_products(&[(self, M)])
+    }
+
+    pub fn to_dense(&self) -> Matrix<Z2> {
+        let mut M = Matrix::<Z2>::zeros(self.nrows(), self.ncols);
+        for (i, row) in self.rows.iter().enumerate() {
+            for j in 0..self.ncols {
+                if row.get(j) {
+                    M[(i, j)] = Z2::one();
+                }
+            }
+        }
+        M
+    }
+}
+
+impl From<&Matrix<Z2>> for BitMatrix {
+    fn from(M: &Matrix<Z2>) -> Self {
+        let mut res = Self::zeros(M.nrows(), M.ncols());
+        for i in 0..M.nrows() {
+            for j in 0..M.ncols() {
+                if !M[(i, j)].is_zero() {
+                    res.set(i, j, true);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl BitXorAssign<&BitMatrix> for BitMatrix {
+    fn bitxor_assign(&mut self, rhs: &BitMatrix) {
+        assert_eq!(self.nrows(), rhs.nrows());
+        for (a, b) in self.rows.iter_mut().zip(rhs.rows.iter()) {
+            *a ^= b;
+        }
+    }
+}
+
+impl SparseMatrix<Z2> {
+    /// Compute $M \cdot v$ over $\mathbb{Z}_2$ in time linear in the number of non-zeros of $M$.
+    pub fn mul_bits(&self, v: &BitVector) -> BitVector {
+        assert_eq!(self.ncols(), v.len());
+        let mut res = BitVector::zeros(self.nrows());
+        for i in 0..self.nrows() {
+            let bit = self
+                .row(i)
+                .filter(|(_, m_ij)| !m_ij.is_zero())
+                .fold(false, |acc, (j, _)| acc ^ v.get(j));
+            if bit {
+                res.set(i, true);
+            }
+        }
+        res
+    }
+}
+
+/// Compute $\sum_i X_i \cdot M_i$ over $\mathbb{Z}_2$, for packed $X_i \in \{0,1\}^{\lambda \times k}$ and sparse $M_i \in \{0,1\}^{k \times n}$.
+/// Every non-zero entry of $M_i$ costs $\lceil \lambda / 64 \rceil$ word XORs.
+pub fn sum_binary_products(terms: &[(&BitMatrix, &SparseMatrix<Z2>)]) -> BitMatrix {
+    assert!(!terms.is_empty());
+    let lambda = terms[0].0.nrows();
+    let n = terms[0].1.ncols();
+
+    // Work on the columns of X_i, so that each non-zero of M_i is a single row XOR
+    let mut res_T = BitMatrix::zeros(n, lambda);
+    for (X, M) in terms {
+        assert_eq!(X.nrows(), lambda);
+        assert_eq!(X.ncols(), M.nrows());
+        assert_eq!(M.ncols(), n);
+        let X_T = X.transpose();
+        for k in 0..M.nrows() {
+            for (j, m_kj) in M.row(k) {
+                if !m_kj.is_zero() {
+                    res_T.rows[j] ^= &X_T.rows[k];
+                }
+            }
+        }
+    }
+    res_T.transpose()
+}
+
+/// Lift a packed binary vector to a vector over R, packing $d$ consecutive bits as the coefficients of one ring element.
+pub fn lift_bits<R: PolyRing>(v: &BitVector) -> Vector<R> {
+    let d = R::dimension();
+    debug_assert_eq!(
+        v.len() % d,
+        0,
+        "vector length {} must be multiple of dimension {}",
+        v.len(),
+        d
+    );
+    let bits = [R::BaseRing::zero(), R::BaseRing::one()];
+    let coeffs = v
+        .words
+        .iter()
+        .flat_map(|word| (0..WORD_BITS).map(move |i| bits[((word >> i) & 1) as usize]))
+        .take(v.len())
+        .collect::<Vec<_>>();
+    Vector::<R>::from_vec(coeffs.chunks(d).map(|c| R::from(c.to_vec())).collect())
+}
+
+/// Squeeze a uniformly random matrix in $\{0,1\}^{n \times m}$ from the transcript, one bit per entry.
+/// The IO pattern must squeeze [`BitMatrix::num_bytes`] bytes for it.
+pub fn challenge_bit_matrix<T: ByteChallenges>(
+    transcript: &mut T,
+    nrows: usize,
+    ncols: usize,
+) -> Result<BitMatrix, IOPatternError> {
+    let mut bytes = vec![0u8; BitMatrix::num_bytes(nrows, ncols)];
+    transcript.fill_challenge_bytes(&mut bytes)?;
+    Ok(BitMatrix::from_le_bytes(nrows, ncols, &bytes))
+}
+
+/// Embed a bit into the base ring of `R`, e.g., to digest a statement over Z2 with [`crate::sparse::statement_digest`]
+pub(crate) fn embed_bit<R: PolyRing>(b: Z2) -> R::BaseRing {
+    if b.is_zero() {
+        R::BaseRing::zero()
+    } else {
+        R::BaseRing::one()
+    }
+}
+
+fn to_z2(b: bool) -> Z2 {
+    if b {
+        Z2::one()
+    } else {
+        Z2::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_std::rand::{thread_rng, Rng};
+
+    use lattirust_arithmetic::ring::ntt::ntt_prime;
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq1};
+
+    use super::*;
+
+    fn random_bits(len: usize) -> BitVector {
+        let rng = &mut thread_rng();
+        BitVector::from_bits(&(0..len).map(|_| rng.gen::<bool>()).collect::<Vec<_>>())
+    }
+
+    fn random_bit_matrix(nrows: usize, ncols: usize) -> BitMatrix {
+        BitMatrix::from_rows((0..nrows).map(|_| random_bits(ncols)).collect())
+    }
+
+    #[test]
+    fn test_dense_roundtrip() {
+        let v = random_bits(100);
+        assert_eq!(BitVector::from(&v.to_dense()), v);
+
+        let M = random_bit_matrix(130, 70);
+        assert_eq!(BitMatrix::from(&M.to_dense()), M);
+        assert_eq!(M.transpose().transpose(), M);
+        let M_T = M.transpose();
+        for i in 0..M.nrows() {
+            for j in 0..M.ncols() {
+                assert_eq!(M_T.get(j, i), M.get(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_products() {
+        let (u, v) = (random_bits(200), random_bits(200));
+        let expected = u.iter().zip(v.iter()).filter(|(a, b)| *a && *b).count();
+        assert_eq!(u.and_popcount(&v), expected);
+        assert_eq!(u.dot(&v), expected % 2 == 1);
+
+        let M = random_bit_matrix(70, 200);
+        assert_eq!(M.mul_vector(&v).to_dense(), &M.to_dense() * &v.to_dense());
+    }
+
+    #[test]
+    fn test_sparse_products() {
+        let X = random_bit_matrix(128, 64);
+        let M = random_bit_matrix(64, 96).to_dense();
+        let M_sparse = SparseMatrix::from_dense(&M);
+        assert_eq!(X.mul_sparse(&M_sparse).to_dense(), &X.to_dense() * &M);
+
+        let v = random_bits(96);
+        assert_eq!(M_sparse.mul_bits(&v).to_dense(), &M * &v.to_dense());
+    }
+
+    #[test]
+    fn test_concat() {
+        let vecs = [
+            random_bits(100),
+            random_bits(0),
+            random_bits(28),
+            random_bits(64),
+            random_bits(3),
+        ];
+        let res = BitVector::concat(&vecs.iter().collect::<Vec<_>>());
+        let expected = vecs.iter().flat_map(|v| v.iter()).collect::<Vec<_>>();
+        assert_eq!(res, BitVector::from_bits(&expected));
+        assert_eq!(res.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_from_le_bytes() {
+        let bytes = [0b1010_0101u8, 0xff, 0x01];
+        let v = BitVector::from_le_bytes(10, &bytes);
+        assert_eq!(
+            v.iter().collect::<Vec<_>>(),
+            [true, false, true, false, false, true, false, true, true, true]
+        );
+        // Bits beyond the length are dropped
+        assert_eq!(v.words(), &[0b11_1010_0101]);
+
+        let M = BitMatrix::from_le_bytes(2, 10, &[1, 2, 3, 4]);
+        assert_eq!(M.row(0), &BitVector::from_le_bytes(10, &[1, 2]));
+        assert_eq!(M.row(1), &BitVector::from_le_bytes(10, &[3, 0]));
+    }
+
+    #[test]
+    fn test_from_rows_empty() {
+        let M = BitMatrix::from_rows(vec![]);
+        assert_eq!(M.nrows(), 0);
+        assert_eq!(M.ncols(), 0);
+    }
+
+    #[test]
+    fn test_lift_bits() {
+        type R = Pow2CyclotomicPolyRingNTT<Zq1<{ ntt_prime::<64>(32) }>, 64>;
+        let v = random_bits(3 * R::dimension());
+        let lifted = lift_bits::<R>(&v);
+        assert_eq!(lifted.len(), 3);
+        for (j, r) in lifted.iter().enumerate() {
+            for (l, c) in r.coefficients().iter().enumerate() {
+                assert_eq!(c.is_one(), v.get(j * R::dimension() + l));
+                assert!(c.is_one() || c.is_zero());
+            }
+        }
+    }
+}
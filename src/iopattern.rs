@@ -5,15 +5,17 @@ use lattirust_arithmetic::nimue::iopattern::{
 };
 use lattirust_arithmetic::ring::{PolyRing, Z2};
 use lattirust_arithmetic::traits::FromRandomBytes;
-use nimue::{DuplexHash, IOPattern};
+use nimue::{ByteIOPattern, DuplexHash, IOPattern};
 use relations::r1cs::R1CS;
 use relations::Relation;
 
 use crate::binary_r1cs::util::BinaryR1CSCRS;
 use crate::common_reference_string::CommonReferenceString;
+use crate::gf2::BitMatrix;
+use crate::sparse::STATEMENT_DIGEST_LEN;
 
 pub trait LabradorIOPattern<R, H>:
-    SerIOPattern + SqueezeFromRandomBytes + RatchetIOPattern
+    SerIOPattern + SqueezeFromRandomBytes + RatchetIOPattern + ByteIOPattern
 where
     R: PolyRing,
     H: DuplexHash<u8>,
@@ -28,38 +30,60 @@ where
     //     self.absorb_serializable_like(instance, "labrador_principalrelation_crs")
     // }
 
-    fn labrador_io(self, crs: &CommonReferenceString<R>) -> Self {
+    /// Messages 1-4 of one round of the core protocol
+    fn labrador_round_io(self, crs: &CommonReferenceString<R>) -> Self {
         let log_q = R::modulus().bits() as f64;
         let num_aggregs = (128. / log_q).ceil() as usize;
-        self.absorb_vector::<R>(crs.k1, "prover message 1")
+        let io = self
+            .absorb_vector::<R>(crs.k1, "prover message 1")
             .squeeze_matrices::<R, WeightedTernaryChallengeSet<R>>(
                 256,
                 crs.n,
                 crs.r,
                 "verifier message 1",
             )
-            .absorb_vector_canonical::<R::BaseRing>(256, "prover message 2")
-            .squeeze_vectors::<R::BaseRing, R::BaseRing>(
+            .absorb_vector_canonical::<R::BaseRing>(256, "prover message 2");
+        // psi is only squeezed if there are constant constraints to aggregate, which is not the case after the first round
+        let io = if crs.num_constant_constraints > 0 {
+            io.squeeze_vectors::<R::BaseRing, R::BaseRing>(
                 crs.num_constant_constraints,
                 num_aggregs,
                 "verifier message 2 (psi)",
             )
-            .squeeze_vectors::<R::BaseRing, R::BaseRing>(
-                256,
-                num_aggregs,
-                "verifier message 2 (omega)",
-            )
-            .absorb_vec::<R>(num_aggregs, "prover message 3")
-            .squeeze_vector::<R, R>(crs.num_constraints, "verifier message 3 (alpha)")
-            .squeeze_vector::<R, R>(num_aggregs, "verifier message 3 (beta)")
-            .absorb_vector::<R>(crs.k2, "prover message 4")
-            .squeeze_vec::<R, LabradorChallengeSet<R>>(crs.r, "verifier message 4")
+        } else {
+            io
+        };
+        io.squeeze_vectors::<R::BaseRing, R::BaseRing>(
+            256,
+            num_aggregs,
+            "verifier message 2 (omega)",
+        )
+        .absorb_vec::<R>(num_aggregs, "prover message 3")
+        .squeeze_vector::<R, R>(crs.num_constraints, "verifier message 3 (alpha)")
+        .squeeze_vector::<R, R>(num_aggregs, "verifier message 3 (beta)")
+        .absorb_vector::<R>(crs.k2, "prover message 4")
+        .squeeze_vec::<R, LabradorChallengeSet<R>>(crs.r, "verifier message 4")
+    }
+
+    fn labrador_io(self, crs: &CommonReferenceString<R>) -> Self {
+        self.labrador_round_io(crs)
             .absorb_vector::<R>(crs.n, "prover message 5 (z)")
             .absorb_vectors::<R>(crs.k, crs.r, "prover message 5 (t)")
             .absorb_symmetric_matrix::<R>(crs.r, "prover message 5 (G)")
             .absorb_symmetric_matrix::<R>(crs.r, "prover message 5 (H)")
     }
 
+    /// The full recursion: one round per CRS in the chain, after which the prover sends the final witness in the clear
+    fn labrador_recursive_io(self, crs: &CommonReferenceString<R>) -> Self {
+        let mut io = self;
+        let mut crs = crs;
+        while let Some(next_crs) = crs.next_crs.as_ref() {
+            io = io.labrador_round_io(crs);
+            crs = next_crs;
+        }
+        io.absorb_vectors::<R>(crs.n, crs.r, "final witness")
+    }
+
     fn labrador_binaryr1cs_io(
         self,
         size: &<R1CS<Z2> as Relation>::Size,
@@ -67,10 +91,20 @@ where
     ) -> Self {
         let k = size.num_constraints;
         let secparam = crs.security_parameter;
-        self.absorb_vector::<R>(crs.A.nrows(), "prover message 1 (t)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (alpha)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (beta)")
-            .squeeze_binary_matrix(secparam, k, "verifier message 1 (gamma)")
+        self.add_bytes(STATEMENT_DIGEST_LEN, "statement digest")
+            .absorb_vector::<R>(crs.A.nrows(), "prover message 1 (t)")
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (alpha)",
+            )
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (beta)",
+            )
+            .challenge_bytes(
+                BitMatrix::num_bytes(secparam, k),
+                "verifier message 1 (gamma)",
+            )
             .absorb_vector_canonical::<R::BaseRing>(secparam, "prover message 2 (g)")
     }
 }
@@ -84,3 +118,23 @@ where
     Self: SerIOPattern + SqueezeFromRandomBytes + RatchetIOPattern,
 {
 }
+
+/// IO pattern of a standalone proof of a principal relation, as produced by `prover::prove_principal_relation`
+pub fn principal_relation_iopattern<R: PolyRing>(crs: &CommonReferenceString<R>) -> IOPattern
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    IOPattern::new("labrador_principal_relation").labrador_recursive_io(crs)
+}
+
+/// IO pattern of a proof about a committed witness, as produced by `prover::prove_principal_relation_committed`
+pub fn commit_and_prove_iopattern<R: PolyRing>(crs: &CommonReferenceString<R>) -> IOPattern
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    IOPattern::new("labrador_commit_and_prove")
+        .absorb_vector::<R>(crs.k1, "commitment")
+        .labrador_recursive_io(crs)
+}
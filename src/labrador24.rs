@@ -0,0 +1,440 @@
+//! Pure-Rust parser for the proofs of the `labrador24` C library, as serialized by the C backend in
+//! `prover::c_prover::serialize`.
+//!
+//! Polynomials are kept in the representation of the C library, as the `int16_t` coefficients of its
+//! `labrador24_vecn`s, which the encoding stores in little-endian order on every machine.
+//!
+//! There is no pure-Rust verifier for these proofs: the C library derives its commitment key and all Fiat-Shamir
+//! challenges, including the JL matrices, with its own AES-CTR and SHAKE-based samplers, so a verifier has to
+//! reimplement those bit for bit before it can check any of the commitments or the recursion between the proofs.
+//! Until then, a proof of the C backend is only accepted by `labrador24_composite_verify_simple`, see
+//! `backend::CBackend`.
+
+use std::fmt::{Display, Formatter};
+
+/// Degree of the polynomials of the C library
+pub const LABRADOR24_N: usize = 64;
+/// Number of `labrador24_poly` in a `labrador24_polx`
+pub const LABRADOR24_K: usize = 5;
+/// Number of limbs of a `labrador24_polz`
+pub const LABRADOR24_L: usize = 2;
+/// Bit length of the modulus that the C library is compiled for
+pub const LABRADOR24_LOGQ: u64 = 24;
+/// Modulus of the C library compiled with `-DLOGQ=24`, the largest prime $q \equiv 5 \pmod 8$ below $2^{24}$
+pub const LABRADOR24_Q: u64 = (1 << LABRADOR24_LOGQ) - 3;
+/// Length of the JL projection of a proof
+pub const JL_LEN: usize = 256;
+/// Number of integer-to-polynomial extensions in a proof
+pub const BB_LEN: usize = 4;
+/// Maximal number of proofs in a composite proof
+pub const MAX_PROOFS: usize = 16;
+
+pub type Poly = [i16; LABRADOR24_N];
+pub type Polx = [Poly; LABRADOR24_K];
+pub type Polz = [Poly; LABRADOR24_L];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Labrador24Error {
+    Malformed(&'static str),
+}
+
+impl Display for Labrador24Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Labrador24Error::Malformed(reason) => write!(f, "malformed proof: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Labrador24Error {}
+
+/// `labrador24_commitment`, with r = `n.len()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commitment {
+    pub n: Vec<usize>,
+    pub fu: usize,
+    pub bu: usize,
+    pub kappa: usize,
+    pub kappa1: usize,
+    pub u: Vec<Polz>,
+    pub alpha: Vec<Polx>,
+}
+
+/// `labrador24_comparams`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitmentParams {
+    pub f: usize,
+    pub fu: usize,
+    pub fg: usize,
+    pub b: usize,
+    pub bu: usize,
+    pub bg: usize,
+    pub kappa: usize,
+    pub kappa1: usize,
+    pub u1len: usize,
+    pub u2len: usize,
+}
+
+/// `labrador24_proof`, with r = `n.len()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proof {
+    pub n: Vec<usize>,
+    pub nu: Vec<usize>,
+    pub tail: i32,
+    pub cpp: CommitmentParams,
+    pub u1: Vec<Polz>,
+    pub jlnonce: usize,
+    pub p: Vec<i32>,
+    pub bb: Vec<Polz>,
+    pub u2: Vec<Polz>,
+    pub normsq: u64,
+}
+
+/// `labrador24_witness`, with r = `s.len()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputWitness {
+    pub normsq: Vec<u64>,
+    pub s: Vec<Vec<Poly>>,
+}
+
+/// The commitment and the `labrador24_composite` written by `labrador24_composite_prove_simple`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Labrador24Proof {
+    pub commitment: Commitment,
+    pub size: f64,
+    pub proofs: Vec<Proof>,
+    pub witness: OutputWitness,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], Labrador24Error> {
+        if self.bytes.len() < len {
+            return Err(Labrador24Error::Malformed("unexpected end of input"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, Labrador24Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, Labrador24Error> {
+        usize::try_from(self.u64()?).map_err(|_| Labrador24Error::Malformed("length overflow"))
+    }
+
+    /// Reject lengths that cannot fit in the remaining input before allocating anything
+    fn check_len(&self, len: usize, size: usize) -> Result<(), Labrador24Error> {
+        match len.checked_mul(size) {
+            Some(total) if total <= self.bytes.len() => Ok(()),
+            _ => Err(Labrador24Error::Malformed("length exceeds the input")),
+        }
+    }
+
+    fn usizes(&mut self, len: usize) -> Result<Vec<usize>, Labrador24Error> {
+        self.check_len(len, 8)?;
+        (0..len).map(|_| self.usize()).collect()
+    }
+
+    fn u64s(&mut self, len: usize) -> Result<Vec<u64>, Labrador24Error> {
+        self.check_len(len, 8)?;
+        (0..len).map(|_| self.u64()).collect()
+    }
+
+    fn i32s(&mut self, len: usize) -> Result<Vec<i32>, Labrador24Error> {
+        self.check_len(len, 4)?;
+        Ok(self
+            .take(4 * len)?
+            .chunks_exact(4)
+            .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+            .collect())
+    }
+
+    fn poly(&mut self) -> Result<Poly, Labrador24Error> {
+        let bytes = self.take(2 * LABRADOR24_N)?;
+        Ok(std::array::from_fn(|i| {
+            i16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]])
+        }))
+    }
+
+    fn polys<const K: usize>(&mut self, len: usize) -> Result<Vec<[Poly; K]>, Labrador24Error> {
+        self.check_len(len, 2 * LABRADOR24_N * K)?;
+        (0..len)
+            .map(|_| {
+                let mut x = [[0; LABRADOR24_N]; K];
+                for x_i in x.iter_mut() {
+                    *x_i = self.poly()?;
+                }
+                Ok(x)
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn usize(&mut self, x: usize) {
+        self.bytes.extend_from_slice(&(x as u64).to_le_bytes());
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn usizes(&mut self, xs: &[usize]) {
+        xs.iter().for_each(|x| self.usize(*x));
+    }
+
+    fn polys<const K: usize>(&mut self, xs: &[[Poly; K]]) {
+        for c in xs.iter().flatten().flatten() {
+            self.bytes.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+}
+
+impl Labrador24Proof {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Labrador24Error> {
+        let mut r = Reader { bytes };
+
+        let com_r = r.usize()?;
+        let n = r.usizes(com_r)?;
+        let (fu, bu, kappa, kappa1) = (r.usize()?, r.usize()?, r.usize()?, r.usize()?);
+        let u = r.polys::<LABRADOR24_L>(kappa1)?;
+        let alpha = r.polys::<LABRADOR24_K>(com_r)?;
+        let commitment = Commitment {
+            n,
+            fu,
+            bu,
+            kappa,
+            kappa1,
+            u,
+            alpha,
+        };
+
+        let l = r.usize()?;
+        if l > MAX_PROOFS {
+            return Err(Labrador24Error::Malformed("too many proofs"));
+        }
+        let size = f64::from_bits(r.u64()?);
+        let mut proofs = Vec::with_capacity(l);
+        for _ in 0..l {
+            let pi_r = r.usize()?;
+            let n = r.usizes(pi_r)?;
+            let nu = r.usizes(pi_r)?;
+            let tail = r.u64()? as i32;
+            let cpp = r.usizes(10)?;
+            let cpp = CommitmentParams {
+                f: cpp[0],
+                fu: cpp[1],
+                fg: cpp[2],
+                b: cpp[3],
+                bu: cpp[4],
+                bg: cpp[5],
+                kappa: cpp[6],
+                kappa1: cpp[7],
+                u1len: cpp[8],
+                u2len: cpp[9],
+            };
+            let u1 = r.polys::<LABRADOR24_L>(cpp.u1len)?;
+            let jlnonce = r.usize()?;
+            let p = r.i32s(JL_LEN)?;
+            let bb = r.polys::<LABRADOR24_L>(BB_LEN)?;
+            let u2 = r.polys::<LABRADOR24_L>(cpp.u2len)?;
+            let normsq = r.u64()?;
+            proofs.push(Proof {
+                n,
+                nu,
+                tail,
+                cpp,
+                u1,
+                jlnonce,
+                p,
+                bb,
+                u2,
+                normsq,
+            });
+        }
+
+        let owt_r = r.usize()?;
+        let owt_n = r.usizes(owt_r)?;
+        let normsq = r.u64s(owt_r)?;
+        let s = owt_n
+            .iter()
+            .map(|n_i| {
+                r.polys::<1>(*n_i)
+                    .map(|s_i| s_i.into_iter().map(|[x]| x).collect())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !r.bytes.is_empty() {
+            return Err(Labrador24Error::Malformed("trailing bytes"));
+        }
+
+        Ok(Labrador24Proof {
+            commitment,
+            size,
+            proofs,
+            witness: OutputWitness { normsq, s },
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        let com = &self.commitment;
+        w.usize(com.n.len());
+        w.usizes(&com.n);
+        w.usizes(&[com.fu, com.bu, com.kappa, com.kappa1]);
+        w.polys(&com.u);
+        w.polys(&com.alpha);
+
+        w.usize(self.proofs.len());
+        w.u64(self.size.to_bits());
+        for pi in &self.proofs {
+            w.usize(pi.n.len());
+            w.usizes(&pi.n);
+            w.usizes(&pi.nu);
+            w.u64(pi.tail as u64);
+            let cpp = &pi.cpp;
+            w.usizes(&[
+                cpp.f, cpp.fu, cpp.fg, cpp.b, cpp.bu, cpp.bg, cpp.kappa, cpp.kappa1, cpp.u1len,
+                cpp.u2len,
+            ]);
+            w.polys(&pi.u1);
+            w.usize(pi.jlnonce);
+            for p_i in &pi.p {
+                w.bytes.extend_from_slice(&p_i.to_le_bytes());
+            }
+            w.polys(&pi.bb);
+            w.polys(&pi.u2);
+            w.u64(pi.normsq);
+        }
+
+        let owt = &self.witness;
+        w.usize(owt.s.len());
+        w.usizes(&owt.s.iter().map(|s_i| s_i.len()).collect::<Vec<_>>());
+        owt.normsq.iter().for_each(|x| w.u64(*x));
+        for s_i in &owt.s {
+            w.polys(&s_i.iter().map(|x| [*x]).collect::<Vec<_>>());
+        }
+        w.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dummy_proof(n: usize) -> Labrador24Proof {
+        let cpp = CommitmentParams {
+            f: 2,
+            fu: 2,
+            fg: 2,
+            b: 1 << 12,
+            bu: 1 << 12,
+            bg: 1 << 12,
+            kappa: 3,
+            kappa1: 2,
+            u1len: 2,
+            u2len: 2,
+        };
+        let polz = [[1i16; LABRADOR24_N]; LABRADOR24_L];
+        Labrador24Proof {
+            commitment: Commitment {
+                n: vec![n],
+                fu: 2,
+                bu: 1 << 12,
+                kappa: 3,
+                kappa1: 2,
+                u: vec![polz; 2],
+                alpha: vec![[[-1i16; LABRADOR24_N]; LABRADOR24_K]],
+            },
+            size: 12.5,
+            proofs: vec![Proof {
+                n: vec![n],
+                nu: vec![1],
+                tail: -1,
+                cpp,
+                u1: vec![polz; cpp.u1len],
+                jlnonce: 7,
+                p: vec![1; JL_LEN],
+                bb: vec![polz; BB_LEN],
+                u2: vec![polz; cpp.u2len],
+                normsq: 100,
+            }],
+            witness: OutputWitness {
+                normsq: vec![3, 0],
+                s: vec![vec![[2i16; LABRADOR24_N]; 2], vec![]],
+            },
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let proof = dummy_proof(8);
+        let bytes = proof.to_bytes();
+        assert_eq!(Labrador24Proof::from_bytes(&bytes), Ok(proof));
+
+        assert!(Labrador24Proof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Labrador24Proof::from_bytes(&trailing),
+            Err(Labrador24Error::Malformed("trailing bytes"))
+        );
+    }
+
+    #[cfg(feature = "c-binding")]
+    mod c_binding {
+        use lattirust_arithmetic::linear_algebra::Vector;
+        use lattirust_arithmetic::ring::{PolyRing, Pow2CyclotomicPolyRing, Zq1};
+        use num_traits::Zero;
+        use relations::principal_relation::{Index, Instance, Witness};
+
+        use super::super::*;
+        use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+
+        type R = Pow2CyclotomicPolyRing<Zq1<LABRADOR24_Q>, 64>;
+
+        /// $\langle \phi, s_1 \rangle - \langle \phi, s_2 \rangle = 0$ for vectors of length 4, satisfied by s_1 = s_2
+        fn statement() -> (Index<R>, Instance<R>, Witness<R>) {
+            let n = 4;
+            let phi = Vector::<R>::from_fn(n, |j, _| {
+                R::from_scalar(Zq1::try_from((j + 1) as u128).unwrap())
+            });
+            let mut builder = RelationBuilder::<R>::new(n, 64.);
+            let (s_1, s_2) = (builder.witness("s_1"), builder.witness("s_2"));
+            builder.constrain(
+                QuadraticExpr::linear(phi.clone(), s_1) - QuadraticExpr::linear(phi, s_2),
+                R::zero(),
+            );
+            let mut s = Vector::<R>::zeros(n);
+            s[0] = R::from_scalar(Zq1::try_from(3u128).unwrap());
+            let mut assignment = builder.assignment();
+            assignment.assign(s_1, s.clone()).assign(s_2, s);
+            let witness = assignment.witness().unwrap();
+            let (index, instance) = builder.build();
+            (index, instance, witness)
+        }
+
+        #[test]
+        fn test_parse_c_proof() {
+            let (index, instance, witness) = statement();
+            let bytes =
+                crate::prover::c_prover::prove_principal_relation(&index, &instance, &witness)
+                    .unwrap();
+            let proof = Labrador24Proof::from_bytes(&bytes).unwrap();
+            assert_eq!(proof.to_bytes(), bytes);
+            // The C backend proves a statement about the concatenation of all witness vectors
+            assert_eq!(proof.commitment.n, vec![index.r * index.n]);
+            assert_eq!(proof.proofs[0].n, proof.commitment.n);
+        }
+    }
+}
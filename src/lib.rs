@@ -1,10 +1,22 @@
+pub mod backend;
 pub mod binary_r1cs;
+pub mod commitment;
 pub mod common_reference_string;
 pub mod falcon_agg;
+pub mod frontend;
+pub mod gf2;
 pub mod iopattern;
+pub mod labrador24;
+pub mod progress;
 pub mod prover;
+pub mod r1cs;
+pub mod relation_builder;
 pub mod shared;
+pub mod sparse;
+pub mod stacking;
+#[cfg(test)]
 mod test;
+#[cfg(test)]
+mod test_util;
 pub mod util;
 pub mod verifier;
-//pub mod r1cs;
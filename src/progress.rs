@@ -0,0 +1,165 @@
+//! Progress of long-running proofs, reported by `prover::prove_principal_relation_with_progress` to a `ProgressSink`,
+//! which is either a closure taking a `ProgressEvent` or `ProgressBars`, which draws them in the terminal.
+//! Events can be reported from several threads at once, so the events of a step may arrive out of order.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressEvent {
+    /// Round `round` of `num_rounds` started, counting from 1
+    RoundStarted { round: usize, num_rounds: usize },
+    /// `done` of the `total` JL projections of the witness of the current round are computed
+    JlProjection { done: usize, total: usize },
+    /// `done` of the `total` constraints of the folded instance of the current round are computed
+    FoldConstraints { done: usize, total: usize },
+    /// The last witness is sent, and the proof is done
+    Finished,
+}
+
+pub trait ProgressSink: Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+impl<F: Fn(ProgressEvent) + Sync> ProgressSink for F {
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Ignores all events
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _event: ProgressEvent) {}
+}
+
+/// Progress bars for the rounds of a proof and the steps of the current round
+pub struct ProgressBars {
+    _bars: MultiProgress,
+    rounds: ProgressBar,
+    steps: ProgressBar,
+}
+
+impl ProgressBars {
+    pub fn new() -> Self {
+        let bars = MultiProgress::new();
+        let rounds = bars.add(ProgressBar::new(0));
+        rounds.set_style(
+            ProgressStyle::with_template("round {pos}/{len} {elapsed_precise}").unwrap(),
+        );
+        let steps = bars.add(ProgressBar::new(0));
+        steps.set_style(
+            ProgressStyle::with_template("{msg:>16} [{bar:40}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        ProgressBars {
+            _bars: bars,
+            rounds,
+            steps,
+        }
+    }
+
+    fn set_step(&self, name: &'static str, done: usize, total: usize) {
+        self.steps.set_message(name);
+        self.steps.set_length(total as u64);
+        self.steps.set_position(done as u64);
+    }
+}
+
+impl Default for ProgressBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressSink for ProgressBars {
+    fn report(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::RoundStarted { round, num_rounds } => {
+                self.rounds.set_length(num_rounds as u64);
+                self.rounds.set_position(round as u64);
+                self.set_step("", 0, 0);
+            }
+            ProgressEvent::JlProjection { done, total } => {
+                self.set_step("JL projection", done, total)
+            }
+            ProgressEvent::FoldConstraints { done, total } => {
+                self.set_step("fold constraints", done, total)
+            }
+            ProgressEvent::Finished => {
+                self.steps.finish_and_clear();
+                self.rounds.finish_and_clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+
+    use super::*;
+    use crate::iopattern::principal_relation_iopattern;
+    use crate::prover::prove_principal_relation_with_progress;
+    use crate::test_util::inner_product_statement;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    #[test]
+    fn test_progress_events() {
+        let (crs, index, instance, witness) = inner_product_statement::<R>();
+        let io = principal_relation_iopattern(&crs);
+        let mut merlin = io.to_merlin();
+        let events = Mutex::new(vec![]);
+        let sink = |event: ProgressEvent| events.lock().unwrap().push(event);
+        let (_, stats) = prove_principal_relation_with_progress(
+            &mut merlin,
+            &crs,
+            &index,
+            &instance,
+            &witness,
+            &sink,
+        )
+        .unwrap();
+        let events = events.into_inner().unwrap();
+        let num_rounds = stats.rounds.len();
+
+        assert_eq!(events.last(), Some(&ProgressEvent::Finished));
+        let rounds = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::RoundStarted { round, num_rounds } => Some((*round, *num_rounds)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rounds,
+            (1..=num_rounds)
+                .map(|round| (round, num_rounds))
+                .collect::<Vec<_>>()
+        );
+        // Every projection and every folded constraint of each round is reported once
+        let count = |is_step: fn(&ProgressEvent) -> Option<(usize, usize)>| {
+            let steps = events.iter().filter_map(is_step).collect::<Vec<_>>();
+            assert!(steps.iter().all(|(done, total)| 0 < *done && done <= total));
+            (
+                steps.len(),
+                steps.iter().filter(|(done, total)| done == total).count(),
+            )
+        };
+        let (num_projections, rounds_projected) = count(|event| match event {
+            ProgressEvent::JlProjection { done, total } => Some((*done, *total)),
+            _ => None,
+        });
+        assert_eq!(num_projections, 256 * num_rounds);
+        assert_eq!(rounds_projected, num_rounds);
+        let (_, rounds_folded) = count(|event| match event {
+            ProgressEvent::FoldConstraints { done, total } => Some((*done, *total)),
+            _ => None,
+        });
+        assert_eq!(rounds_folded, num_rounds);
+    }
+}
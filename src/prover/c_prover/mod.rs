@@ -1,113 +1,217 @@
-#[cfg(feature = "c-binding")]
-use std::fmt::Debug;
+//! Backend that proves and verifies principal relations with the `labrador24` C library of LaZer.
+//!
+//! The C library only supports linear constraints $\sum_i \langle \phi_i, s_i \rangle = b$ over the ring of dimension
+//! `LABRADOR24_N`, with the modulus it is compiled for (`-DLOGQ=24`), and does its own Fiat-Shamir.
+//! All witness vectors are concatenated into a single vector for the C library, so that the norm bound on the whole
+//! witness is the same as in the principal relation.
 
-#[cfg(feature = "c-binding")]
-use lattirust_arithmetic::{
-    challenge_set::{
-        labrador_challenge_set::LabradorChallengeSet, weighted_ternary::WeightedTernaryChallengeSet,
-    },
-    decomposition::DecompositionFriendlySignedRepresentative,
-    ring::{representatives::WithSignedRepresentative, PolyRing},
-    traits::FromRandomBytes,
-};
-#[cfg(feature = "c-binding")]
-use nimue::{Merlin, ProofResult};
-#[cfg(feature = "c-binding")]
+use std::fmt::{Display, Formatter};
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use lattirust_arithmetic::ring::{representatives::WithSignedRepresentative, PolyRing};
 use relations::principal_relation::{Index, Instance, Witness};
 
-#[cfg(feature = "c-binding")]
-use crate::common_reference_string::CommonReferenceString;
+use crate::prover::c_prover::serialize::{deserialize_proof, serialize_proof};
+use crate::prover::c_prover::types::{CStatement, CWitness};
 
+pub mod serialize;
 pub mod types;
 
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+/// Bindings to `labrador.h` generated by bindgen
+mod bindings {
+    #![allow(
+        non_upper_case_globals,
+        non_camel_case_types,
+        non_snake_case,
+        dead_code
+    )]
+
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+pub use bindings::*;
+
+pub use crate::labrador24::{LABRADOR24_LOGQ, LABRADOR24_Q};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CBackendError {
+    /// The ring does not have dimension `LABRADOR24_N` and modulus `LABRADOR24_Q`
+    UnsupportedRing {
+        dimension: usize,
+        modulus: BigUint,
+    },
+    /// The k-th constraint is quadratic, which the C library does not support
+    UnsupportedConstraint(usize),
+    /// The instance has constant constraints, which the C library does not support
+    UnsupportedConstantConstraints,
+    /// A coefficient does not fit in the 64-bit integers that the C library takes
+    CoefficientOutOfRange,
+    /// An index passed to the C library is not less than the number of entries
+    IndexOutOfRange {
+        what: &'static str,
+        index: usize,
+        len: usize,
+    },
+    LengthMismatch {
+        what: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// A linear constraint refers to the same witness vector twice
+    DuplicateWitnessIndex(usize),
+    /// The index-th witness vector or constraint was never set
+    Unset {
+        what: &'static str,
+        index: usize,
+    },
+    /// A call to the C library returned a non-zero status
+    CallFailed {
+        function: &'static str,
+        status: i32,
+    },
+    MalformedProof(&'static str),
+    InvalidProof,
+}
+
+impl Display for CBackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CBackendError::UnsupportedRing { dimension, modulus } => write!(
+                f,
+                "the C library needs a ring of dimension {LABRADOR24_N} with modulus {LABRADOR24_Q}, but got dimension {dimension} and modulus {modulus}"
+            ),
+            CBackendError::UnsupportedConstraint(k) => write!(
+                f,
+                "constraint {k} is quadratic, but the C library only supports linear constraints"
+            ),
+            CBackendError::UnsupportedConstantConstraints => write!(
+                f,
+                "the C library does not support constraints on constant coefficients"
+            ),
+            CBackendError::CoefficientOutOfRange => {
+                write!(f, "coefficient does not fit in a 64-bit integer")
+            }
+            CBackendError::IndexOutOfRange { what, index, len } => {
+                write!(f, "{what} {index} is out of range, there are {len}")
+            }
+            CBackendError::LengthMismatch {
+                what,
+                expected,
+                found,
+            } => write!(f, "expected {expected} {what}, found {found}"),
+            CBackendError::DuplicateWitnessIndex(i) => {
+                write!(f, "witness vector {i} appears twice in a constraint")
+            }
+            CBackendError::Unset { what, index } => write!(f, "{what} {index} is not set"),
+            CBackendError::CallFailed { function, status } => {
+                write!(f, "{function} failed with status {status}")
+            }
+            CBackendError::MalformedProof(reason) => write!(f, "malformed proof: {reason}"),
+            CBackendError::InvalidProof => write!(f, "invalid proof"),
+        }
+    }
+}
+
+impl std::error::Error for CBackendError {}
+
+/// Signed coefficients of a ring element
+fn to_int64<R: PolyRing>(x: &R) -> Result<Vec<i64>, CBackendError>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative: ToPrimitive,
+{
+    x.coefficients()
+        .iter()
+        .map(|c| {
+            c.as_signed_representative()
+                .to_i64()
+                .ok_or(CBackendError::CoefficientOutOfRange)
+        })
+        .collect()
+}
 
-#[cfg(feature = "c-binding")]
-pub fn prove_principal_relation<'a, R: PolyRing>(
-    _merlin: &'a mut Merlin,
-    mut crs: &CommonReferenceString<R>,
+/// Translate the index and instance to a C statement about the concatenation of all witness vectors
+fn statement<R: PolyRing>(
     index: &Index<R>,
     instance: &Instance<R>,
-    witness: &Witness<R>,
-) -> ProofResult<&'a [u8]>
+) -> Result<CStatement, CBackendError>
 where
-    LabradorChallengeSet<R>: FromRandomBytes<R>,
-    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
     <R as PolyRing>::BaseRing: WithSignedRepresentative,
-    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
-        DecompositionFriendlySignedRepresentative,
-    <R as TryFrom<u128>>::Error: Debug,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative: ToPrimitive,
 {
-    use std::mem::MaybeUninit;
-    use std::slice;
-
-    unsafe {
-        // 1) Initialize the commitment key (comkey) from the CRS size.
-        //    In Lazer this is: lib.labrador32_init_comkey(n)
-
-        use crate::prover::c_prover::types::ProofStatement;
-        labrador24_init_comkey(crs.max_witness_polys()); // ❓ exact `n` needs confirmation :contentReference[oaicite:9]{index=9}
-
-        let ps = ProofStatement::new(deg_list, num_pols_list, norm_list, num_constraints);
-
-        // 3) Populate each witness vector into the C struct.
-        for (i, vec) in witness.vectors().enumerate() {
-            let coeffs: Vec<i64> = vec.to_int64_array();
-            let ret = labrador24_set_witness_vector_raw(
-                c_witness.as_mut_ptr(),
-                i,
-                vec.num_polys(),
-                vec.len_in_blocks(),
-                coeffs.as_ptr(),
-            ); // :contentReference[oaicite:12]{index=12}
-            assert!(ret == 0, "setting witness vector failed");
-        }
+    if R::dimension() != LABRADOR24_N as usize || R::modulus() != BigUint::from(LABRADOR24_Q) {
+        return Err(CBackendError::UnsupportedRing {
+            dimension: R::dimension(),
+            modulus: R::modulus(),
+        });
+    }
+    if let Some(k) = instance
+        .quad_dot_prod_funcs
+        .iter()
+        .position(|constraint| constraint.A.is_some())
+    {
+        return Err(CBackendError::UnsupportedConstraint(k));
+    }
+    if !instance.ct_quad_dot_prod_funcs.is_empty() {
+        return Err(CBackendError::UnsupportedConstantConstraints);
+    }
 
-        // 4) Populate each linear constraint into the C stmt.
-        for (i, cnst) in index.constraints().enumerate() {
-            let a_arr: Vec<i64> = cnst.a.to_int64_array();
-            let b_arr: Vec<i64> = cnst.b.to_int64_array();
-            let ret = labrador24_set_smplstmnt_lincnst_raw(
-                c_statement.as_mut_ptr(),
-                i,
-                cnst.num_terms(),
-                cnst.witness_indices().as_ptr(),
-                cnst.term_dims().as_ptr(),
-                cnst.poly_deg_in_blocks(),
-                a_arr.as_ptr(),
-                b_arr.as_ptr(),
-            );
-            assert!(ret == 0, "setting constraint failed");
+    let mut st = CStatement::new(
+        &[index.r * index.n],
+        &[index.norm_bound_squared.floor() as u64],
+        instance.quad_dot_prod_funcs.len(),
+    )?;
+    for (k, constraint) in instance.quad_dot_prod_funcs.iter().enumerate() {
+        let mut phi = Vec::<i64>::with_capacity(index.r * index.n * R::dimension());
+        for phi_i in &constraint.phi {
+            for phi_ij in phi_i.iter() {
+                phi.extend(to_int64(phi_ij)?);
+            }
         }
+        st.set_linear_constraint(k, &[0], &phi, &to_int64(&constraint.b)?)?;
+    }
+    Ok(st)
+}
 
-        // 5) Call into the C prover.
-        let mut c_prf = MaybeUninit::<proof>::zeroed();
-        let mut c_comm = MaybeUninit::<witness>::zeroed();
-        let rc = prove(
-            c_statement.as_mut_ptr(),
-            c_witness.as_mut_ptr(),
-            c_prf.as_mut_ptr(),
-            c_statement.as_ptr(),
-            c_witness.as_ptr(),
-            /* tail = */ 0,
-        ); // :contentReference[oaicite:13]{index=13}
-
-        if rc != 0 {
-            return Err(ProofError::ProverFailed(rc));
-        }
+/// Prove the principal relation with the C library, and return the serialized commitment and composite proof.
+/// Fails if the ring or the constraints are not supported, see `CBackendError`.
+pub fn prove_principal_relation<R: PolyRing>(
+    index: &Index<R>,
+    instance: &Instance<R>,
+    witness: &Witness<R>,
+) -> Result<Vec<u8>, CBackendError>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative: ToPrimitive,
+{
+    let st = statement(index, instance)?;
 
-        // 6) Serialize the proof struct into a byte slice.
-        //    TODO: we need a C API (e.g. `serialize_proof`) or define
-        //          how `proof` should be turned into &[u8].
-        //
-        // For now, we simply expose the raw bytes of the `proof` struct:
-        let prf_ptr = c_prf.as_ptr() as *const u8;
-        let prf_len = std::mem::size_of::<proof>();
-        let proof_bytes = slice::from_raw_parts(prf_ptr, prf_len);
+    let mut s = Vec::<i64>::with_capacity(index.r * index.n * R::dimension());
+    for s_i in &witness.s {
+        for s_ij in s_i.iter() {
+            s.extend(to_int64(s_ij)?);
+        }
+    }
+    let mut c_witness = CWitness::new(&[index.r * index.n]);
+    c_witness.set(0, &s)?;
 
-        // 7) Clean up if needed (e.g. free_comkey, free_witness, free_statement).
-        labrador24_free_comkey(); // :contentReference[oaicite:14]{index=14}
+    let proof = st.prove(&c_witness)?;
+    Ok(serialize_proof(proof.commitment(), proof.composite()))
+}
 
-        Ok(proof_bytes)
-    }
+/// Verify a proof produced by `prove_principal_relation` with the C library
+pub fn verify_principal_relation<R: PolyRing>(
+    index: &Index<R>,
+    instance: &Instance<R>,
+    proof: &[u8],
+) -> Result<(), CBackendError>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative: ToPrimitive,
+{
+    let st = statement(index, instance)?;
+    let proof = deserialize_proof(proof)?;
+    st.verify(&proof)
 }
@@ -0,0 +1,392 @@
+//! Byte encoding of the commitment and the composite proof written by `labrador24_composite_prove_simple`.
+//!
+//! All integers are encoded as little-endian 64-bit values, the JL projection p as little-endian 32-bit values, and
+//! arrays of `labrador24_poly`, `labrador24_polx` and `labrador24_polz` as their `int16_t` coefficients in little-endian
+//! order, so that the encoding does not depend on the machine. Each array is preceded by the lengths it is allocated
+//! with:
+//! - commitment: r, n[r], fu, bu, kappa, kappa1, u[kappa1], alpha[r]
+//! - composite: l, size, then l proofs and the output witness
+//! - proof: r, n[r], nu[r], tail, the commitment parameters, u1[u1len], jlnonce, p[256], bb[LABRADOR24_BB_LEN], u2[u2len], normsq
+//! - witness: r, n[r], normsq[r], s_i[n_i] for all i
+//!
+//! `crate::labrador24::Labrador24Proof` parses the same encoding without the C library.
+
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use crate::prover::c_prover::{
+    labrador24_commitment, labrador24_comparams, labrador24_composite, labrador24_polx,
+    labrador24_poly, labrador24_polz, labrador24_proof, labrador24_witness, CBackendError,
+};
+
+/// Number of `labrador24_polz` in the integer-to-polynomial extensions `bb` of a proof
+const LABRADOR24_BB_LEN: usize = 4;
+
+/// Maximal number of proofs in a composite proof
+const MAX_PROOFS: usize = 16;
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn usize(&mut self, x: usize) {
+        self.bytes.extend_from_slice(&(x as u64).to_le_bytes());
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+
+    /// Append the coefficients of `len` polynomials starting at `ptr`, where `T` is one of `labrador24_poly`,
+    /// `labrador24_polx` and `labrador24_polz`, which consist of `int16_t` coefficients only
+    unsafe fn polys<T>(&mut self, ptr: *const T, len: usize) {
+        if len > 0 {
+            let coeffs = slice::from_raw_parts(ptr as *const i16, len * mem::size_of::<T>() / 2);
+            for c in coeffs {
+                self.bytes.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+    }
+
+    unsafe fn usizes(&mut self, ptr: *const usize, len: usize) {
+        for i in 0..len {
+            self.usize(*ptr.add(i));
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], CBackendError> {
+        if self.bytes.len() < len {
+            return Err(CBackendError::MalformedProof("unexpected end of input"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, CBackendError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, CBackendError> {
+        usize::try_from(self.u64()?).map_err(|_| CBackendError::MalformedProof("length overflow"))
+    }
+
+    fn usizes(&mut self, len: usize) -> Result<Vec<usize>, CBackendError> {
+        self.check_len(len, 8)?;
+        (0..len).map(|_| self.usize()).collect()
+    }
+
+    fn u64s(&mut self, len: usize) -> Result<Vec<u64>, CBackendError> {
+        self.check_len(len, 8)?;
+        (0..len).map(|_| self.u64()).collect()
+    }
+
+    fn i32s(&mut self, len: usize) -> Result<Vec<i32>, CBackendError> {
+        self.check_len(len, 4)?;
+        Ok(self
+            .take(4 * len)?
+            .chunks_exact(4)
+            .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+            .collect())
+    }
+
+    /// Read `len` polynomials written by `Writer::polys`, for which every bit pattern of the coefficients is valid
+    fn polys<T>(&mut self, len: usize) -> Result<Vec<T>, CBackendError> {
+        let size = mem::size_of::<T>();
+        self.check_len(len, size)?;
+        let bytes = self.take(len * size)?;
+        let mut values = Vec::<T>::with_capacity(len);
+        unsafe {
+            let coeffs = slice::from_raw_parts_mut(values.as_mut_ptr() as *mut i16, len * size / 2);
+            for (c, b) in coeffs.iter_mut().zip(bytes.chunks_exact(2)) {
+                *c = i16::from_le_bytes([b[0], b[1]]);
+            }
+            values.set_len(len);
+        }
+        Ok(values)
+    }
+
+    fn comparams(&mut self) -> Result<labrador24_comparams, CBackendError> {
+        let cpp = self.usizes(10)?;
+        Ok(labrador24_comparams {
+            f: cpp[0],
+            fu: cpp[1],
+            fg: cpp[2],
+            b: cpp[3],
+            bu: cpp[4],
+            bg: cpp[5],
+            kappa: cpp[6],
+            kappa1: cpp[7],
+            u1len: cpp[8],
+            u2len: cpp[9],
+        })
+    }
+
+    /// Reject lengths that cannot fit in the remaining input before allocating anything
+    fn check_len(&self, len: usize, size: usize) -> Result<(), CBackendError> {
+        match len.checked_mul(size) {
+            Some(total) if total <= self.bytes.len() => Ok(()),
+            _ => Err(CBackendError::MalformedProof("length exceeds the input")),
+        }
+    }
+}
+
+pub(crate) fn serialize_proof(
+    commitment: &labrador24_commitment,
+    composite: &labrador24_composite,
+) -> Vec<u8> {
+    let mut w = Writer { bytes: vec![] };
+    unsafe {
+        let com = commitment;
+        w.usize(com.r);
+        w.usizes(com.n, com.r);
+        for x in [com.fu, com.bu, com.kappa, com.kappa1] {
+            w.usize(x);
+        }
+        w.polys(com.u, com.kappa1);
+        w.polys(com.alpha, com.r);
+
+        w.usize(composite.l);
+        w.u64(composite.size.to_bits());
+        for pi in &composite.pi[..composite.l] {
+            let pi = &**pi;
+            w.usize(pi.r);
+            w.usizes(pi.n, pi.r);
+            w.usizes(pi.nu, pi.r);
+            w.u64(pi.tail as u64);
+            let cpp = &pi.cpp[0];
+            for x in [
+                cpp.f, cpp.fu, cpp.fg, cpp.b, cpp.bu, cpp.bg, cpp.kappa, cpp.kappa1, cpp.u1len,
+                cpp.u2len,
+            ] {
+                w.usize(x);
+            }
+            w.polys(pi.u1, cpp.u1len);
+            w.usize(pi.jlnonce);
+            for p_i in &pi.p {
+                w.bytes.extend_from_slice(&p_i.to_le_bytes());
+            }
+            w.polys(pi.bb, LABRADOR24_BB_LEN);
+            w.polys(pi.u2, cpp.u2len);
+            w.u64(pi.normsq);
+        }
+
+        let owt = &composite.owt;
+        w.usize(owt.r);
+        w.usizes(owt.n, owt.r);
+        for i in 0..owt.r {
+            w.u64(*owt.normsq.add(i));
+        }
+        for i in 0..owt.r {
+            w.polys(*owt.s.add(i), *owt.n.add(i));
+        }
+    }
+    w.bytes
+}
+
+struct ProofBuffers {
+    proof: Box<labrador24_proof>,
+    n: Vec<usize>,
+    nu: Vec<usize>,
+    _u1: Vec<labrador24_polz>,
+    _bb: Vec<labrador24_polz>,
+    _u2: Vec<labrador24_polz>,
+}
+
+/// A commitment and composite proof decoded from bytes.
+/// The C structs point into buffers owned by Rust, so they must only be read by the C library, never freed.
+pub(crate) struct DeserializedProof {
+    pub(crate) commitment: labrador24_commitment,
+    pub(crate) composite: labrador24_composite,
+    commitment_buffers: (Vec<usize>, Vec<labrador24_polz>, Vec<labrador24_polx>),
+    proofs: Vec<ProofBuffers>,
+    witness_buffers: (
+        Vec<usize>,
+        Vec<u64>,
+        Vec<Vec<labrador24_poly>>,
+        Vec<*mut labrador24_poly>,
+    ),
+}
+
+impl DeserializedProof {
+    /// Check the lengths that the C verifier reads without checking them against a statement about witness vectors of
+    /// `n[i]` polynomials: the commitment and the first proof must be about the same witness vectors, with the
+    /// commitment parameters of the first proof, and every proof and the output witness must have non-empty vectors and
+    /// outer commitments of rank kappa1
+    pub(crate) fn check_dimensions(&self, n: &[usize]) -> Result<(), CBackendError> {
+        let check = |cond: bool, reason: &'static str| {
+            if cond {
+                Ok(())
+            } else {
+                Err(CBackendError::MalformedProof(reason))
+            }
+        };
+        let com = &self.commitment;
+        let com_n = &self.commitment_buffers.0;
+        check(
+            com.r == n.len() && *com_n == n,
+            "commitment does not match the statement",
+        )?;
+
+        let first = &self.proofs[0];
+        check(first.n == n, "first proof does not match the statement")?;
+        let cpp = &first.proof.cpp[0];
+        check(
+            (com.fu, com.bu, com.kappa, com.kappa1) == (cpp.fu, cpp.bu, cpp.kappa, cpp.kappa1),
+            "commitment parameters do not match the first proof",
+        )?;
+
+        for buffers in &self.proofs {
+            let (pi, cpp) = (&buffers.proof, &buffers.proof.cpp[0]);
+            check(
+                pi.r > 0 && buffers.n.iter().all(|n_i| *n_i > 0),
+                "empty witness vector",
+            )?;
+            check(
+                buffers.nu.iter().all(|nu_i| *nu_i > 0),
+                "zero decomposition parts",
+            )?;
+            check(
+                cpp.kappa1 > 0 && cpp.u1len == cpp.kappa1 && cpp.u2len == cpp.kappa1,
+                "outer commitment length does not match kappa1",
+            )?;
+        }
+
+        let owt_n = &self.witness_buffers.0;
+        check(
+            !owt_n.is_empty() && owt_n.iter().all(|n_i| *n_i > 0),
+            "empty output witness",
+        )
+    }
+}
+
+pub(crate) fn deserialize_proof(bytes: &[u8]) -> Result<DeserializedProof, CBackendError> {
+    let mut r = Reader { bytes };
+
+    let com_r = r.usize()?;
+    let mut com_n = r.usizes(com_r)?;
+    let (fu, bu, kappa, kappa1) = (r.usize()?, r.usize()?, r.usize()?, r.usize()?);
+    let mut u = r.polys::<labrador24_polz>(kappa1)?;
+    let mut alpha = r.polys::<labrador24_polx>(com_r)?;
+    let commitment = labrador24_commitment {
+        r: com_r,
+        n: com_n.as_mut_ptr(),
+        fu,
+        bu,
+        kappa,
+        kappa1,
+        u: u.as_mut_ptr(),
+        alpha: alpha.as_mut_ptr(),
+    };
+
+    let l = r.usize()?;
+    if l == 0 {
+        return Err(CBackendError::MalformedProof("no proofs"));
+    }
+    if l > MAX_PROOFS {
+        return Err(CBackendError::MalformedProof("too many proofs"));
+    }
+    let size = f64::from_bits(r.u64()?);
+    let mut proofs = Vec::<ProofBuffers>::with_capacity(l);
+    for _ in 0..l {
+        let pi_r = r.usize()?;
+        let mut n = r.usizes(pi_r)?;
+        let mut nu = r.usizes(pi_r)?;
+        let tail = r.u64()? as i32;
+        let cpp = r.comparams()?;
+        let mut u1 = r.polys::<labrador24_polz>(cpp.u1len)?;
+        let jlnonce = r.usize()?;
+        let p = r.i32s(256)?;
+        let mut bb = r.polys::<labrador24_polz>(LABRADOR24_BB_LEN)?;
+        let mut u2 = r.polys::<labrador24_polz>(cpp.u2len)?;
+        let normsq = r.u64()?;
+        let proof = Box::new(labrador24_proof {
+            r: pi_r,
+            n: n.as_mut_ptr(),
+            nu: nu.as_mut_ptr(),
+            tail,
+            cpp: [cpp],
+            u1: u1.as_mut_ptr(),
+            jlnonce,
+            p: p.try_into().unwrap(),
+            bb: bb.as_mut_ptr(),
+            u2: u2.as_mut_ptr(),
+            normsq,
+        });
+        proofs.push(ProofBuffers {
+            proof,
+            n,
+            nu,
+            _u1: u1,
+            _bb: bb,
+            _u2: u2,
+        });
+    }
+
+    let owt_r = r.usize()?;
+    let mut owt_n = r.usizes(owt_r)?;
+    let mut owt_normsq = r.u64s(owt_r)?;
+    let mut owt_s = owt_n
+        .iter()
+        .map(|n_i| r.polys::<labrador24_poly>(*n_i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut owt_s_ptrs = owt_s
+        .iter_mut()
+        .map(|s_i| s_i.as_mut_ptr())
+        .collect::<Vec<_>>();
+    if !r.bytes.is_empty() {
+        return Err(CBackendError::MalformedProof("trailing bytes"));
+    }
+
+    let mut pi = [ptr::null_mut(); MAX_PROOFS];
+    for (pi_i, buffers) in pi.iter_mut().zip(proofs.iter_mut()) {
+        *pi_i = &mut *buffers.proof;
+    }
+    let composite = labrador24_composite {
+        l,
+        size,
+        pi,
+        owt: labrador24_witness {
+            r: owt_r,
+            n: owt_n.as_mut_ptr(),
+            normsq: owt_normsq.as_mut_ptr(),
+            s: owt_s_ptrs.as_mut_ptr(),
+        },
+    };
+
+    Ok(DeserializedProof {
+        commitment,
+        composite,
+        commitment_buffers: (com_n, u, alpha),
+        proofs,
+        witness_buffers: (owt_n, owt_normsq, owt_s, owt_s_ptrs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_rejects_malformed_input() {
+        assert!(deserialize_proof(&[]).is_err());
+        // A commitment that claims more vectors than there are bytes
+        assert_eq!(
+            deserialize_proof(&u64::MAX.to_le_bytes()).err(),
+            Some(CBackendError::MalformedProof("length exceeds the input"))
+        );
+        // An empty commitment followed by a composite proof with l = 0
+        assert_eq!(
+            deserialize_proof(&[0u8; 6 * 8]).err(),
+            Some(CBackendError::MalformedProof("no proofs"))
+        );
+    }
+}
@@ -1,79 +1,334 @@
-use std::mem::{self, MaybeUninit};
+//! Owners of the `labrador24_*` C objects.
+//!
+//! Each wrapper initializes its C object in its constructor and frees it in `drop`, so a C object is never used before
+//! it is initialized or freed twice. Setters check their indices and lengths before calling into C, and the prover
+//! only runs once every witness vector and constraint has been set.
+//! The C library keeps its commitment key in a global, which `ComKey` guards, so the C backend can be called from
+//! several threads, but only one prover or verifier runs in the C library at a time.
 
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::prover::c_prover::serialize::DeserializedProof;
 use crate::prover::c_prover::{
-    labrador24_commitment, labrador24_composite, labrador24_free_comkey, labrador24_free_commitment, labrador24_free_composite, labrador24_free_smplstmnt, labrador24_free_witness, labrador24_init_smplstmnt_raw, labrador24_init_witness_raw, labrador24_smplstmnt, labrador24_witness
+    labrador24_commitment, labrador24_composite, labrador24_composite_prove_simple,
+    labrador24_composite_verify_simple, labrador24_free_comkey, labrador24_free_commitment,
+    labrador24_free_composite, labrador24_free_smplstmnt, labrador24_free_witness,
+    labrador24_init_comkey, labrador24_init_smplstmnt_raw, labrador24_init_witness_raw,
+    labrador24_set_smplstmnt_lincnst_raw, labrador24_set_witness_vector_raw,
+    labrador24_simple_verify, labrador24_smplstmnt, labrador24_witness, CBackendError,
+    LABRADOR24_N,
 };
 
-pub(crate) struct ProofStatement {
-    cur_witness_num: usize,
-    cur_constraint_num: usize,
-    witness_polys: usize,
-    witness_ptr: Box<labrador24_witness>,
-    smplstmnt_ptr: Box<labrador24_smplstmnt>,
-    commitment_ptr: Box<labrador24_commitment>,
-    composite_ptr: Box<labrador24_composite>,
-}
-
-impl ProofStatement {
-    pub(crate) fn new(
-        deg_list: &[usize],
-        num_pols_list: &[usize],
-        norm_list: &[f64],
-        num_constraints: usize,
-    ) -> Self {
+const N: usize = LABRADOR24_N as usize;
+
+/// Return an error for the index unless it is less than len
+fn check_index(what: &'static str, index: usize, len: usize) -> Result<(), CBackendError> {
+    if index >= len {
+        return Err(CBackendError::IndexOutOfRange { what, index, len });
+    }
+    Ok(())
+}
+
+fn check_len(what: &'static str, expected: usize, found: usize) -> Result<(), CBackendError> {
+    if expected != found {
+        return Err(CBackendError::LengthMismatch {
+            what,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+fn check_status(function: &'static str, status: i32) -> Result<(), CBackendError> {
+    if status != 0 {
+        return Err(CBackendError::CallFailed { function, status });
+    }
+    Ok(())
+}
+
+/// Return an error for the first entry that has not been set
+fn check_all_set(what: &'static str, is_set: &[bool]) -> Result<(), CBackendError> {
+    match is_set.iter().position(|set| !set) {
+        Some(index) => Err(CBackendError::Unset { what, index }),
+        None => Ok(()),
+    }
+}
+
+/// A `labrador24_witness` of r vectors, the i-th of which has `n[i]` polynomials of degree `LABRADOR24_N`
+pub(crate) struct CWitness {
+    raw: Box<labrador24_witness>,
+    n: Vec<usize>,
+    is_set: Vec<bool>,
+}
+
+impl CWitness {
+    pub(crate) fn new(n: &[usize]) -> Self {
+        let mut n = n.to_vec();
+        // Initialized by the C library right away, and only freed in `drop`
+        let mut raw: Box<labrador24_witness> = Box::new(unsafe { mem::zeroed() });
+        unsafe { labrador24_init_witness_raw(&mut *raw, n.len(), n.as_mut_ptr()) };
+        let is_set = vec![false; n.len()];
+        CWitness { raw, n, is_set }
+    }
+
+    /// Set the i-th vector to the polynomials with coefficients `coeffs`, `LABRADOR24_N` per polynomial
+    pub(crate) fn set(&mut self, i: usize, coeffs: &[i64]) -> Result<(), CBackendError> {
+        check_index("witness vector", i, self.n.len())?;
+        check_len("witness vector coefficients", self.n[i] * N, coeffs.len())?;
+        let status = unsafe {
+            labrador24_set_witness_vector_raw(&mut *self.raw, i, self.n[i], N, coeffs.as_ptr())
+        };
+        check_status("labrador24_set_witness_vector_raw", status)?;
+        self.is_set[i] = true;
+        Ok(())
+    }
+}
+
+impl Drop for CWitness {
+    fn drop(&mut self) {
+        unsafe { labrador24_free_witness(&mut *self.raw) };
+    }
+}
+
+/// A `labrador24_smplstmnt` about r witness vectors of `n[i]` polynomials and squared norm at most `betasq[i]`, with k
+/// linear constraints
+pub(crate) struct CStatement {
+    raw: Box<labrador24_smplstmnt>,
+    n: Vec<usize>,
+    is_set: Vec<bool>,
+}
+
+impl CStatement {
+    pub(crate) fn new(n: &[usize], betasq: &[u64], k: usize) -> Result<Self, CBackendError> {
+        check_len("norm bounds", n.len(), betasq.len())?;
+        let mut n = n.to_vec();
+        let mut betasq = betasq.to_vec();
+        // Initialized by the C library right away, and only freed in `drop`
+        let mut raw: Box<labrador24_smplstmnt> = Box::new(unsafe { mem::zeroed() });
         unsafe {
-            assert_eq!(deg_list.len(), num_pols_list.len());
-            assert_eq!(num_pols_list.len(), norm_list.len());
-            // 2) Allocate & initialize the raw C witness & statement.
-            // This corresponds to initializing `proof_statement` instance in LaZer
-            let mut witness_ptr: Box<labrador24_witness> = Box::new(mem::zeroed());
-            let mut smplstmnt_ptr: Box<labrador24_smplstmnt> = Box::new(mem::zeroed());
-            let commitment_ptr: Box<labrador24_commitment> =
-                Box::new(mem::zeroed());
-            let composite_ptr: Box<labrador24_composite> = Box::new(mem::zeroed());
-
-            let mut dim_ar: Vec<usize> = deg_list
-                .iter()
-                .zip(num_pols_list.iter())
-                .map(|(&deg, &npol)| (npol * deg) / 64)
-                .collect();
-
-            labrador24_init_witness_raw(
-                &mut *witness_ptr,
-                num_pols_list.len(),
-                dim_ar.as_mut_ptr(),
-            );
-
-            let mut norms_ar: Vec<u64> = vec![0; norm_list.len()];
             labrador24_init_smplstmnt_raw(
-                &mut *smplstmnt_ptr,
-                num_pols_list.len(),
-                dim_ar.as_mut_ptr(),
-                norms_ar.as_mut_ptr(),
-                num_constraints,
-            );
-
-            ProofStatement {
-                witness_ptr,
-                smplstmnt_ptr,
-                commitment_ptr,
-                composite_ptr,
-                cur_witness_num: 0,
-                cur_constraint_num: 0,
-                witness_polys: 0,
+                &mut *raw,
+                n.len(),
+                n.as_mut_ptr(),
+                betasq.as_mut_ptr(),
+                k,
+            )
+        };
+        Ok(CStatement {
+            raw,
+            n,
+            is_set: vec![false; k],
+        })
+    }
+
+    /// Set the i-th constraint to $\sum_j \langle \phi_j, s_{idx_j} \rangle = b$, where `phi` holds the coefficients of
+    /// all $\phi_j$ one after the other
+    pub(crate) fn set_linear_constraint(
+        &mut self,
+        i: usize,
+        idx: &[usize],
+        phi: &[i64],
+        b: &[i64],
+    ) -> Result<(), CBackendError> {
+        check_index("constraint", i, self.is_set.len())?;
+        for (j, &idx_j) in idx.iter().enumerate() {
+            check_index("witness vector", idx_j, self.n.len())?;
+            if idx[..j].contains(&idx_j) {
+                return Err(CBackendError::DuplicateWitnessIndex(idx_j));
             }
         }
+        let mut n = idx.iter().map(|j| self.n[*j]).collect::<Vec<_>>();
+        check_len(
+            "constraint coefficients",
+            n.iter().sum::<usize>() * N,
+            phi.len(),
+        )?;
+        check_len("constraint right-hand side", N, b.len())?;
+        let mut idx = idx.to_vec();
+        let (mut phi, mut b) = (phi.to_vec(), b.to_vec());
+        let status = unsafe {
+            labrador24_set_smplstmnt_lincnst_raw(
+                &mut *self.raw,
+                i,
+                idx.len(),
+                idx.as_mut_ptr(),
+                n.as_mut_ptr(),
+                N,
+                phi.as_mut_ptr(),
+                b.as_mut_ptr(),
+            )
+        };
+        check_status("labrador24_set_smplstmnt_lincnst_raw", status)?;
+        self.is_set[i] = true;
+        Ok(())
+    }
+
+    /// Number of polynomials in the largest witness vector, which determines the size of the commitment key
+    fn max_witness_polys(&self) -> usize {
+        self.n.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Check that the witness satisfies the statement
+    pub(crate) fn check(&self, witness: &CWitness) -> Result<(), CBackendError> {
+        check_all_set("constraint", &self.is_set)?;
+        check_all_set("witness vector", &witness.is_set)?;
+        check_len("witness vectors", self.n.len(), witness.n.len())?;
+        for (n_i, witness_n_i) in self.n.iter().zip(witness.n.iter()) {
+            check_len("witness vector", *n_i, *witness_n_i)?;
+        }
+        check_status("labrador24_simple_verify", unsafe {
+            labrador24_simple_verify(&*self.raw, &*witness.raw)
+        })
+    }
+
+    /// Check the witness against the statement, and compute the commitment to it and the composite proof
+    pub(crate) fn prove(&self, witness: &CWitness) -> Result<CProof, CBackendError> {
+        self.check(witness)?;
+        let _comkey = ComKey::new(self.max_witness_polys());
+        // Wrapped in a `CProof` before calling the prover, so that whatever it allocated is freed even if it fails
+        let mut proof = CProof {
+            commitment: Box::new(unsafe { mem::zeroed() }),
+            composite: Box::new(unsafe { mem::zeroed() }),
+        };
+        let status = unsafe {
+            labrador24_composite_prove_simple(
+                &mut *proof.composite,
+                &mut *proof.commitment,
+                &*self.raw,
+                &*witness.raw,
+            )
+        };
+        check_status("labrador24_composite_prove_simple", status)?;
+        Ok(proof)
+    }
+
+    /// Verify a decoded commitment and composite proof against the statement, after checking that its dimensions are
+    /// those of the statement, see `DeserializedProof::check_dimensions`
+    pub(crate) fn verify(&self, proof: &DeserializedProof) -> Result<(), CBackendError> {
+        check_all_set("constraint", &self.is_set)?;
+        proof.check_dimensions(&self.n)?;
+        let _comkey = ComKey::new(self.max_witness_polys());
+        let status = unsafe {
+            labrador24_composite_verify_simple(&proof.composite, &proof.commitment, &*self.raw)
+        };
+        if status != 0 {
+            return Err(CBackendError::InvalidProof);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CStatement {
+    fn drop(&mut self) {
+        unsafe { labrador24_free_smplstmnt(&mut *self.raw) };
+    }
+}
+
+/// The commitment and composite proof written by `labrador24_composite_prove_simple`, zeroed before the call
+pub(crate) struct CProof {
+    commitment: Box<labrador24_commitment>,
+    composite: Box<labrador24_composite>,
+}
+
+impl CProof {
+    pub(crate) fn commitment(&self) -> &labrador24_commitment {
+        &self.commitment
+    }
+
+    pub(crate) fn composite(&self) -> &labrador24_composite {
+        &self.composite
     }
 }
 
-impl Drop for ProofStatement {
+impl Drop for CProof {
     fn drop(&mut self) {
         unsafe {
-            labrador24_free_commitment(&mut *self.commitment_ptr);
-            labrador24_free_witness(&mut *self.witness_ptr);
-            labrador24_free_composite(&mut *self.composite_ptr);
-            labrador24_free_smplstmnt(&mut *self.smplstmnt_ptr);
-            labrador24_free_comkey();
+            labrador24_free_commitment(&mut *self.commitment);
+            labrador24_free_composite(&mut *self.composite);
         }
     }
 }
+
+/// Held by the `ComKey` in use, since the C library has a single, process-global commitment key
+static COMKEY_LOCK: Mutex<()> = Mutex::new(());
+
+/// The global commitment key of the C library for witness vectors of up to n polynomials, freed on drop.
+/// Only one `ComKey` exists at a time: `new` blocks until the previous one is dropped, so that concurrent proofs do
+/// not overwrite or free each other's key.
+struct ComKey {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl ComKey {
+    fn new(n: usize) -> Self {
+        // The key is freed even if a thread panics while holding it, so a poisoned lock can be reused
+        let guard = COMKEY_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        unsafe { labrador24_init_comkey(n) };
+        ComKey { _guard: guard }
+    }
+}
+
+impl Drop for ComKey {
+    fn drop(&mut self) {
+        // Runs before the guard is released
+        unsafe { labrador24_free_comkey() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_range_and_unset() {
+        let mut witness = CWitness::new(&[2, 1]);
+        assert_eq!(
+            witness.set(2, &[0; N]),
+            Err(CBackendError::IndexOutOfRange {
+                what: "witness vector",
+                index: 2,
+                len: 2
+            })
+        );
+        assert!(matches!(
+            witness.set(0, &[0; N]),
+            Err(CBackendError::LengthMismatch { .. })
+        ));
+        witness.set(0, &[1; 2 * N]).unwrap();
+
+        let mut st = CStatement::new(&[2, 1], &[1000, 1000], 1).unwrap();
+        assert!(matches!(
+            st.set_linear_constraint(1, &[0], &[0; 2 * N], &[0; N]),
+            Err(CBackendError::IndexOutOfRange { .. })
+        ));
+        assert!(matches!(
+            st.set_linear_constraint(0, &[0, 2], &[0; 3 * N], &[0; N]),
+            Err(CBackendError::IndexOutOfRange { .. })
+        ));
+        assert_eq!(
+            st.set_linear_constraint(0, &[1, 1], &[0; 2 * N], &[0; N]),
+            Err(CBackendError::DuplicateWitnessIndex(1))
+        );
+        assert_eq!(
+            st.check(&witness),
+            Err(CBackendError::Unset {
+                what: "constraint",
+                index: 0
+            })
+        );
+        st.set_linear_constraint(0, &[1], &[0; N], &[0; N]).unwrap();
+        assert_eq!(
+            st.check(&witness),
+            Err(CBackendError::Unset {
+                what: "witness vector",
+                index: 1
+            })
+        );
+        witness.set(1, &[0; N]).unwrap();
+        assert_eq!(st.check(&witness), Ok(()));
+    }
+}
@@ -0,0 +1,117 @@
+#![allow(non_snake_case)]
+
+//! Gram matrices of the witness for the outer commitments of the prover.
+//!
+//! Only the lower triangle of each symmetric matrix is computed. Rows are computed in parallel, and within a row the
+//! inner products are accumulated over blocks of `BLOCK_LEN` entries, so that a block of $s_i$ stays in cache while it
+//! is multiplied with the same block of every $s_j$, $j \leq i$.
+
+use std::cmp::min;
+use std::fmt::Debug;
+
+use num_traits::Zero;
+use rayon::prelude::*;
+
+use lattirust_arithmetic::linear_algebra::{SymmetricMatrix, Vector};
+use lattirust_arithmetic::ring::PolyRing;
+
+/// Number of ring elements of a witness vector in one block
+const BLOCK_LEN: usize = 64;
+
+fn dot<R: PolyRing>(a: &[R], b: &[R]) -> R {
+    a.iter()
+        .zip(b.iter())
+        .fold(R::zero(), |acc, (a_k, b_k)| acc + *a_k * *b_k)
+}
+
+/// Lower triangle of the rows of an r x r symmetric matrix, where `entries(start, end, i, row)` adds the contribution
+/// of the entries in `start..end` of the vectors to the row $(M_{ij})_{j \leq i}$
+fn lower_triangle<R: PolyRing, F>(r: usize, n: usize, entries: F) -> SymmetricMatrix<R>
+where
+    F: Fn(usize, usize, usize, &mut [R]) + Sync,
+{
+    let rows = (0..r)
+        .into_par_iter()
+        .map(|i| {
+            let mut row = vec![R::zero(); i + 1];
+            for start in (0..n).step_by(BLOCK_LEN) {
+                entries(start, min(start + BLOCK_LEN, n), i, &mut row);
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+    SymmetricMatrix::<R>::from(rows)
+}
+
+/// $G_{ij} = \langle s_i, s_j \rangle$
+pub fn gram<R: PolyRing>(s: &[Vector<R>]) -> SymmetricMatrix<R> {
+    let n = s.first().map_or(0, |s_0| s_0.len());
+    lower_triangle(s.len(), n, |start, end, i, row| {
+        let s_i = &s[i].as_slice()[start..end];
+        for (j, row_j) in row.iter_mut().enumerate() {
+            *row_j += dot(s_i, &s[j].as_slice()[start..end]);
+        }
+    })
+}
+
+/// $H_{ij} = (\langle \phi_i, s_j \rangle + \langle \phi_j, s_i \rangle) / 2$, symmetrized as it is computed
+pub fn symmetrized_cross_gram<R: PolyRing>(phi: &[Vector<R>], s: &[Vector<R>]) -> SymmetricMatrix<R>
+where
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    assert_eq!(phi.len(), s.len());
+    let n = s.first().map_or(0, |s_0| s_0.len());
+    let mut H = lower_triangle(s.len(), n, |start, end, i, row| {
+        let (phi_i, s_i) = (&phi[i].as_slice()[start..end], &s[i].as_slice()[start..end]);
+        for (j, row_j) in row.iter_mut().enumerate() {
+            *row_j +=
+                dot(phi_i, &s[j].as_slice()[start..end]) + dot(&phi[j].as_slice()[start..end], s_i);
+        }
+    });
+    let two_inv = R::inverse(&R::try_from(2u128).unwrap()).unwrap();
+    for i in 0..s.len() {
+        for j in 0..=i {
+            H[(i, j)] *= two_inv;
+        }
+    }
+    H
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_std::rand::thread_rng;
+    use lattirust_arithmetic::linear_algebra::inner_products::{inner_products, inner_products2};
+    use lattirust_arithmetic::linear_algebra::Matrix;
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+
+    use super::*;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    #[test]
+    fn test_gram_matrices() {
+        let rng = &mut thread_rng();
+        // Vectors longer than a block, and not a multiple of it
+        let (r, n) = (5, 2 * BLOCK_LEN + 3);
+        let columns = |m: Matrix<R>| {
+            (0..r)
+                .map(|i| m.column(i).into_owned())
+                .collect::<Vec<Vector<R>>>()
+        };
+        let s = columns(Matrix::<R>::rand(n, r, rng));
+        let phi = columns(Matrix::<R>::rand(n, r, rng));
+
+        let G = gram(&s);
+        let G_expected = inner_products(&s);
+        let H = symmetrized_cross_gram(&phi, &s);
+        let H_1 = inner_products2(&phi, &s);
+        let H_2 = inner_products2(&s, &phi);
+        let two_inv = R::inverse(&R::try_from(2u128).unwrap()).unwrap();
+        for i in 0..r {
+            for j in 0..=i {
+                assert_eq!(G[(i, j)], G_expected[(i, j)]);
+                assert_eq!(H[(i, j)], (H_1[(i, j)] + H_2[(i, j)]) * two_inv);
+            }
+        }
+    }
+}
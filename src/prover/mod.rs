@@ -1,6 +1,7 @@
 #![allow(non_snake_case)]
 
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
 use nimue::{Merlin, ProofResult};
@@ -11,7 +12,6 @@ use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChalle
 use lattirust_arithmetic::decomposition::balanced_decomposition::decompose_vec_vector_dimfirst;
 use lattirust_arithmetic::decomposition::decomposition::decompose_vec_polyring;
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
-use lattirust_arithmetic::linear_algebra::inner_products::{inner_products, inner_products2};
 use lattirust_arithmetic::linear_algebra::Vector;
 use lattirust_arithmetic::nimue::merlin::SerMerlin;
 use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
@@ -21,15 +21,21 @@ use lattirust_arithmetic::traits::FromRandomBytes;
 use relations::principal_relation::{Index, Instance, Witness};
 use tracing::info_span;
 
+use crate::commitment::Commitment;
 use crate::common_reference_string::CommonReferenceString;
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink};
 use crate::shared::{
-    compute_a__, compute_phi, compute_phi__, fold_instance, Layouter, TranscriptView,
+    compute_a__, compute_phi, compute_phi__, fold_instance_with_progress, Layouter, TranscriptView,
 };
 use crate::util::*;
+use gram::{gram, symmetrized_cross_gram};
+use stats::{PeakAllocation, Phase, ProverStats, RoundStats};
 
+#[cfg(feature = "c-binding")]
 pub mod c_prover;
+pub mod gram;
+pub mod stats;
 
-#[cfg(not(feature = "c-binding"))]
 pub fn prove_principal_relation_oneround<'a, R: PolyRing>(
     merlin: &'a mut Merlin,
     crs: &CommonReferenceString<R>,
@@ -37,6 +43,42 @@ pub fn prove_principal_relation_oneround<'a, R: PolyRing>(
     instance: &Instance<R>,
     witness: &Witness<R>,
 ) -> ProofResult<(Index<R>, Instance<R>, Witness<R>)>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    prove_round(
+        merlin,
+        crs,
+        index,
+        instance,
+        witness,
+        None,
+        &mut RoundStats::default(),
+        &NoProgress,
+    )
+}
+
+/// One round of the protocol.
+/// If the witness is committed to in `commitment`, the first message only has the part of u_1 that commits to the inner
+/// products, since the verifier knows the rest.
+/// The time of each phase, the size of the messages and the padding of the folded witness are recorded in `stats`, and
+/// the JL projections and folded constraints are reported to `progress` as they are computed.
+#[allow(clippy::too_many_arguments)]
+fn prove_round<'a, R: PolyRing>(
+    merlin: &'a mut Merlin,
+    crs: &CommonReferenceString<R>,
+    index: &Index<R>,
+    instance: &Instance<R>,
+    witness: &Witness<R>,
+    commitment: Option<&Commitment<R>>,
+    stats: &mut RoundStats,
+    progress: &dyn ProgressSink,
+) -> ProofResult<(Index<R>, Instance<R>, Witness<R>)>
 where
     LabradorChallengeSet<R>: FromRandomBytes<R>,
     WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
@@ -59,83 +101,112 @@ where
     // Prove
     let num_constraints = index.num_constraints;
     let num_ct_constraints = index.num_constant_constraints;
+    let transcript_len = merlin.transcript().len();
 
     // Message 1
     // let s_mat = Matrix::<R>::from_columns(&witness.s); // s_mat = [s_1 | ... | s_r] in Rq^{n x r}
     // let t = &crs.A * &s_mat; // t = A * s_mat in Rq^{k x r}
     // let t_decomp = decompose_balanced_polyring(&t, crs.b1, Some(crs.t1)); // t_decomp = [t_1 | ... | t_r] in Rq^{k x r}
-    let span = info_span!("Computing the first outer commitment");
-    let _ = span.enter();
-    let t: Vec<Vector<R>> = witness
-        .s
-        .par_iter()
-        .map(|s_i| commit(&crs.A, s_i))
-        .collect(); // r x k, t[i, j] = (A * s_i)[j]
+    let phase = Phase::new(
+        info_span!("Computing the first outer commitment"),
+        &mut stats.times.commit,
+    );
+    let t: Vec<Vector<R>> = match commitment {
+        Some(commitment) => commitment.t.clone(),
+        None => witness
+            .s
+            .par_iter()
+            .map(|s_i| commit(&crs.A, s_i))
+            .collect(), // r x k, t[i, j] = (A * s_i)[j]
+    };
 
     let t_decomp = decompose_vec_vector_dimfirst(&t, crs.b1, Some(crs.t1)); // t1 x r x k
     let t_flat = flatten_vec_vec_vector(&t_decomp); // t1 * r * k
 
-    let G = inner_products(&witness.s); // upper triangular matrix in Rq^{r x r}
+    let G = gram(&witness.s); // lower triangle of a symmetric matrix in Rq^{r x r}
     let G_decomp = decompose_symmetric_matrix(&G, crs.b2, Some(crs.t2)); // t2 x r x r
     let G_flat = flatten_vec_symmetric_matrix(&G_decomp); // t2 * r * r
 
-    let u_1 = commit(&crs.B, &t_flat) + commit(&crs.C, &G_flat);
-    drop(span);
+    let u_1_g = commit(&crs.C, &G_flat);
+    let u_1 = commit(&crs.B, &t_flat) + &u_1_g;
+    drop(phase);
     merlin
-        .absorb_vector(&u_1)
+        .absorb_vector(if commitment.is_some() { &u_1_g } else { &u_1 })
         .expect("error absorbing prover message 1");
 
     // Challenge 1
-    let span = info_span!("Computing JL projection");
-    let _ = span.enter();
+    let phase = Phase::new(info_span!("Computing JL projection"), &mut stats.times.jl);
     let num_projections = 256; // TODO: set in CRS
     let Pi = merlin
         .challenge_matrices::<R, WeightedTernaryChallengeSet<R>>(num_projections, crs.n, crs.r)
         .expect("error squeezing verifier message 1"); // r matrices in R^{num_projections x n}
 
     // Message 2
+    // The coefficients of each witness vector, shared by all projections
+    let s_coeffs: Vec<Vector<R::BaseRing>> = witness
+        .s
+        .par_iter()
+        .map(|s_i| R::flattened(s_i)) // in R::BaseRing^{n*d}
+        .collect();
     // let mut p = Vector::<R::BaseRing>::zeros(num_projections);
     use num_traits::Zero;
     let mut p = vec![R::BaseRing::zero(); num_projections];
+    let num_projected = AtomicUsize::new(0);
     p.par_iter_mut().enumerate().for_each(|(j, p_j)| {
         for i in 0..crs.r {
-            let s_i_vec = R::flattened(&witness.s[i]); // in R::BaseRing^{n*d}
             let pi_i = &Pi[i];
             let pi_ij = &pi_i.row(j).transpose();
             let pi_ij_vec = R::flattened(pi_ij); // in R::BaseRing^{n*d}
-            *p_j += pi_ij_vec.dot(&s_i_vec);
+            *p_j += pi_ij_vec.dot(&s_coeffs[i]);
         }
+        progress.report(ProgressEvent::JlProjection {
+            done: num_projected.fetch_add(1, Ordering::Relaxed) + 1,
+            total: num_projections,
+        });
     });
     let p = Vector::<R::BaseRing>::from_vec(p);
-    drop(span);
+    drop(phase);
     merlin
         .absorb_vector_canonical::<R::BaseRing>(&p)
         .expect("error absorbing prover message 2");
 
     // Challenge 2
-    let psi = merlin
-        .challenge_vectors::<R::BaseRing, R::BaseRing>(num_ct_constraints, crs.num_aggregs)
-        .expect("error squeezing verifier message 2 (psi)");
+    let psi = if num_ct_constraints > 0 {
+        merlin
+            .challenge_vectors::<R::BaseRing, R::BaseRing>(num_ct_constraints, crs.num_aggregs)
+            .expect("error squeezing verifier message 2 (psi)")
+    } else {
+        vec![Vector::<R::BaseRing>::zeros(0); crs.num_aggregs]
+    };
     let omega = merlin
         .challenge_vectors::<R::BaseRing, R::BaseRing>(256, crs.num_aggregs)
         .expect("error squeezing verifier message 2 (omega)");
 
     // Message 3
-    let span = info_span!("Computing aggregation");
-    let _ = span.enter();
+    let phase = Phase::new(
+        info_span!("Computing aggregation"),
+        &mut stats.times.aggregation,
+    );
     let phi__ = compute_phi__(crs, index, instance, &Pi, &psi, &omega);
-    let mut b__ = vec![R::zero(); crs.num_aggregs];
     let a__ = compute_a__(crs, instance, &psi);
 
-    for k in 0..crs.num_aggregs {
-        for i in 0..crs.r {
-            for j in 0..crs.r {
-                b__[k] += a__[k][(i, j)] * G[(i, j)];
-            }
-            b__[k] += &phi__[k][i].dot(&witness.s[i]);
-        }
-    }
-    drop(span);
+    // b''^(k) = sum_{i, j in [r]} a''_ij^(k) * g_ij + sum_{i in [r]} <phi''_i^(k), s_i>
+    let b__: Vec<R> = (0..crs.num_aggregs)
+        .into_par_iter()
+        .map(|k| {
+            (0..crs.r)
+                .into_par_iter()
+                .map(|i| {
+                    let mut b_ki = phi__[k][i].dot(&witness.s[i]);
+                    for j in 0..crs.r {
+                        b_ki += a__[k][(i, j)] * G[(i, j)];
+                    }
+                    b_ki
+                })
+                .reduce(R::zero, |a, b| a + b)
+        })
+        .collect();
+    drop(phase);
 
     merlin
         .absorb_vec(&b__)
@@ -150,24 +221,18 @@ where
         .expect("error squeezing verifier message 3 (beta)");
 
     // Message 4
-    let span = info_span!("Computing the second outer commitment");
-    let _ = span.enter();
+    let phase = Phase::new(
+        info_span!("Computing the second outer commitment"),
+        &mut stats.times.second_commitment,
+    );
     let phi = compute_phi(crs, instance, &alpha, &beta, &phi__);
 
-    let two_inv = R::inverse(&R::try_from(2u64).unwrap()).unwrap();
-    let mut H = inner_products2(&phi, &witness.s);
-    let H_2 = inner_products2(&witness.s, &phi);
-    for i in 0..crs.r {
-        for j in 0..i + 1 {
-            H[(i, j)] += H_2[(i, j)];
-            H[(i, j)] *= two_inv;
-        }
-    }
+    let H = symmetrized_cross_gram(&phi, &witness.s);
     let H_decomp = decompose_symmetric_matrix(&H, crs.b1, Some(crs.t1)); // t1 x r x r
     let H_flat = flatten_vec_symmetric_matrix(&H_decomp); // t1 * r * r
 
     let u_2 = commit(&crs.D, &H_flat);
-    drop(span);
+    drop(phase);
     merlin
         .absorb_vector(&u_2)
         .expect("error absorbing prover message 4");
@@ -178,6 +243,10 @@ where
         .expect("error squeezing verifier message 4");
 
     // Compute next instance
+    let phase = Phase::new(
+        info_span!("Folding the instance and witness"),
+        &mut stats.times.folding,
+    );
     let transcript = TranscriptView {
         u_1,
         b__,
@@ -188,27 +257,24 @@ where
         phi,
         a__,
     };
-    let (index_next, instance_next) = fold_instance(&crs, &instance, &transcript);
+    let (index_next, instance_next) = fold_instance_with_progress(
+        crs,
+        crs.folding_precomputation(),
+        instance,
+        &transcript,
+        progress,
+    );
     let next_size = crs.next_size();
 
-    // Compute next witnes
-    let span = info_span!("Compute the amortized opening");
-    let now = Instant::now();
-    let _ = span.enter();
+    // Compute next witness
     let z: Vector<R> = witness
-        .clone()
         .s
-        .into_iter()
-        .zip(c.into_iter())
-        .map(|(s_i, c_i)| (s_i * c_i).into())
-        .sum();
+        .par_iter()
+        .zip(c.par_iter())
+        .map(|(s_i, c_i)| s_i * *c_i)
+        .reduce(|| Vector::<R>::zeros(crs.n), |a, b| a + b);
 
     let z_decomp = decompose_vec_polyring(&z.as_slice(), crs.b, Some(2usize));
-    println!(
-        "Time elapsed for computing the amortized opening : {:?}",
-        now.elapsed()
-    );
-    drop(span);
 
     let mut layouter = Layouter::<R>::new(next_size);
 
@@ -219,14 +285,16 @@ where
     layouter.set_h(H_flat.as_slice());
 
     let witness_next = Witness::<R>::new(layouter.split());
+    drop(phase);
+    stats.set_folded_size(&layouter.folded_size);
+    stats.message_bytes = merlin.transcript().len() - transcript_len;
 
     Ok((index_next, instance_next, witness_next))
 }
 
-#[cfg(not(feature = "c-binding"))]
 pub fn prove_principal_relation<'a, R: PolyRing>(
     merlin: &'a mut Merlin,
-    mut crs: &CommonReferenceString<R>,
+    crs: &CommonReferenceString<R>,
     index: &Index<R>,
     instance: &Instance<R>,
     witness: &Witness<R>,
@@ -239,20 +307,132 @@ where
         DecompositionFriendlySignedRepresentative,
     <R as TryFrom<u128>>::Error: Debug,
 {
-    let mut index_curr = index.clone();
-    let mut instance_curr = instance.clone();
-    let mut witness_curr = witness.clone();
+    prove_principal_relation_with_stats(merlin, crs, index, instance, witness)
+        .map(|(proof, _)| proof)
+}
+
+/// Like `prove_principal_relation`, and also return the time of each phase of every round, the size of the messages,
+/// the padding of the folded witnesses, and the peak allocation if `stats::TrackingAllocator` is the global allocator.
+pub fn prove_principal_relation_with_stats<'a, R: PolyRing>(
+    merlin: &'a mut Merlin,
+    crs: &CommonReferenceString<R>,
+    index: &Index<R>,
+    instance: &Instance<R>,
+    witness: &Witness<R>,
+) -> ProofResult<(&'a [u8], ProverStats)>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    prove_principal_relation_with_progress(merlin, crs, index, instance, witness, &NoProgress)
+}
+
+/// Like `prove_principal_relation_with_stats`, and report the progress of the proof to `progress`, such as a closure
+/// taking a `ProgressEvent`, or `ProgressBars`.
+pub fn prove_principal_relation_with_progress<'a, R: PolyRing>(
+    merlin: &'a mut Merlin,
+    mut crs: &CommonReferenceString<R>,
+    index: &Index<R>,
+    instance: &Instance<R>,
+    witness: &Witness<R>,
+    progress: &dyn ProgressSink,
+) -> ProofResult<(&'a [u8], ProverStats)>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    let start = Instant::now();
+    let peak_allocation = PeakAllocation::start();
+    let mut stats = ProverStats::default();
+    // The statement of the last round, which is borrowed in the first round so that the witness is never copied
+    let mut folded: Option<(Index<R>, Instance<R>, Witness<R>)> = None;
+    let num_rounds = std::iter::successors(Some(crs), |crs| crs.next_crs.as_deref()).count() - 1;
 
     while crs.next_crs.is_some() {
-        (index_curr, instance_curr, witness_curr) = prove_principal_relation_oneround(
+        progress.report(ProgressEvent::RoundStarted {
+            round: stats.rounds.len() + 1,
+            num_rounds,
+        });
+        let (index_curr, instance_curr, witness_curr) = match &folded {
+            Some((index_curr, instance_curr, witness_curr)) => {
+                (index_curr, instance_curr, witness_curr)
+            }
+            None => (index, instance, witness),
+        };
+        let mut round_stats = RoundStats::default();
+        folded = Some(prove_round(
             merlin,
             crs,
-            &index_curr,
-            &instance_curr,
-            &witness_curr,
-        )?;
+            index_curr,
+            instance_curr,
+            witness_curr,
+            None,
+            &mut round_stats,
+            progress,
+        )?);
+        stats.rounds.push(round_stats);
         crs = crs.next_crs.as_ref().unwrap();
     }
+    let witness_curr = folded
+        .as_ref()
+        .map_or(witness, |(_, _, witness_curr)| witness_curr);
+    // Send the final witness in the clear
+    let transcript_len = merlin.transcript().len();
+    for s_i in &witness_curr.s {
+        merlin.absorb_vector(s_i)?;
+    }
+    stats.final_witness_bytes = merlin.transcript().len() - transcript_len;
+    stats.proof_bytes = merlin.transcript().len();
+    stats.time = start.elapsed();
+    stats.peak_allocation = peak_allocation.bytes();
+    progress.report(ProgressEvent::Finished);
     // TODO: add index/instance to the transcript
-    Ok(merlin.transcript())
+    Ok((merlin.transcript(), stats))
+}
+
+/// Prove a principal relation about a witness committed to in `commitment`, for the IO pattern
+/// `commit_and_prove_iopattern(crs)`, where the CRS has the commitment matrices that the commitment was made with.
+/// The proof only verifies against the published outer commitment `commitment.u_1`.
+pub fn prove_principal_relation_committed<'a, R: PolyRing>(
+    merlin: &'a mut Merlin,
+    crs: &CommonReferenceString<R>,
+    commitment: &Commitment<R>,
+    index: &Index<R>,
+    instance: &Instance<R>,
+    witness: &Witness<R>,
+) -> ProofResult<&'a [u8]>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+    <R as TryFrom<u128>>::Error: Debug,
+{
+    debug_assert!(Commitment::verify_opening(crs, &commitment.u_1, witness));
+    merlin.absorb_vector(&commitment.u_1)?;
+
+    let Some(next_crs) = crs.next_crs.as_ref() else {
+        // The witness is sent in the clear, and the verifier checks the opening itself
+        return prove_principal_relation(merlin, crs, index, instance, witness);
+    };
+    let (index_next, instance_next, witness_next) = prove_round(
+        merlin,
+        crs,
+        index,
+        instance,
+        witness,
+        Some(commitment),
+        &mut RoundStats::default(),
+        &NoProgress,
+    )?;
+    prove_principal_relation(merlin, next_crs, &index_next, &instance_next, &witness_next)
 }
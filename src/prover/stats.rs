@@ -0,0 +1,227 @@
+//! Statistics of a proof, returned by `prove_principal_relation_with_stats`.
+//!
+//! Each phase of a round runs in a tracing span, which is entered for as long as the phase runs, and its wall time is
+//! added to the `PhaseTimes` of the round. Peak allocation is only measured if the binary installs `TrackingAllocator`
+//! as its global allocator:
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOC: labrador::prover::stats::TrackingAllocator = labrador::prover::stats::TrackingAllocator;
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::span::EnteredSpan;
+use tracing::Span;
+
+use crate::common_reference_string::FoldedSize;
+
+/// Wall time of each phase of a round
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PhaseTimes {
+    /// First-level commitments, Gram matrix and first outer commitment u_1
+    pub commit: Duration,
+    /// JL projection p
+    pub jl: Duration,
+    /// Aggregation of the constraints and the values b''
+    pub aggregation: Duration,
+    /// Cross Gram matrix and second outer commitment u_2
+    pub second_commitment: Duration,
+    /// Folded instance, amortized opening and folded witness
+    pub folding: Duration,
+}
+
+impl PhaseTimes {
+    pub fn total(&self) -> Duration {
+        self.commit + self.jl + self.aggregation + self.second_commitment + self.folding
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoundStats {
+    pub times: PhaseTimes,
+    /// Size in bytes of the prover messages of the round
+    pub message_bytes: usize,
+    /// Number of zero ring elements that pad the parts of the folded witness to whole vectors of the next round
+    pub padding: usize,
+    /// Number of ring elements of the folded witness, including the padding
+    pub folded_witness_len: usize,
+}
+
+impl RoundStats {
+    pub(crate) fn set_folded_size(&mut self, folded_size: &FoldedSize) {
+        self.folded_witness_len =
+            (2 * folded_size.nu + folded_size.mu) * folded_size.size.witness_len;
+        self.padding = self.folded_witness_len
+            - (folded_size.size_z + folded_size.size_t + folded_size.size_g + folded_size.size_h);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProverStats {
+    /// Statistics of each folding round, in order
+    pub rounds: Vec<RoundStats>,
+    /// Size in bytes of the last witness, which is sent in the clear
+    pub final_witness_bytes: usize,
+    /// Size in bytes of the whole proof
+    pub proof_bytes: usize,
+    /// Wall time of the whole proof
+    pub time: Duration,
+    /// Peak number of bytes allocated during the proof on top of what was allocated before, or `None` if
+    /// `TrackingAllocator` is not the global allocator, or if too many proofs run at the same time to measure them all
+    pub peak_allocation: Option<usize>,
+}
+
+/// A phase of a round, which is in its tracing span until it is dropped, and then adds its wall time to `time`
+pub(crate) struct Phase<'a> {
+    _entered: EnteredSpan,
+    start: Instant,
+    time: &'a mut Duration,
+}
+
+impl<'a> Phase<'a> {
+    pub(crate) fn new(span: Span, time: &'a mut Duration) -> Self {
+        Phase {
+            _entered: span.entered(),
+            start: Instant::now(),
+            time,
+        }
+    }
+}
+
+impl Drop for Phase<'_> {
+    fn drop(&mut self) {
+        *self.time += self.start.elapsed();
+    }
+}
+
+/// Number of proofs whose peak allocation can be measured at the same time, one per bit of `TRACKED`
+const MAX_TRACKED: usize = 64;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// Bit i is set while `PEAKS[i]` measures the peak allocation of a proof
+static TRACKED: AtomicU64 = AtomicU64::new(0);
+static PEAKS: [AtomicUsize; MAX_TRACKED] = [const { AtomicUsize::new(0) }; MAX_TRACKED];
+
+/// The system allocator, counting the bytes in use, and their peak for each proof that measures it with a
+/// `PeakAllocation`.
+/// The bytes in use are counted globally, so the peak of a proof includes what concurrent proofs allocate meanwhile.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            let mut tracked = TRACKED.load(Ordering::Relaxed);
+            while tracked != 0 {
+                PEAKS[tracked.trailing_zeros() as usize].fetch_max(allocated, Ordering::Relaxed);
+                tracked &= tracked - 1;
+            }
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+/// The peak allocation of one proof, measured in a slot of `PEAKS` of its own, so that proofs running at the same
+/// time do not reset each other's peak. The slot is released when this is dropped.
+pub(crate) struct PeakAllocation {
+    /// `None` if `TrackingAllocator` is not in use, or if `MAX_TRACKED` proofs are already measured
+    slot: Option<usize>,
+    baseline: usize,
+}
+
+impl PeakAllocation {
+    /// Start measuring from the bytes allocated now
+    pub(crate) fn start() -> Self {
+        let baseline = ALLOCATED.load(Ordering::Relaxed);
+        // Nothing is ever allocated through TrackingAllocator unless it is the global allocator
+        let slot = if baseline == 0 {
+            None
+        } else {
+            TRACKED
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tracked| {
+                    (tracked != u64::MAX).then_some(tracked | (tracked + 1))
+                })
+                .ok()
+                .map(|tracked| (!tracked).trailing_zeros() as usize)
+        };
+        if let Some(i) = slot {
+            PEAKS[i].store(baseline, Ordering::Relaxed);
+        }
+        PeakAllocation { slot, baseline }
+    }
+
+    /// Peak bytes allocated since `start` on top of what was allocated then, if it is measured
+    pub(crate) fn bytes(&self) -> Option<usize> {
+        self.slot.map(|i| {
+            PEAKS[i]
+                .load(Ordering::Relaxed)
+                .saturating_sub(self.baseline)
+        })
+    }
+}
+
+impl Drop for PeakAllocation {
+    fn drop(&mut self) {
+        if let Some(i) = self.slot {
+            TRACKED.fetch_and(!(1 << i), Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+
+    use crate::iopattern::principal_relation_iopattern;
+    use crate::prover::prove_principal_relation_with_stats;
+    use crate::test_util::inner_product_statement;
+    use crate::verifier::verify_principal_relation;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    #[test]
+    fn test_prover_stats() {
+        let (crs, index, instance, witness) = inner_product_statement::<R>();
+        let io = principal_relation_iopattern(&crs);
+        let mut merlin = io.to_merlin();
+        let (proof, stats) =
+            prove_principal_relation_with_stats(&mut merlin, &crs, &index, &instance, &witness)
+                .unwrap();
+        let proof = proof.to_vec();
+        let mut arthur = io.to_arthur(&proof);
+        verify_principal_relation(&mut arthur, &crs, &index, &instance).unwrap();
+
+        let mut num_rounds = 0;
+        let mut crs_curr = &crs;
+        while let Some(next_crs) = crs_curr.next_crs.as_ref() {
+            num_rounds += 1;
+            crs_curr = next_crs;
+        }
+        assert_eq!(stats.rounds.len(), num_rounds);
+        assert_eq!(stats.proof_bytes, proof.len());
+        assert_eq!(
+            stats
+                .rounds
+                .iter()
+                .map(|round| round.message_bytes)
+                .sum::<usize>()
+                + stats.final_witness_bytes,
+            stats.proof_bytes
+        );
+        for round in &stats.rounds {
+            assert!(round.message_bytes > 0);
+            assert!(round.padding < round.folded_witness_len);
+            assert!(round.times.total() <= stats.time);
+        }
+        // The test binary does not install the tracking allocator
+        assert_eq!(stats.peak_allocation, None);
+    }
+}
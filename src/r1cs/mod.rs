@@ -1,5 +1,94 @@
+use nimue::{Arthur, ByteIOPattern, IOPattern, Merlin, ProofResult};
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::nimue::iopattern::{SerIOPattern, SqueezeFromRandomBytes};
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use relations::principal_relation::PrincipalRelation;
+use relations::r1cs::R1CS;
+use relations::reduction::Reduction;
+
+use crate::iopattern::LabradorIOPattern;
+use crate::r1cs::prover::prove_reduction_r1cs_labradorpr;
+use crate::r1cs::util::R1CSCRS;
+use crate::r1cs::verifier::verify_reduction_r1cs_labradorpr;
+use crate::sparse::STATEMENT_DIGEST_LEN;
+
 pub mod prover;
+#[cfg(test)]
+pub mod test;
 pub mod util;
 pub mod verifier;
-#[cfg(test)]
-pub mod test;
\ No newline at end of file
+
+/// R1CS over the base ring Z_q of the polynomial ring R
+pub type ZqR1CS<R> = R1CS<<R as PolyRing>::BaseRing>;
+
+fn reduction_iopattern<R: PolyRing>(pp: &R1CSCRS<R>) -> IOPattern {
+    let k = pp.padded_size;
+    let l = pp.num_repetitions;
+    IOPattern::new("reduction_r1cs_principalrelation")
+        .add_bytes(STATEMENT_DIGEST_LEN, "statement digest")
+        .absorb_vector::<R>(pp.A.nrows(), "prover message 1 (t)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(k, l, "verifier message 1 (r)")
+        .absorb_vector::<R>(pp.B.nrows(), "prover message 2 (t_d)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(k, l, "verifier message 2 (alpha)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(k, l, "verifier message 2 (beta)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(k, l, "verifier message 2 (gamma)")
+        .squeeze_vectors::<R::BaseRing, R::BaseRing>(k, l, "verifier message 2 (eps)")
+}
+
+/// IO pattern of a proof by `prover::prove_r1cs`: the reduction to a principal relation, followed by the proof of the
+/// principal relation under `pp.core_crs`
+pub fn r1cs_iopattern<R: PolyRing>(pp: &R1CSCRS<R>) -> IOPattern
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    reduction_iopattern(pp)
+        .ratchet()
+        .labrador_recursive_io(&pp.core_crs)
+}
+
+pub struct ReductionR1CSPrincipalRelation<R: PolyRing> {
+    _marker: std::marker::PhantomData<R>,
+}
+
+impl<R: PolyRing> Reduction<ZqR1CS<R>, PrincipalRelation<R>, R1CSCRS<R>>
+    for ReductionR1CSPrincipalRelation<R>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    fn iopattern(
+        pp: &R1CSCRS<R>,
+        _index_in: &Self::IndexIn,
+        _instance_in: &Self::InstanceIn,
+    ) -> IOPattern {
+        reduction_iopattern(pp)
+    }
+
+    fn prove(
+        pp: &R1CSCRS<R>,
+        index: &Self::IndexIn,
+        instance: &Self::InstanceIn,
+        witness: &Self::WitnessIn,
+        merlin: &mut Merlin,
+    ) -> ProofResult<(Self::IndexOut, Self::InstanceOut, Self::WitnessOut)> {
+        prove_reduction_r1cs_labradorpr(pp, merlin, index, instance, witness)
+    }
+
+    fn verify(
+        pp: &R1CSCRS<R>,
+        index_in: &Self::IndexIn,
+        instance_in: &Self::InstanceIn,
+        arthur: &mut Arthur,
+    ) -> ProofResult<(Self::IndexOut, Self::InstanceOut)> {
+        verify_reduction_r1cs_labradorpr(arthur, pp, index_in, instance_in)
+    }
+}
@@ -1,103 +1,211 @@
 #![allow(non_snake_case)]
 
-use ark_ff::{Fp, MontBackend};
-use ark_ff::fields::MontConfig;
-use nimue::{Arthur, ProofResult};
+use nimue::{BytePublic, Merlin, ProofResult};
+use tracing::{event, instrument, Level};
 
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
 use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
-use lattirust_arithmetic::nimue::merlin::SerArthur;
+use lattirust_arithmetic::decomposition::balanced_decomposition::decompose_balanced_vec_polyring;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::nimue::merlin::SerMerlin;
 use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
 use lattirust_arithmetic::ring::PolyRing;
-use lattirust_arithmetic::ring::Zq;
 use lattirust_arithmetic::traits::FromRandomBytes;
-use lattirust_arithmetic::linear_algebra::Vector;
-
-use crate::r1cs::util::{R1CSCRS, R1CSInstance};
-use crate::util::{concat, flatten_vec_vector};
+use relations::{principal_relation, Relation};
 
-pub type Z64 = Zq2<274177, 67280421310721>; // Q = 2^64+1
+use crate::prover::prove_principal_relation;
+use crate::r1cs::util::{encode, hadamard, pad, reduce, R1CSTranscript, R1CSCRS};
+use crate::r1cs::ZqR1CS;
+use crate::sparse::{statement_digest, SparseR1CSIndex};
+use crate::util::{concat, shift_right};
 
-fn enc<R: PolyRing>(vec: &Vector<Z64>) -> Vector<R> {
-    todo!()
+/// Decompose a vector over R into `t` vectors with short coefficients in basis `b`
+fn decompose<R: PolyRing>(v: &Vector<R>, b: u128, t: usize) -> Vec<Vector<R>>
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    decompose_balanced_vec_polyring(v.as_slice(), b, Some(t))
+        .into_iter()
+        .map(|v_i| Vector::<R>::from_slice(v_i.as_slice()))
+        .collect()
 }
 
-struct R1CSTranscript<R: PolyRing> {
-    t: Vec<Vector<R>>,
-    phi: Vec<Vector<Z64>>,
-    // t_d: _,
-    // c: OMatrix<Z64, Dyn, U1>,
-    // g: _,
-    // r: _,
+pub fn prove_reduction_r1cs_labradorpr<'a, R: PolyRing>(
+    pp: &R1CSCRS<R>,
+    merlin: &'a mut Merlin,
+    index: &<ZqR1CS<R> as Relation>::Index,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+    witness: &<ZqR1CS<R> as Relation>::Witness,
+) -> ProofResult<(
+    principal_relation::Index<R>,
+    principal_relation::Instance<R>,
+    principal_relation::Witness<R>,
+)>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    prove_reduction_r1cs_labradorpr_sparse(
+        pp,
+        merlin,
+        &SparseR1CSIndex::from(index),
+        instance,
+        witness,
+    )
 }
 
-pub fn prove_r1cs<'a, R: PolyRing>(
-    crs: &R1CSCRS<R>,
-    merlin: &'a mut Arthur,
-    instance: &R1CSInstance<Z64>,
-    witness: &Vector<Z64>,
-) -> ProofResult<&'a [u8]>
+/// Same as [`prove_reduction_r1cs_labradorpr`], but for an index whose matrices are already in CSR format.
+#[instrument(
+    name = "R1CS -> PR",
+    level = "info",
+    skip(pp, merlin, index, instance, witness)
+)]
+pub fn prove_reduction_r1cs_labradorpr_sparse<'a, R: PolyRing>(
+    pp: &R1CSCRS<R>,
+    merlin: &'a mut Merlin,
+    index: &SparseR1CSIndex<R::BaseRing>,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+    witness: &<ZqR1CS<R> as Relation>::Witness,
+) -> ProofResult<(
+    principal_relation::Index<R>,
+    principal_relation::Instance<R>,
+    principal_relation::Witness<R>,
+)>
 where
     LabradorChallengeSet<R>: FromRandomBytes<R>,
     WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <R::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
 {
-    let (A, B, C) = (&instance.A, &instance.B, &instance.C);
-    let w = witness;
-    let (k, n) = (A.nrows(), A.ncols());
-    assert_eq!(k, n, "the current implementation only support k = n"); // TODO: remove this restriction by splitting a,b,c or w into multiple vectors
-    let l = crs.l;
-
-    let a = A * w;
-    let b = B * w;
-    let c = C * w;
-
-    let a_R = enc::<R>(&a);
-    let b_R = enc::<R>(&b);
-    let c_R = enc::<R>(&c);
-    let w_R = enc::<R>(w);
-
-    let v = concat(&[
-        a_R.as_slice(),
-        b_R.as_slice(),
-        c_R.as_slice(),
-        w_R.as_slice(),
-    ]);
-    let t = &crs.A * &v;
-
-    merlin.absorb_vector(&t).unwrap();
-
-    let phi = merlin.challenge_vectors::<Z64, Z64>(l, k)?;
-
-    let mut d_R = Vec::<Vector<R>>::with_capacity(l);
-    for i in 0..l {
-        d_R.push(enc::<R>(&phi[i].component_mul(&a)));
-    }
-    let d_R_flat = flatten_vec_vector(&d_R);
-    let t_d = &crs.B * &d_R_flat;
-
-    merlin.absorb_vector(&t_d)?;
-
-    // let alpha = merlin.squeeze_vector::<Z64, Z64>()
-    let c = merlin.challenge_vectors::<Z64, Z64>(l, k * (l + 3) + l);
-
-    let g = (0..l)
-        .map(|i|
-        // f(i, a_R, b_R, c_R, w_R, d_R_flat)
-        i)
+    let (A, B, C) = (&index.a, &index.b, &index.c);
+
+    let z = concat(vec![instance.0.as_slice(), witness.0.as_slice()].as_slice());
+    let size = pp.padded_size;
+    let (b_dec, t_dec) = (pp.decomposition_basis, pp.decomposition_length);
+
+    merlin.public_bytes(&statement_digest::<R, _>(index, &instance.0, |x| x))?;
+
+    // a, b, c and w are padded with zeros, which satisfy the all-zero constraints added by padding
+    event!(Level::DEBUG, "computing A*z, B*z, C*z");
+    let a = pad::<R>((A * &z).as_slice(), size);
+    let b = pad::<R>((B * &z).as_slice(), size);
+    let c = pad::<R>((C * &z).as_slice(), size);
+
+    // The public input is handled by the verifier, so only commit to the witness part of z
+    let w = pad::<R>(
+        shift_right(witness.0.as_slice(), instance.0.len()).as_slice(),
+        size,
+    );
+
+    let a_dec = decompose(&encode::<R>(a.as_slice()), b_dec, t_dec);
+    let b_dec_ = decompose(&encode::<R>(b.as_slice()), b_dec, t_dec);
+    let c_dec = decompose(&encode::<R>(c.as_slice()), b_dec, t_dec);
+    let w_dec = decompose(&encode::<R>(w.as_slice()), b_dec, t_dec);
+
+    let v = concat(
+        a_dec
+            .iter()
+            .chain(b_dec_.iter())
+            .chain(c_dec.iter())
+            .chain(w_dec.iter())
+            .map(|v_i| v_i.as_slice())
+            .collect::<Vec<_>>()
+            .as_slice(),
+    );
+    let t = &pp.A * &v;
+
+    merlin.absorb_vector(&t)?;
+
+    event!(
+        Level::DEBUG,
+        "squeezing r in Z_q^{}x{size}",
+        pp.num_repetitions
+    );
+    let r = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(size, pp.num_repetitions)?;
+
+    // d_i = σ_{-1}(r_i ∘ b), such that ct(<a, d_i>) = sum_j a_j * r_ij * b_j
+    let d_dec = r
+        .iter()
+        .map(|r_i| {
+            let d_i = R::apply_automorphism_vec(&encode::<R>(hadamard::<R>(r_i, &b).as_slice()));
+            decompose(&d_i, b_dec, t_dec)
+        })
         .collect::<Vec<_>>();
+    let v_d = concat(
+        d_dec
+            .iter()
+            .flatten()
+            .map(|v_i| v_i.as_slice())
+            .collect::<Vec<_>>()
+            .as_slice(),
+    );
+    let t_d = &pp.B * &v_d;
 
-    // merlin.absorb_vec(&g)?;
-    todo!();
+    merlin.absorb_vector(&t_d)?;
 
-    // let transcript = R1CSTranscript { t, phi, t_d, c, g, r };
+    event!(
+        Level::DEBUG,
+        "squeezing alpha, beta, gamma, eps in Z_q^{}x{size}",
+        pp.num_repetitions
+    );
+    let alpha = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(size, pp.num_repetitions)?;
+    let beta = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(size, pp.num_repetitions)?;
+    let gamma = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(size, pp.num_repetitions)?;
+    let eps = merlin.challenge_vectors::<R::BaseRing, R::BaseRing>(size, pp.num_repetitions)?;
+
+    let transcript = R1CSTranscript {
+        t,
+        r,
+        t_d,
+        alpha,
+        beta,
+        gamma,
+        eps,
+    };
+
+    let (index_pr, instance_pr) = reduce(pp, index, instance, &transcript);
+
+    let witness_pr = principal_relation::Witness::<R>::new(
+        a_dec
+            .into_iter()
+            .chain(b_dec_)
+            .chain(c_dec)
+            .chain(w_dec)
+            .chain(d_dec.into_iter().flatten())
+            .collect(),
+    ); // see R1CSCRS::{a,b,c,w,d}_idx for the layout
+
+    Ok((index_pr, instance_pr, witness_pr))
+}
 
-    // let instance_pr = reduce(&crs, &instance, &transcript);
+/// Prove an R1CS over Z_q whose index is in CSR format, which callers build once with [`SparseR1CSIndex::from`] or
+/// directly from the non-zero entries of the matrices, e.g., with `ImportedR1CS::to_zq_r1cs`
+pub fn prove_r1cs<'a, R: PolyRing>(
+    pp: &R1CSCRS<R>,
+    merlin: &'a mut Merlin,
+    index: &SparseR1CSIndex<R::BaseRing>,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+    witness: &<ZqR1CS<R> as Relation>::Witness,
+) -> ProofResult<&'a [u8]>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    let (index_pr, instance_pr, witness_pr) =
+        prove_reduction_r1cs_labradorpr_sparse(pp, merlin, index, instance, witness)?;
 
-    // let witness_pr = Witness::<R> {
-    //     s: vec![a_R, b_R, c_R, w_R, d_R] // see definition of indices above
-    // };
+    merlin.ratchet()?;
 
-    // merlin.ratchet()?;
-    // prove_principal_relation(merlin, &instance_pr, &witness_pr, &crs.pr_crs())
-    Ok(merlin.transcript())
+    prove_principal_relation(merlin, &pp.core_crs, &index_pr, &instance_pr, &witness_pr)
 }
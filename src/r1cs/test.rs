@@ -0,0 +1,122 @@
+use tracing_subscriber::fmt::format;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use ark_std::rand::{thread_rng, Rng};
+use num_traits::{One, Zero};
+
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
+use lattirust_arithmetic::ring::ntt::ntt_prime;
+use lattirust_arithmetic::ring::Pow2CyclotomicPolyRingNTT;
+use lattirust_arithmetic::ring::Zq1;
+use relations::r1cs::{Index, Instance, Size, Witness};
+use relations::reduction::Reduction;
+use relations::{test_completeness_with_init, test_soundness_with_init};
+
+use crate::r1cs::prover::prove_r1cs;
+use crate::r1cs::util::R1CSCRS;
+use crate::r1cs::verifier::verify_r1cs;
+use crate::r1cs::{r1cs_iopattern, ReductionR1CSPrincipalRelation};
+use crate::sparse::{SparseMatrix, SparseR1CSIndex};
+
+const Q: u64 = ntt_prime::<64>(32);
+const D: usize = 64;
+type F = Zq1<Q>;
+
+type R = Pow2CyclotomicPolyRingNTT<F, D>;
+
+fn init() {
+    let _ = tracing_subscriber::fmt::fmt()
+        .with_span_events(FmtSpan::ENTER | FmtSpan::CLOSE)
+        .event_format(format().compact())
+        .with_env_filter("none,labrador=trace")
+        .try_init();
+}
+
+const TEST_SIZE: Size = Size {
+    num_constraints: D * 4,
+    num_instance_variables: D,
+    num_witness_variables: D * 3,
+};
+
+test_completeness_with_init!(
+    ReductionR1CSPrincipalRelation<R>,
+    R1CSCRS::new(
+        TEST_SIZE.num_constraints,
+        TEST_SIZE.num_instance_variables + TEST_SIZE.num_witness_variables
+    ),
+    TEST_SIZE,
+    init
+);
+
+test_soundness_with_init!(
+    ReductionR1CSPrincipalRelation<R>,
+    R1CSCRS::new(
+        TEST_SIZE.num_constraints,
+        TEST_SIZE.num_instance_variables + TEST_SIZE.num_witness_variables
+    ),
+    TEST_SIZE,
+    init
+);
+
+/// The R1CS z_i * z_i = z_i for i < k, over n variables, with public input z_0 = 1, and a random witness in {0, 1}
+fn boolean_r1cs(k: usize, n: usize) -> (SparseR1CSIndex<F>, Instance<F>, Witness<F>) {
+    let rng = &mut thread_rng();
+    let diagonal = Matrix::<F>::from_fn(k, n, |i, j| if i == j { F::one() } else { F::zero() });
+    let index = SparseR1CSIndex::from(&Index {
+        a: diagonal.clone(),
+        b: diagonal.clone(),
+        c: diagonal,
+    });
+    let n_x = 1;
+    let instance = Instance(Vector::<F>::from_element(n_x, F::one()));
+    let witness = Witness(Vector::<F>::from_fn(n - n_x, |_, _| {
+        if rng.gen::<bool>() {
+            F::one()
+        } else {
+            F::zero()
+        }
+    }));
+    (index, instance, witness)
+}
+
+fn test_prove_verify_r1cs_of_size(k: usize, n: usize) {
+    init();
+    let (index, instance, witness) = boolean_r1cs(k, n);
+
+    let crs = R1CSCRS::<R>::new(k, n);
+    let io = r1cs_iopattern(&crs);
+    let mut merlin = io.to_merlin();
+    let proof = prove_r1cs(&crs, &mut merlin, &index, &instance, &witness)
+        .unwrap()
+        .to_vec();
+
+    let mut arthur = io.to_arthur(&proof);
+    verify_r1cs(&mut arthur, &crs, &index, &instance).unwrap();
+
+    // 2 * 2 != 2, so the proof does not verify for the public input 2
+    let other_instance = Instance(Vector::<F>::from_element(1, F::one() + F::one()));
+    let mut arthur = io.to_arthur(&proof);
+    assert!(verify_r1cs(&mut arthur, &crs, &index, &other_instance).is_err());
+
+    // The proof is bound to the statement, so it does not verify for another index either
+    let mut other_c = index.c.to_dense();
+    other_c[(0, 0)] = F::zero();
+    let other_index = SparseR1CSIndex {
+        a: index.a.clone(),
+        b: index.b.clone(),
+        c: SparseMatrix::from_dense(&other_c),
+    };
+    let mut arthur = io.to_arthur(&proof);
+    assert!(verify_r1cs(&mut arthur, &crs, &other_index, &instance).is_err());
+}
+
+#[test]
+fn test_prove_verify_r1cs() {
+    test_prove_verify_r1cs_of_size(D, D);
+}
+
+#[test]
+fn test_prove_verify_r1cs_padded() {
+    // Fewer constraints than variables, and neither a multiple of D
+    test_prove_verify_r1cs_of_size(D / 2 + 3, D + 5);
+}
@@ -1,114 +1,392 @@
 #![allow(non_snake_case)]
 
-use ark_std::rand::thread_rng;
+use ark_std::rand;
+use derive_more::Display;
 use num_bigint::BigUint;
-use num_traits::{One, ToPrimitive, Zero};
+use num_traits::{One, Zero};
 
-use lattirust_arithmetic::linear_algebra::{Matrix, Scalar};
+use lattice_estimator::msis::{msis_h_128_linf, MSIS};
+use lattice_estimator::norms::Norm;
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
 use lattirust_arithmetic::ring::PolyRing;
-use lattirust_arithmetic::ring::Ring;
-use lattirust_arithmetic::linear_algebra::Vector;
+use relations::principal_relation::{Index, Instance, Size};
+use relations::r1cs::R1CS;
+use relations::Relation;
 
-use crate::binary_r1cs::util::Z2;
-use crate::common_reference_string::{CommonReferenceString, SECURITY_PARAMETER};
+use crate::common_reference_string::CommonReferenceString;
+use crate::relation_builder::{QuadraticExpr, RelationBuilder, WitnessVar};
+use crate::sparse::SparseR1CSIndex;
+use crate::util::{mul_basescalar_vector, smallest_prime_factor_bound};
 
+const SECURITY_PARAMETER: usize = 128;
+
+/// Logarithm of the basis used to decompose Z_q-elements into short digits before committing to them
+const LOG_DECOMPOSITION_BASIS: usize = 8;
+
+#[derive(Clone, Debug, Display)]
+#[display(
+    "R1CSCRS: {} constraints, {} variables, {} bits of security, m={}, m_d={}, l={}",
+    num_constraints,
+    num_variables,
+    security_parameter,
+    commitment_output_size,
+    commitment_output_size_d,
+    num_repetitions
+)]
 pub struct R1CSCRS<R: PolyRing> {
+    /// Commitment matrix for the decomposed vectors a, b, c and w
     pub A: Matrix<R>,
+    /// Commitment matrix for the decomposed vectors d_1, ..., d_l
     pub B: Matrix<R>,
     pub num_constraints: usize,
     pub num_variables: usize,
-    pub m: usize,
-    pub m_d: usize,
-    pub l: usize,
+    /// Number of constraints and of variables after padding with all-zero constraints and zero-valued variables
+    pub padded_size: usize,
+    commitment_output_size: usize,
+    commitment_output_size_d: usize,
+    /// Number of parallel repetitions of each check, each of which has soundness error 1/p, where p is the smallest
+    /// prime factor of q
+    pub num_repetitions: usize,
+    /// Basis used to decompose Z_q-elements into short digits
+    pub decomposition_basis: u128,
+    /// Number of digits of each decomposed Z_q-element
+    pub decomposition_length: usize,
+    /// CRS of the output principal relation
+    pub core_crs: CommonReferenceString<R>,
+    pub security_parameter: usize,
 }
 
 impl<R: PolyRing> R1CSCRS<R> {
+    /// The reduction needs as many constraints as variables, and both to be a multiple of d, so R1CS instances of any
+    /// size are padded to `padded_size` with all-zero constraints and zero-valued witness variables.
     pub fn new(num_constraints: usize, num_variables: usize) -> Self {
-        let k = num_constraints;
-        let n = num_variables;
         let d = R::dimension();
-        // TODO: pad instead
-        assert_eq!(
-            k % d,
-            0,
-            "number of constraints {} must be multiple of d = {}",
-            k,
-            d
-        );
-        assert_eq!(
-            n % d,
-            0,
-            "number of variables {} must be multiple of d = {}",
-            n,
-            d
-        );
-        let m = 10usize; // TODO: choose such that MSIS_{m, 2n+6k} is hard with l_inf bound = 1
+        let padded_size = num_constraints.max(num_variables).div_ceil(d) * d;
+        let (k, n) = (padded_size, padded_size);
 
         let q = R::modulus();
+        let log2_q = q.bits() as usize;
+        let log2_p_min = (smallest_prime_factor_bound(&q) as f64).log2().floor() as usize;
+        let num_repetitions = SECURITY_PARAMETER.div_ceil(log2_p_min);
+        let decomposition_length = log2_q.div_ceil(LOG_DECOMPOSITION_BASIS);
+        let decomposition_basis = 1u128 << LOG_DECOMPOSITION_BASIS;
+
+        // Ensure MSIS_{d, q, b, m=2t(n+3k)} is hard for the l_inf norm, where b is the decomposition basis
+        let msis = MSIS {
+            h: 0, // dummy value, will be set later
+            d,
+            q: q.clone(),
+            length_bound: decomposition_basis as f64,
+            w: 2 * decomposition_length * (n + 3 * k),
+            norm: Norm::Linf,
+        };
+        let h: usize = msis_h_128_linf(&msis).unwrap();
+
+        // Ensure MSIS_{d, q, b, m=2tlk} is hard for the l_inf norm
+        let msis_d = MSIS {
+            h: 0, // dummy value, will be set later
+            d,
+            q: q.clone(),
+            length_bound: decomposition_basis as f64,
+            w: 2 * decomposition_length * num_repetitions * k,
+            norm: Norm::Linf,
+        };
+        let h_d: usize = msis_h_128_linf(&msis_d).unwrap();
+
         assert!(
-            BigUint::from(n + 3 * k) < q,
-            "n + 3k = {} must be less than q = {} for soundness",
-            n + 3 * k,
-            q
-        );
-        assert!(
-            BigUint::from(6 * k) < q,
-            "6k = {} must be less than q = {} for soundness",
-            6 * k,
+            BigUint::from(SECURITY_PARAMETER) < q,
+            "q = {} must be larger than the security parameter {SECURITY_PARAMETER} for soundness",
             q
         );
-        assert!(
-            BigUint::from(SECURITY_PARAMETER * (n + 3 * k)) < BigUint::from(15u32) * q.clone(),
-            "n + 3*k = {} must be less than 15q/128 = {} to be able to compose with Labrador-core",
-            n + 3 * k,
-            (BigUint::from(15u32) * q).to_f64().unwrap() / 128.,
-        );
 
-        let m_d = 10usize; // TODO: choose such that MSIS_{m_d, l*k} is hard with l_2 norm bound = beta
-        let l = (SECURITY_PARAMETER - 1).div_ceil(18); // protocol has soundness 2*p^-l, where p ~= 2^18 is the smallest prime factor of 2^64 + 1
+        let rng = &mut rand::thread_rng();
 
-        let rng = &mut ark_std::rand::rngs::OsRng::default();
+        let commitment_output_size = h.div_ceil(d);
+        let commitment_output_size_d = h_d.div_ceil(d);
+        let core_crs = CommonReferenceString::<R>::new_for_size(Self::size(
+            padded_size,
+            num_repetitions,
+            decomposition_basis,
+            decomposition_length,
+            commitment_output_size + commitment_output_size_d,
+        ));
         Self {
-            A: Matrix::<R>::rand(m.div_ceil(d), (3 * k + n).div_ceil(d), rng),
-            B: Matrix::<R>::rand(m.div_ceil(d), l * k, rng),
+            A: Matrix::<R>::rand(
+                commitment_output_size,
+                decomposition_length * (3 * k + n).div_ceil(d),
+                rng,
+            ),
+            B: Matrix::<R>::rand(
+                commitment_output_size_d,
+                decomposition_length * num_repetitions * k.div_ceil(d),
+                rng,
+            ),
             num_constraints,
             num_variables,
-            m,
-            m_d,
-            l,
+            padded_size,
+            commitment_output_size,
+            commitment_output_size_d,
+            num_repetitions,
+            decomposition_basis,
+            decomposition_length,
+            core_crs,
+            security_parameter: SECURITY_PARAMETER,
         }
     }
 
-    pub fn pr_crs(&self) -> CommonReferenceString<R> {
+    /// Number of witness vectors in the output principal relation: t digits for each of a, b, c, w and d_1, ..., d_l
+    fn r_pr(&self) -> usize {
+        (4 + self.num_repetitions) * self.decomposition_length
+    }
+
+    fn size(
+        padded_size: usize,
+        num_repetitions: usize,
+        decomposition_basis: u128,
+        decomposition_length: usize,
+        commitment_size: usize,
+    ) -> Size {
         let d = R::dimension();
-        let r_pr: usize = 8;
-        let n_pr = self.num_variables.div_ceil(d);
-        let norm_bound = (R::modulus().to_f64().unwrap()).sqrt();
-
-        let num_quad_constraints = self.m.div_ceil(d) + 3 * n_pr;
-        let num_constant_quad_constraints = 4 + 1 + SECURITY_PARAMETER;
-
-        CommonReferenceString::<R>::new(
-            r_pr,
-            n_pr,
-            norm_bound,
-            num_quad_constraints,
-            num_constant_quad_constraints,
-            &mut thread_rng(),
+        let r_pr = (4 + num_repetitions) * decomposition_length;
+        let n_pr = padded_size / d;
+
+        // Every coefficient of every digit has absolute value at most b/2
+        let max_digit = (decomposition_basis / 2) as f64;
+        let norm_bound_sq = (r_pr * n_pr * d) as f64 * max_digit * max_digit;
+
+        Size {
+            num_witnesses: r_pr,
+            witness_len: n_pr,
+            norm_bound_sq,
+            num_constraints: commitment_size,
+            num_constant_constraints: 3 * num_repetitions,
+        }
+    }
+
+    pub fn pr_size(&self) -> Size {
+        Self::size(
+            self.padded_size,
+            self.num_repetitions,
+            self.decomposition_basis,
+            self.decomposition_length,
+            self.A.nrows() + self.B.nrows(),
         )
     }
+
+    pub fn pr_index(&self) -> Index<R> {
+        Index::<R>::new(&self.pr_size())
+    }
+
+    #[inline(always)]
+    pub(crate) fn a_idx(&self, p: usize) -> usize {
+        p
+    }
+
+    #[inline(always)]
+    pub(crate) fn b_idx(&self, p: usize) -> usize {
+        self.decomposition_length + p
+    }
+
+    #[inline(always)]
+    pub(crate) fn c_idx(&self, p: usize) -> usize {
+        2 * self.decomposition_length + p
+    }
+
+    #[inline(always)]
+    pub(crate) fn w_idx(&self, p: usize) -> usize {
+        3 * self.decomposition_length + p
+    }
+
+    #[inline(always)]
+    pub(crate) fn d_idx(&self, l: usize, p: usize) -> usize {
+        (4 + l) * self.decomposition_length + p
+    }
 }
 
-pub fn Z2_to_R_vec<R: Ring>(vec: &Vec<Z2>) -> Vec<R> {
-    vec.iter()
-        .map(|x| if x.is_zero() { R::zero() } else { R::one() })
-        .collect()
+#[derive(Clone, Debug)]
+pub struct R1CSTranscript<R: PolyRing> {
+    /// Commitment to the decomposed vectors a, b, c and w
+    pub t: Vector<R>,
+    /// Challenges for the Hadamard product check a ∘ b = c
+    pub r: Vec<Vector<R::BaseRing>>,
+    /// Commitment to the decomposed vectors d_i = σ_{-1}(r_i ∘ b)
+    pub t_d: Vector<R>,
+    pub alpha: Vec<Vector<R::BaseRing>>,
+    pub beta: Vec<Vector<R::BaseRing>>,
+    pub gamma: Vec<Vector<R::BaseRing>>,
+    pub eps: Vec<Vector<R::BaseRing>>,
 }
 
+/// Encode a vector in Z_q^{n*d} as a vector in R^n, where each consecutive chunk of d elements is used as the coefficients of a polynomial
+pub fn encode<R: PolyRing>(vec: &[R::BaseRing]) -> Vector<R> {
+    let d = R::dimension();
+    debug_assert_eq!(
+        vec.len() % d,
+        0,
+        "vector length {} must be multiple of dimension {}",
+        vec.len(),
+        d
+    );
+    Vector::<R>::from_vec(vec.chunks(d).map(|chunk| R::from(chunk.to_vec())).collect())
+}
 
-pub struct R1CSInstance<R: Scalar> {
-    // TODO: use sparse matrices instead
-    pub A: Matrix<R>,
-    pub B: Matrix<R>,
-    pub C: Matrix<R>,
+/// Express the Z_q-linear function x -> <e, x> as an R-linear function phi such that ct(<phi, encode(x)>) = <e, x>
+pub fn embed_Zqlinear_Rqlinear<R: PolyRing>(e: &[R::BaseRing]) -> Vector<R> {
+    // ct(a * σ_{-1}(b)) is the inner product of the coefficient vectors of a and b
+    R::apply_automorphism_vec(&encode::<R>(e))
+}
+
+/// Returns [1, b, b^2, ..., b^{t-1}] over Z_q
+pub(crate) fn powers<R: PolyRing>(b: u128, t: usize) -> Vec<R::BaseRing> {
+    let b = R::BaseRing::try_from(b).unwrap();
+    let mut pows = Vec::<R::BaseRing>::with_capacity(t);
+    pows.push(R::BaseRing::one());
+    for p in 1..t {
+        pows.push(pows[p - 1] * b);
+    }
+    pows
+}
+
+/// Pad `v` with zeros to length `len`
+pub(crate) fn pad<R: PolyRing>(v: &[R::BaseRing], len: usize) -> Vector<R::BaseRing> {
+    let mut v = v.to_vec();
+    v.resize(len, R::BaseRing::zero());
+    Vector::<R::BaseRing>::from_vec(v)
+}
+
+/// Compute the component-wise product of two vectors over Z_q
+pub(crate) fn hadamard<R: PolyRing>(
+    a: &Vector<R::BaseRing>,
+    b: &Vector<R::BaseRing>,
+) -> Vector<R::BaseRing> {
+    debug_assert_eq!(a.len(), b.len());
+    Vector::<R::BaseRing>::from_vec((0..a.len()).map(|i| a[i] * b[i]).collect())
+}
+
+pub fn reduce<R: PolyRing>(
+    pp: &R1CSCRS<R>,
+    index: &SparseR1CSIndex<R::BaseRing>,
+    instance: &<R1CS<R::BaseRing> as Relation>::Instance,
+    transcript: &R1CSTranscript<R>,
+) -> (Index<R>, Instance<R>) {
+    let (A, B, C) = (&index.a, &index.b, &index.c);
+    let size = pp.padded_size;
+
+    let n_pr = size / R::dimension();
+    let t_dec = pp.decomposition_length;
+    let b_pows = powers::<R>(pp.decomposition_basis, t_dec);
+    let x = &instance.0;
+    let n_x = x.len();
+
+    let (t, r, t_d, alpha, beta, gamma, eps) = (
+        &transcript.t,
+        &transcript.r,
+        &transcript.t_d,
+        &transcript.alpha,
+        &transcript.beta,
+        &transcript.gamma,
+        &transcript.eps,
+    );
+
+    // See R1CSCRS::{a, b, c, w, d}_idx for the layout: the t digits of a, b, c, w and of each d_i
+    let mut builder = RelationBuilder::<R>::new(n_pr, pp.pr_size().norm_bound_sq);
+    let mut digits = |name: &str| {
+        (0..t_dec)
+            .map(|p| builder.witness(&format!("{name}^({p})")))
+            .collect::<Vec<_>>()
+    };
+    let (a, b, c, w) = (digits("a"), digits("b"), digits("c"), digits("w"));
+    let d_ = (0..pp.num_repetitions)
+        .map(|i| digits(&format!("d_{i}")))
+        .collect::<Vec<_>>();
+    debug_assert!((0..t_dec).all(|p| a[p].index() == pp.a_idx(p)
+        && b[p].index() == pp.b_idx(p)
+        && c[p].index() == pp.c_idx(p)
+        && w[p].index() == pp.w_idx(p)
+        && (0..pp.num_repetitions).all(|i| d_[i][p].index() == pp.d_idx(i, p))));
+    debug_assert_eq!(builder.num_witnesses(), pp.r_pr());
+
+    // F_1 = {A_i * (a || b || c || w) = t_i}_{i in [m/d]} and {B_i * (d_1 || ... || d_l) = t_d_i}_{i in [m_d/d]}
+    let linear = |M: &Matrix<R>, i: usize, vars: &[WitnessVar]| {
+        M.row(i)
+            .transpose()
+            .as_slice()
+            .chunks(n_pr)
+            .zip(vars)
+            .fold(QuadraticExpr::zero(), |expr, (M_iv, v)| {
+                expr + QuadraticExpr::linear(Vector::<R>::from_slice(M_iv), *v)
+            })
+    };
+    let (abcw, ds) = ([a.as_slice(), &b, &c, &w].concat(), d_.concat());
+    for i in 0..t.len() {
+        let expr = linear(&pp.A, i, &abcw);
+        builder.constrain(expr, t[i]);
+    }
+    for i in 0..t_d.len() {
+        let expr = linear(&pp.B, i, &ds);
+        builder.constrain(expr, t_d[i]);
+    }
+
+    // F_2
+    // <alpha_i, a> + <beta_i, b> + <gamma_i, c> - <delta_i, w> = <delta_i, x>, where delta_i = alpha_i^T A + beta_i^T B + gamma_i^T C
+    // The witness vector w is zero on the first n_x positions, which are reserved for the public input x
+    // The rows and columns added by padding are zero, so the padded entries of delta_i are zero as well
+    for i in 0..pp.num_repetitions {
+        let k = A.nrows();
+        let mut delta = (A.left_mul_vector(&alpha[i].as_slice()[..k])
+            + B.left_mul_vector(&beta[i].as_slice()[..k])
+            + C.left_mul_vector(&gamma[i].as_slice()[..k]))
+        .as_slice()
+        .to_vec();
+        delta.resize(size, R::BaseRing::zero());
+        let mut rhs = R::BaseRing::zero();
+        for j in 0..n_x {
+            rhs += delta[j] * x[j];
+        }
+        let mut delta_w = delta;
+        delta_w[..n_x].fill(R::BaseRing::zero());
+
+        let (alpha_R, beta_R, gamma_R, delta_R) = (
+            embed_Zqlinear_Rqlinear::<R>(alpha[i].as_slice()),
+            embed_Zqlinear_Rqlinear::<R>(beta[i].as_slice()),
+            embed_Zqlinear_Rqlinear::<R>(gamma[i].as_slice()),
+            embed_Zqlinear_Rqlinear::<R>(delta_w.as_slice()),
+        );
+        let expr = (0..t_dec).fold(QuadraticExpr::zero(), |expr, p| {
+            expr + QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &alpha_R), a[p])
+                + QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &beta_R), b[p])
+                + QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &gamma_R), c[p])
+                - QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &delta_R), w[p])
+        });
+        builder.constrain_ct(expr, rhs);
+    }
+
+    // <eps_i, σ_{-1}(d_i)> = <eps_i ∘ r_i, b>, which ensures that d_i = σ_{-1}(r_i ∘ b)
+    for i in 0..pp.num_repetitions {
+        let eps_R = encode::<R>(eps[i].as_slice());
+        let eps_r_R = embed_Zqlinear_Rqlinear::<R>(hadamard::<R>(&eps[i], &r[i]).as_slice());
+        let expr = (0..t_dec).fold(QuadraticExpr::zero(), |expr, p| {
+            expr + QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &eps_R), d_[i][p])
+                - QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &eps_r_R), b[p])
+        });
+        builder.constrain_ct(expr, R::BaseRing::zero());
+    }
+
+    // sum_j a_j * (r_i ∘ b)_j = <r_i, c>, which ensures that a ∘ b = c <=>
+    // sum_{p, p'} b^{p+p'} * <a^(p), d_i^(p')> - <r_i, c> = 0 (over the constant coefficients)
+    for i in 0..pp.num_repetitions {
+        let r_R = embed_Zqlinear_Rqlinear::<R>(r[i].as_slice());
+        let mut expr = QuadraticExpr::zero();
+        for p in 0..t_dec {
+            for p_ in 0..t_dec {
+                expr = expr
+                    + QuadraticExpr::inner(a[p], d_[i][p_])
+                        * R::from_scalar(b_pows[p] * b_pows[p_]);
+            }
+            expr = expr - QuadraticExpr::linear(mul_basescalar_vector(b_pows[p], &r_R), c[p]);
+        }
+        builder.constrain_ct(expr, R::BaseRing::zero());
+    }
+
+    builder.build()
 }
@@ -0,0 +1,89 @@
+#![allow(non_snake_case)]
+
+use nimue::{Arthur, BytePublic, ProofError, ProofResult};
+
+use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
+use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
+use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::nimue::arthur::SerArthur;
+use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
+use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::FromRandomBytes;
+use relations::principal_relation::{Index, Instance};
+use relations::Relation;
+
+use crate::r1cs::util::{reduce, R1CSTranscript, R1CSCRS};
+use crate::r1cs::ZqR1CS;
+use crate::sparse::{statement_digest, SparseR1CSIndex};
+use crate::verifier::verify_principal_relation;
+
+pub fn verify_reduction_r1cs_labradorpr<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &R1CSCRS<R>,
+    index: &<ZqR1CS<R> as Relation>::Index,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+) -> ProofResult<(Index<R>, Instance<R>)> {
+    verify_reduction_r1cs_labradorpr_sparse(arthur, crs, &SparseR1CSIndex::from(index), instance)
+}
+
+/// Same as [`verify_reduction_r1cs_labradorpr`], but for an index whose matrices are already in CSR format.
+pub fn verify_reduction_r1cs_labradorpr_sparse<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &R1CSCRS<R>,
+    index: &SparseR1CSIndex<R::BaseRing>,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+) -> ProofResult<(Index<R>, Instance<R>)> {
+    let k = crs.padded_size;
+    let l = crs.num_repetitions;
+
+    arthur.public_bytes(&statement_digest::<R, _>(index, &instance.0, |x| x))?;
+
+    let t = arthur.next_vector(crs.A.nrows())?;
+
+    let r = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(k, l)?;
+
+    let t_d = arthur.next_vector(crs.B.nrows())?;
+
+    let alpha = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(k, l)?;
+    let beta = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(k, l)?;
+    let gamma = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(k, l)?;
+    let eps = arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(k, l)?;
+
+    // TODO: ratchet to make sure we consumed everything?
+    let transcript = R1CSTranscript {
+        t,
+        r,
+        t_d,
+        alpha,
+        beta,
+        gamma,
+        eps,
+    };
+
+    let (index_pr, instance_pr) = reduce(crs, index, instance, &transcript);
+    Ok((index_pr, instance_pr))
+}
+
+/// Verify a proof of an R1CS over Z_q whose index is in CSR format, see [`crate::r1cs::prover::prove_r1cs`]
+pub fn verify_r1cs<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &R1CSCRS<R>,
+    index: &SparseR1CSIndex<R::BaseRing>,
+    instance: &<ZqR1CS<R> as Relation>::Instance,
+) -> Result<(), ProofError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    //TODO: add crs to transcript
+    let (index_pr, instance_pr) =
+        verify_reduction_r1cs_labradorpr_sparse(arthur, crs, index, instance)?;
+
+    arthur.ratchet()?;
+
+    verify_principal_relation(arthur, &crs.core_crs, &index_pr, &instance_pr)
+}
@@ -0,0 +1,453 @@
+#![allow(non_snake_case)]
+
+//! A builder for statements of the principal relation.
+//!
+//! Instead of indexing `phi[idx]`, filling `SymmetricMatrix` entries and padding with zero vectors by hand, allocate named
+//! witness vectors and add constraints as sums of inner products, e.g.
+//! ```ignore
+//! let mut builder = RelationBuilder::<R>::new(n, norm_bound_sq);
+//! let (a, a_conj, c) = (builder.witness("a"), builder.witness("a~"), builder.witness("c"));
+//! // <a, a~> + 2 <1, c> = 0
+//! builder.constrain(QuadraticExpr::inner(a, a_conj) + QuadraticExpr::linear(builder.ones(), c) * two, R::zero());
+//! // ct(<alpha, a>) = g
+//! builder.constrain_ct(QuadraticExpr::linear(alpha, a), g);
+//! let (index, instance) = builder.build();
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num_traits::{One, ToPrimitive, Zero};
+
+use lattirust_arithmetic::linear_algebra::{SymmetricMatrix, Vector};
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::WithL2Norm;
+use relations::principal_relation::{
+    ConstantQuadraticConstraint, Index, Instance, PrincipalRelation, QuadraticConstraint, Size,
+    Witness,
+};
+use relations::Relation;
+
+/// Handle to a witness vector allocated with `RelationBuilder::witness`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WitnessVar(usize);
+
+impl WitnessVar {
+    /// Position of the witness vector in the witness of the principal relation
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// A sum of terms $a_{ij} \langle w_i, w_j \rangle$ and $\langle \phi, w_i \rangle$ over witness vectors
+#[derive(Clone, Debug)]
+pub struct QuadraticExpr<R: PolyRing> {
+    quadratic: Vec<(WitnessVar, WitnessVar, R)>,
+    linear: Vec<(WitnessVar, Vector<R>)>,
+}
+
+impl<R: PolyRing> QuadraticExpr<R> {
+    pub fn zero() -> Self {
+        Self {
+            quadratic: vec![],
+            linear: vec![],
+        }
+    }
+
+    /// $\langle a, b \rangle$
+    pub fn inner(a: WitnessVar, b: WitnessVar) -> Self {
+        Self {
+            quadratic: vec![(a, b, R::one())],
+            linear: vec![],
+        }
+    }
+
+    /// $\langle \phi, a \rangle$
+    pub fn linear(phi: Vector<R>, a: WitnessVar) -> Self {
+        Self {
+            quadratic: vec![],
+            linear: vec![(a, phi)],
+        }
+    }
+
+    pub fn is_linear(&self) -> bool {
+        self.quadratic.is_empty()
+    }
+
+    fn evaluate(&self, s: &[Vector<R>]) -> R {
+        let inner = |a: &Vector<R>, b: &Vector<R>| {
+            a.iter()
+                .zip(b.iter())
+                .fold(R::zero(), |acc, (a_k, b_k)| acc + *a_k * *b_k)
+        };
+        let quadratic = self.quadratic.iter().fold(R::zero(), |acc, (a, b, coeff)| {
+            acc + *coeff * inner(&s[a.0], &s[b.0])
+        });
+        self.linear
+            .iter()
+            .fold(quadratic, |acc, (a, phi)| acc + inner(phi, &s[a.0]))
+    }
+}
+
+impl<R: PolyRing> Add for QuadraticExpr<R> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.quadratic.extend(rhs.quadratic);
+        self.linear.extend(rhs.linear);
+        self
+    }
+}
+
+impl<R: PolyRing> Neg for QuadraticExpr<R> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self * -R::one()
+    }
+}
+
+impl<R: PolyRing> Sub for QuadraticExpr<R> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl<R: PolyRing> Mul<R> for QuadraticExpr<R> {
+    type Output = Self;
+
+    fn mul(self, rhs: R) -> Self {
+        Self {
+            quadratic: self
+                .quadratic
+                .into_iter()
+                .map(|(a, b, coeff)| (a, b, coeff * rhs))
+                .collect(),
+            linear: self
+                .linear
+                .into_iter()
+                .map(|(a, phi)| (a, phi * rhs))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelationError {
+    /// No value was assigned to the named witness vector
+    Unassigned(String),
+    /// The k-th constraint does not hold
+    Unsatisfied(usize),
+    /// The k-th constant constraint does not hold
+    UnsatisfiedConstant(usize),
+    /// All constraints hold, but the witness exceeds the norm bound
+    NormBoundExceeded,
+    /// The built statement rejects the witness, with the error reported by the principal relation
+    Rejected(String),
+}
+
+impl Display for RelationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelationError::Unassigned(name) => write!(f, "witness vector {name} is unassigned"),
+            RelationError::Unsatisfied(k) => write!(f, "constraint {k} is not satisfied"),
+            RelationError::UnsatisfiedConstant(k) => {
+                write!(f, "constant constraint {k} is not satisfied")
+            }
+            RelationError::NormBoundExceeded => write!(f, "witness exceeds the norm bound"),
+            RelationError::Rejected(e) => write!(f, "principal relation is not satisfied: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RelationError {}
+
+/// Builds the index and instance of a principal relation from named witness vectors of length `witness_len` and
+/// constraints given as `QuadraticExpr`s
+#[derive(Clone, Debug)]
+pub struct RelationBuilder<R: PolyRing> {
+    witness_len: usize,
+    norm_bound_sq: f64,
+    names: Vec<String>,
+    constraints: Vec<(QuadraticExpr<R>, R)>,
+    ct_constraints: Vec<(QuadraticExpr<R>, R::BaseRing)>,
+}
+
+impl<R: PolyRing> RelationBuilder<R> {
+    pub fn new(witness_len: usize, norm_bound_sq: f64) -> Self {
+        Self {
+            witness_len,
+            norm_bound_sq,
+            names: vec![],
+            constraints: vec![],
+            ct_constraints: vec![],
+        }
+    }
+
+    /// Allocate the next witness vector
+    pub fn witness(&mut self, name: &str) -> WitnessVar {
+        self.names.push(name.to_string());
+        WitnessVar(self.names.len() - 1)
+    }
+
+    /// The all-ones vector, such that $\langle 1, a \rangle$ is the sum of the entries of a
+    pub fn ones(&self) -> Vector<R> {
+        Vector::<R>::from_element(self.witness_len, R::one())
+    }
+
+    /// Add the constraint `expr = rhs` over R
+    pub fn constrain(&mut self, expr: QuadraticExpr<R>, rhs: R) -> &mut Self {
+        self.check_lengths(&expr);
+        self.constraints.push((expr, rhs));
+        self
+    }
+
+    /// Add the constraint `ct(expr) = rhs` on the constant coefficient of `expr`
+    pub fn constrain_ct(&mut self, expr: QuadraticExpr<R>, rhs: R::BaseRing) -> &mut Self {
+        self.check_lengths(&expr);
+        self.ct_constraints.push((expr, rhs));
+        self
+    }
+
+    fn check_lengths(&self, expr: &QuadraticExpr<R>) {
+        for (a, phi) in &expr.linear {
+            assert_eq!(
+                phi.len(),
+                self.witness_len,
+                "coefficients for witness vector {} have the wrong length",
+                self.names[a.0]
+            );
+        }
+    }
+
+    pub fn num_witnesses(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn size(&self) -> Size {
+        Size {
+            num_witnesses: self.num_witnesses(),
+            witness_len: self.witness_len,
+            norm_bound_sq: self.norm_bound_sq,
+            num_constraints: self.constraints.len(),
+            num_constant_constraints: self.ct_constraints.len(),
+        }
+    }
+
+    /// An empty assignment to the witness vectors allocated so far
+    pub fn assignment(&self) -> WitnessAssignment<R> {
+        WitnessAssignment {
+            names: self.names.clone(),
+            witness_len: self.witness_len,
+            values: vec![None; self.num_witnesses()],
+        }
+    }
+
+    /// Check that the assignment satisfies every constraint and the norm bound
+    pub fn check(&self, assignment: &WitnessAssignment<R>) -> Result<(), RelationError> {
+        let witness = assignment.witness()?;
+        for (k, (expr, rhs)) in self.constraints.iter().enumerate() {
+            if expr.evaluate(&witness.s) != *rhs {
+                return Err(RelationError::Unsatisfied(k));
+            }
+        }
+        for (k, (expr, rhs)) in self.ct_constraints.iter().enumerate() {
+            if expr.evaluate(&witness.s).coefficients()[0] != *rhs {
+                return Err(RelationError::UnsatisfiedConstant(k));
+            }
+        }
+        let norm_sq = witness
+            .s
+            .iter()
+            .map(|s_i| R::flattened(s_i).l2_norm_squared().to_f64().unwrap())
+            .sum::<f64>();
+        if norm_sq > self.norm_bound_sq {
+            return Err(RelationError::NormBoundExceeded);
+        }
+        let (index, instance) = self.clone().build();
+        PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &witness)
+            .map_err(|e| RelationError::Rejected(e.to_string()))
+    }
+
+    /// The index and instance of the principal relation.
+    /// The quadratic part of the principal relation sums over ordered pairs of witness vectors, so a constraint with a
+    /// term $\langle w_i, w_j \rangle$ for $i \neq j$ is doubled to set $a_{ij} = a_{ji}$ to its coefficient; this needs 2 to be invertible in R.
+    pub fn build(self) -> (Index<R>, Instance<R>) {
+        let index = Index::<R>::new(&self.size());
+        let (r, n) = (self.num_witnesses(), self.witness_len);
+        let two = R::BaseRing::one() + R::BaseRing::one();
+
+        let materialize = |expr: QuadraticExpr<R>| {
+            let needs_doubling = expr.quadratic.iter().any(|(a, b, _)| a != b);
+            let scale = if needs_doubling {
+                R::from_scalar(two)
+            } else {
+                R::one()
+            };
+            let A = (!expr.is_linear()).then(|| {
+                let mut A = SymmetricMatrix::<R>::zero(r);
+                for (a, b, coeff) in &expr.quadratic {
+                    // A[(i, j)] sets A[(j, i)] as well
+                    let a_ij = if a == b { *coeff * scale } else { *coeff };
+                    A[(a.0, b.0)] = A[(a.0, b.0)] + a_ij;
+                }
+                A
+            });
+            let mut phi = vec![Vector::<R>::zeros(n); r];
+            for (a, phi_a) in expr.linear {
+                phi[a.0] += phi_a * scale;
+            }
+            (A, phi, needs_doubling)
+        };
+
+        let quad_dot_prod_funcs = self
+            .constraints
+            .into_iter()
+            .map(|(expr, rhs)| match materialize(expr) {
+                (Some(A), phi, needs_doubling) => QuadraticConstraint::<R>::new(
+                    A,
+                    phi,
+                    if needs_doubling {
+                        rhs * R::from_scalar(two)
+                    } else {
+                        rhs
+                    },
+                ),
+                (None, phi, _) => QuadraticConstraint::<R>::new_linear(phi, rhs),
+            })
+            .collect();
+        let ct_quad_dot_prod_funcs = self
+            .ct_constraints
+            .into_iter()
+            .map(|(expr, rhs)| match materialize(expr) {
+                (Some(A), phi, needs_doubling) => ConstantQuadraticConstraint::<R>::new(
+                    A,
+                    phi,
+                    if needs_doubling { rhs * two } else { rhs },
+                ),
+                (None, phi, _) => ConstantQuadraticConstraint::<R>::new_linear(phi, rhs),
+            })
+            .collect();
+
+        (
+            index,
+            Instance::<R> {
+                quad_dot_prod_funcs,
+                ct_quad_dot_prod_funcs,
+            },
+        )
+    }
+}
+
+/// Values for the witness vectors of a `RelationBuilder`
+#[derive(Clone, Debug)]
+pub struct WitnessAssignment<R: PolyRing> {
+    names: Vec<String>,
+    witness_len: usize,
+    values: Vec<Option<Vector<R>>>,
+}
+
+impl<R: PolyRing> WitnessAssignment<R> {
+    pub fn assign(&mut self, var: WitnessVar, value: Vector<R>) -> &mut Self {
+        assert_eq!(
+            value.len(),
+            self.witness_len,
+            "value for witness vector {} has the wrong length",
+            self.names[var.0]
+        );
+        self.values[var.0] = Some(value);
+        self
+    }
+
+    /// The witness of the principal relation, failing if a witness vector is unassigned
+    pub fn witness(&self) -> Result<Witness<R>, RelationError> {
+        self.values
+            .iter()
+            .zip(self.names.iter())
+            .map(|(value, name)| {
+                value
+                    .clone()
+                    .ok_or_else(|| RelationError::Unassigned(name.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Witness::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+
+    use super::*;
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    fn constant(c: u128) -> R {
+        R::try_from(c).unwrap()
+    }
+
+    #[test]
+    fn test_quadratic_and_linear_constraints() {
+        let n = 4;
+        let mut builder = RelationBuilder::<R>::new(n, 1000.);
+        let a = builder.witness("a");
+        let b = builder.witness("b");
+        let c = builder.witness("c");
+
+        let value_a = Vector::<R>::from_vec(vec![constant(1), constant(2), R::zero(), R::zero()]);
+        let value_b = Vector::<R>::from_vec(vec![constant(3), R::zero(), constant(1), R::zero()]);
+        let value_c = Vector::<R>::from_element(n, constant(1));
+
+        // <a, b> - <1, c> = -1
+        builder.constrain(
+            QuadraticExpr::inner(a, b) - QuadraticExpr::linear(builder.ones(), c),
+            -R::one(),
+        );
+        // <a, a> + 2 <b, c> = 13
+        builder.constrain(
+            QuadraticExpr::inner(a, a) + QuadraticExpr::inner(b, c) * constant(2),
+            constant(13),
+        );
+        // ct(<e_0, a>) = 1
+        let mut e_0 = Vector::<R>::zeros(n);
+        e_0[0] = R::one();
+        builder.constrain_ct(QuadraticExpr::linear(e_0, a), R::BaseRing::one());
+
+        let mut assignment = builder.assignment();
+        assignment.assign(a, value_a.clone()).assign(b, value_b);
+        assert_eq!(
+            builder.check(&assignment),
+            Err(RelationError::Unassigned("c".to_string()))
+        );
+        assignment.assign(c, value_c);
+        assert_eq!(builder.check(&assignment), Ok(()));
+
+        let (index, instance) = builder.clone().build();
+        let witness = assignment.witness().unwrap();
+        assert!(PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &witness).is_ok());
+
+        assignment.assign(a, value_a * constant(2));
+        assert_eq!(
+            builder.check(&assignment),
+            Err(RelationError::Unsatisfied(0))
+        );
+        let witness = assignment.witness().unwrap();
+        assert!(PrincipalRelation::<R>::is_satisfied_err(&index, &instance, &witness).is_err());
+    }
+
+    #[test]
+    fn test_norm_bound() {
+        let mut builder = RelationBuilder::<R>::new(1, 3.);
+        let a = builder.witness("a");
+        builder.constrain(QuadraticExpr::inner(a, a), constant(4));
+
+        let mut assignment = builder.assignment();
+        assignment.assign(a, Vector::<R>::from_element(1, constant(2)));
+        assert_eq!(
+            builder.check(&assignment),
+            Err(RelationError::NormBoundExceeded)
+        );
+    }
+}
@@ -1,13 +1,18 @@
 #![allow(non_snake_case)]
 
+use std::fmt::{Debug, Formatter};
+use std::sync::OnceLock;
+
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
 use lattirust_arithmetic::linear_algebra::{Matrix, SymmetricMatrix, Vector};
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
 use lattirust_arithmetic::ring::PolyRing;
 use num_traits::zero;
+use rayon::prelude::*;
 use relations::principal_relation::{Index, Instance, QuadraticConstraint, Size};
 
 use crate::common_reference_string::{CommonReferenceString, FoldedSize};
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink};
 use crate::util::{flatten_symmetric_matrix, mul_basescalar_vector};
 
 /// A view of the transcript of one execution of the core Labrador protocol
@@ -22,6 +27,7 @@ pub struct TranscriptView<R: PolyRing> {
     pub(crate) a__: Vec<SymmetricMatrix<R>>,
 }
 
+#[derive(Clone)]
 pub struct Layouter<R: PolyRing> {
     pub folded_size: FoldedSize,
     pub vec: Vec<R>,
@@ -105,12 +111,153 @@ impl<R: PolyRing> Layouter<R> {
     }
 }
 
+/// The parts of the folded instance that only depend on the CRS of a round, which the CRS computes once for all proofs
+/// and verifications under it, see `CommonReferenceString::folding_precomputation`
+pub struct FoldingPrecomputation<R: PolyRing> {
+    b_ring: R,
+    b1_pows: Vec<R>,
+    b2_pows: Vec<R>,
+    /// Layout of the constraint for the l-th row of Az = sum_{i in [r]} c_i t_i, with the z part set
+    az_layouters: Vec<Layouter<R>>,
+    /// Coefficients of the constraints for u_1
+    pub(crate) u_1_phi: Vec<Vec<Vector<R>>>,
+    /// Coefficients of the constraints for u_2
+    pub(crate) u_2_phi: Vec<Vec<Vector<R>>>,
+}
+
+impl<R: PolyRing> FoldingPrecomputation<R> {
+    pub fn new(crs: &CommonReferenceString<R>) -> Self {
+        let next_size = crs.next_size();
+
+        let b_ring = R::try_from(crs.b).unwrap();
+
+        let b1_ring = R::try_from(crs.b1).unwrap();
+        let mut b1_pows = Vec::<R>::with_capacity(crs.t1);
+        b1_pows.push(R::one());
+        for i in 1..crs.t1 {
+            b1_pows.push(b1_ring * b1_pows[i - 1]);
+        }
+
+        let b2_ring = R::try_from(crs.b2).unwrap();
+        let mut b2_pows = Vec::<R>::with_capacity(crs.t2);
+        b2_pows.push(R::one());
+        for i in 1..crs.t2 {
+            b2_pows.push(b2_ring * b2_pows[i - 1]);
+        }
+
+        let az_layouters = (0..crs.k)
+            .map(|l| {
+                let mut layouter = Layouter::<R>::new(next_size);
+                let A_l = crs.A.row(l).transpose();
+                layouter.set_z0(A_l.as_slice()); // <A_l, z_0>
+                layouter.set_z1((&A_l * b_ring).as_slice()); // <A_l * b, z_1>
+                layouter
+            })
+            .collect();
+
+        let u_1_phi = (0..crs.k1)
+            .map(|l| {
+                let mut layouter = Layouter::<R>::new(next_size);
+                layouter.set_t(&crs.B.row(l).transpose().as_slice());
+                layouter.set_g(crs.C.row(l).transpose().as_slice());
+                layouter.split()
+            })
+            .collect();
+
+        let u_2_phi = (0..crs.k2)
+            .map(|l| {
+                let mut layouter = Layouter::<R>::new(next_size);
+                layouter.set_h(crs.D.row(l).transpose().as_slice());
+                layouter.split()
+            })
+            .collect();
+
+        Self {
+            b_ring,
+            b1_pows,
+            b2_pows,
+            az_layouters,
+            u_1_phi,
+            u_2_phi,
+        }
+    }
+}
+
+/// The `FoldingPrecomputation` of a CRS, built the first time it is needed, see
+/// `CommonReferenceString::folding_precomputation`.
+/// A clone of a CRS starts with an empty cache, since its matrices may be changed independently of the original.
+pub struct FoldingCache<R: PolyRing>(OnceLock<FoldingPrecomputation<R>>);
+
+impl<R: PolyRing> Default for FoldingCache<R> {
+    fn default() -> Self {
+        Self(OnceLock::new())
+    }
+}
+
+impl<R: PolyRing> Clone for FoldingCache<R> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<R: PolyRing> Debug for FoldingCache<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FoldingCache")
+            .field("is_built", &self.0.get().is_some())
+            .finish()
+    }
+}
+
+/// The cache is determined by the rest of the CRS, so it does not affect equality
+impl<R: PolyRing> PartialEq for FoldingCache<R> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<R: PolyRing> FoldingCache<R> {
+    pub(crate) fn get_or_build(&self, crs: &CommonReferenceString<R>) -> &FoldingPrecomputation<R> {
+        self.0.get_or_init(|| FoldingPrecomputation::new(crs))
+    }
+}
+
 // TODO: add tracing info with size of padding to enable efficiency fine-tuning
 pub fn fold_instance<R: PolyRing>(
     crs: &CommonReferenceString<R>,
     instance: &Instance<R>,
     transcript: &TranscriptView<R>,
 ) -> (Index<R>, Instance<R>)
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    fold_instance_precomputed(crs, crs.folding_precomputation(), instance, transcript)
+}
+
+/// `fold_instance` with the parts that only depend on the CRS given in `pre`, which must be computed for `crs`
+pub fn fold_instance_precomputed<R: PolyRing>(
+    crs: &CommonReferenceString<R>,
+    pre: &FoldingPrecomputation<R>,
+    instance: &Instance<R>,
+    transcript: &TranscriptView<R>,
+) -> (Index<R>, Instance<R>)
+where
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    fold_instance_with_progress(crs, pre, instance, transcript, &NoProgress)
+}
+
+/// `fold_instance_precomputed`, reporting each constraint of the folded instance to `progress`
+pub(crate) fn fold_instance_with_progress<R: PolyRing>(
+    crs: &CommonReferenceString<R>,
+    pre: &FoldingPrecomputation<R>,
+    instance: &Instance<R>,
+    transcript: &TranscriptView<R>,
+    progress: &dyn ProgressSink,
+) -> (Index<R>, Instance<R>)
 where
     <R as PolyRing>::BaseRing: WithSignedRepresentative,
     <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
@@ -118,6 +265,14 @@ where
 {
     // Generate instance for next iteration of the protocol
     let next_size = crs.next_size();
+    // Az = sum_{i in [r]} c_i t_i, <z, z>, the inner products with phi, the aggregated constraint, u_1 and u_2
+    let num_constraints_next = crs.k + 3 + crs.k1 + crs.k2;
+    let report = |quad_dot_prod_funcs_next: &Vec<QuadraticConstraint<R>>| {
+        progress.report(ProgressEvent::FoldConstraints {
+            done: quad_dot_prod_funcs_next.len(),
+            total: num_constraints_next,
+        })
+    };
 
     let r_next = next_size.size.num_witnesses;
     let n_next = next_size.size.witness_len;
@@ -126,29 +281,14 @@ where
     let mut quad_dot_prod_funcs_next =
         Vec::<QuadraticConstraint<R>>::with_capacity(next_size.size.num_constraints);
 
-    let b_ring = R::try_from(crs.b).unwrap();
-
-    let b1_ring = R::try_from(crs.b1).unwrap();
-    let mut b1_pows = Vec::<R>::with_capacity(crs.t1);
-    b1_pows.push(R::one());
-    for i in 1..crs.t1 {
-        b1_pows.push(b1_ring * b1_pows[i - 1]);
-    }
-
-    let b2_ring = R::try_from(crs.b2).unwrap();
-    let mut b2_pows = Vec::<R>::with_capacity(crs.t2);
-    b2_pows.push(R::one());
-    for i in 1..crs.t2 {
-        b2_pows.push(b2_ring * b2_pows[i - 1]);
-    }
+    let b_ring = pre.b_ring;
+    let b1_pows = &pre.b1_pows;
+    let b2_pows = &pre.b2_pows;
 
     // Constraints for Az = sum_{i in [r]} c_i t_i
     {
         for l in 0..crs.k {
-            let mut layouter = Layouter::<R>::new(next_size);
-            let A_l = crs.A.row(l).transpose();
-            layouter.set_z0(A_l.as_slice()); // <A_l, z_0>
-            layouter.set_z1((&A_l * b_ring).as_slice()); // <A_l * b, z_1>
+            let mut layouter = pre.az_layouters[l].clone();
 
             // <(t_i^(k)_l)_{k in [t1], i in [r]}, (c_i * b^k)_{k in [t1], i in [r]})> = sum_{k in [t1]} sum_{i in [r]} t_i^(k)_l * c_i * b^k
             let mut c_vec = vec![R::zero(); next_size.size_t];
@@ -162,6 +302,7 @@ where
             quad_dot_prod_funcs_next.push(QuadraticConstraint::<R>::new_homogeneous_linear(
                 layouter.split(),
             ));
+            report(&quad_dot_prod_funcs_next);
         }
     }
 
@@ -206,6 +347,7 @@ where
             layouter.split(),
             R::zero(),
         ));
+        report(&quad_dot_prod_funcs_next);
     }
 
     // Constraint for sum_{i in [r]} <phi_i, z> c_i = sum_{i, j in [r]} h_ij c_i c_j
@@ -229,6 +371,7 @@ where
         quad_dot_prod_funcs_next.push(QuadraticConstraint::<R>::new_homogeneous_linear(
             layouter.split(),
         ));
+        report(&quad_dot_prod_funcs_next);
     }
 
     // Constraint for sum_{i,j in [r]} a_ij * g_ij + sum_{i in [r]} h_ii = b
@@ -283,32 +426,28 @@ where
         }
 
         quad_dot_prod_funcs_next.push(QuadraticConstraint::<R>::new_linear(layouter.split(), b));
+        report(&quad_dot_prod_funcs_next);
     }
 
     // Constraints for u_1
     {
         for l in 0..crs.k1 {
-            let mut layouter = Layouter::<R>::new(next_size);
-            layouter.set_t(&crs.B.row(l).transpose().as_slice());
-            layouter.set_g(crs.C.row(l).transpose().as_slice());
-
             quad_dot_prod_funcs_next.push(QuadraticConstraint::<R>::new_linear(
-                layouter.split(),
+                pre.u_1_phi[l].clone(),
                 transcript.u_1[l],
             ));
+            report(&quad_dot_prod_funcs_next);
         }
     }
 
     // Constraints for u_2
     {
         for l in 0..crs.k2 {
-            let mut layouter = Layouter::<R>::new(next_size);
-            layouter.set_h(crs.D.row(l).transpose().as_slice());
-
             quad_dot_prod_funcs_next.push(QuadraticConstraint::<R>::new_linear(
-                layouter.split(),
+                pre.u_2_phi[l].clone(),
                 transcript.u_2[l],
             ));
+            report(&quad_dot_prod_funcs_next);
         }
     }
 
@@ -343,20 +482,19 @@ pub fn compute_phi__<R: PolyRing>(
     omega: &Vec<Vector<R::BaseRing>>,
 ) -> Vec<Vec<Vector<R>>> {
     let mut phi__ = vec![vec![Vector::<R>::zeros(index.n); index.r]; crs.num_aggregs];
-    for k in 0..crs.num_aggregs {
-        for i in 0..index.r {
+    phi__.par_iter_mut().enumerate().for_each(|(k, phi_k)| {
+        phi_k.par_iter_mut().enumerate().for_each(|(i, phi_ki)| {
             // Compute vec{phi}''_i^{(k)}
             for l in 0..instance.ct_quad_dot_prod_funcs.len() {
-                phi__[k][i] +=
+                *phi_ki +=
                     mul_basescalar_vector(psi[k][l], &instance.ct_quad_dot_prod_funcs[l].phi[i]);
             }
             for j in 0..256 {
                 let pi_ij = &Pi[i].row(j).transpose(); // Vector of n elements in R
-                phi__[k][i] +=
-                    mul_basescalar_vector(omega[k][j], &R::apply_automorphism_vec(&pi_ij));
+                *phi_ki += mul_basescalar_vector(omega[k][j], &R::apply_automorphism_vec(&pi_ij));
             }
-        }
-    }
+        });
+    });
     phi__
 }
 
@@ -383,27 +521,26 @@ pub fn compute_phi<R: PolyRing>(
     phi
 }
 
+/// a''^(k) = sum_{l} psi_l^(k) * a'^(l) for the constant constraints, accumulated in place without copying their
+/// coefficient matrices
 pub fn compute_a__<R: PolyRing>(
-    _crs: &CommonReferenceString<R>,
+    crs: &CommonReferenceString<R>,
     instance: &Instance<R>,
     psi: &Vec<Vector<R::BaseRing>>,
 ) -> Vec<SymmetricMatrix<R>> {
-    psi.into_iter()
+    psi.par_iter()
         .map(|psi_k| {
-            psi_k
-                .into_iter()
-                .zip(instance.ct_quad_dot_prod_funcs.to_owned().into_iter())
-                .filter_map(|(psi_k_j, constraint)| {
-                    constraint.A.and_then(|A_j| Some((psi_k_j, A_j)))
-                })
-                .fold(None, |acc, (psi_k_j, A_j)| {
-                    let prod = A_j * psi_k_j.clone();
-                    match acc {
-                        None => Some(prod),
-                        Some(a) => Some(a + prod),
+            let mut a_k = SymmetricMatrix::<R>::zero(crs.r);
+            for (psi_k_l, constraint) in psi_k.iter().zip(instance.ct_quad_dot_prod_funcs.iter()) {
+                if let Some(A_l) = &constraint.A {
+                    for i in 0..crs.r {
+                        for j in 0..=i {
+                            a_k[(i, j)] += A_l[(i, j)] * *psi_k_l;
+                        }
                     }
-                })
-                .unwrap_or(SymmetricMatrix::<R>::zero(_crs.r))
+                }
+            }
+            a_k
         })
         .collect()
 }
@@ -0,0 +1,344 @@
+#![allow(non_snake_case)]
+
+use std::ops::Mul;
+
+use nimue::{ByteChallenges, ByteIOPattern, IOPattern};
+use num_traits::Zero;
+
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
+use lattirust_arithmetic::nimue::iopattern::SerIOPattern;
+use lattirust_arithmetic::nimue::merlin::SerMerlin;
+use lattirust_arithmetic::ring::{PolyRing, Ring};
+use relations::r1cs::Index as R1CSIndex;
+
+/// Length in bytes of the digest of an R1CS statement, see `statement_digest`
+pub(crate) const STATEMENT_DIGEST_LEN: usize = 32;
+
+/// A matrix in compressed sparse row (CSR) format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix<F> {
+    nrows: usize,
+    ncols: usize,
+    row_ptr: Vec<usize>,
+    col_idx: Vec<usize>,
+    values: Vec<F>,
+}
+
+impl<F: Ring> SparseMatrix<F> {
+    pub fn from_dense(M: &Matrix<F>) -> Self {
+        let (nrows, ncols) = (M.nrows(), M.ncols());
+        let mut row_ptr = Vec::<usize>::with_capacity(nrows + 1);
+        let mut col_idx = Vec::<usize>::new();
+        let mut values = Vec::<F>::new();
+        row_ptr.push(0);
+        for i in 0..nrows {
+            for j in 0..ncols {
+                let v = M[(i, j)];
+                if !v.is_zero() {
+                    col_idx.push(j);
+                    values.push(v);
+                }
+            }
+            row_ptr.push(col_idx.len());
+        }
+        Self {
+            nrows,
+            ncols,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    /// Build a matrix from `(row, column, value)` triplets; entries with the same position are summed.
+    pub fn from_triplets(nrows: usize, ncols: usize, triplets: &[(usize, usize, F)]) -> Self {
+        let mut rows = vec![Vec::<(usize, F)>::new(); nrows];
+        for &(i, j, v) in triplets {
+            assert!(
+                i < nrows && j < ncols,
+                "entry ({i}, {j}) out of bounds for a {nrows}x{ncols} matrix"
+            );
+            rows[i].push((j, v));
+        }
+
+        let mut row_ptr = Vec::<usize>::with_capacity(nrows + 1);
+        let mut col_idx = Vec::<usize>::with_capacity(triplets.len());
+        let mut values = Vec::<F>::with_capacity(triplets.len());
+        row_ptr.push(0);
+        for mut row in rows {
+            row.sort_by_key(|(j, _)| *j);
+            for (j, v) in row {
+                if col_idx.len() > *row_ptr.last().unwrap() && *col_idx.last().unwrap() == j {
+                    *values.last_mut().unwrap() += v;
+                } else {
+                    col_idx.push(j);
+                    values.push(v);
+                }
+            }
+            row_ptr.push(col_idx.len());
+        }
+        let mut res = Self {
+            nrows,
+            ncols,
+            row_ptr,
+            col_idx,
+            values,
+        };
+        res.prune();
+        res
+    }
+
+    /// Remove explicitly stored zeros.
+    fn prune(&mut self) {
+        let mut row_ptr = Vec::<usize>::with_capacity(self.nrows + 1);
+        let mut col_idx = Vec::<usize>::with_capacity(self.col_idx.len());
+        let mut values = Vec::<F>::with_capacity(self.values.len());
+        row_ptr.push(0);
+        for i in 0..self.nrows {
+            for (j, v) in self.row(i) {
+                if !v.is_zero() {
+                    col_idx.push(j);
+                    values.push(*v);
+                }
+            }
+            row_ptr.push(col_idx.len());
+        }
+        self.row_ptr = row_ptr;
+        self.col_idx = col_idx;
+        self.values = values;
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    /// Number of stored non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterate over the non-zero entries `(column, value)` of row `i`.
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, &F)> {
+        let range = self.row_ptr[i]..self.row_ptr[i + 1];
+        self.col_idx[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.values[range].iter())
+    }
+
+    pub fn to_dense(&self) -> Matrix<F> {
+        let mut M = Matrix::<F>::zeros(self.nrows, self.ncols);
+        for i in 0..self.nrows {
+            for (j, v) in self.row(i) {
+                M[(i, j)] = *v;
+            }
+        }
+        M
+    }
+
+    /// Compute $M \cdot v$ in time linear in the number of non-zeros of $M$.
+    pub fn mul_vector(&self, v: &[F]) -> Vector<F> {
+        assert_eq!(
+            v.len(),
+            self.ncols,
+            "vector has length {} but matrix has {} columns",
+            v.len(),
+            self.ncols
+        );
+        Vector::<F>::from_vec(
+            (0..self.nrows)
+                .map(|i| {
+                    self.row(i)
+                        .fold(F::zero(), |acc, (j, m_ij)| acc + *m_ij * v[j])
+                })
+                .collect(),
+        )
+    }
+
+    /// Compute $v^T \cdot M$ in time linear in the number of non-zeros of $M$.
+    pub fn left_mul_vector(&self, v: &[F]) -> Vector<F> {
+        assert_eq!(
+            v.len(),
+            self.nrows,
+            "vector has length {} but matrix has {} rows",
+            v.len(),
+            self.nrows
+        );
+        let mut res = vec![F::zero(); self.ncols];
+        for (i, v_i) in v.iter().enumerate() {
+            if v_i.is_zero() {
+                continue;
+            }
+            for (j, m_ij) in self.row(i) {
+                res[j] += *v_i * *m_ij;
+            }
+        }
+        Vector::<F>::from_vec(res)
+    }
+}
+
+impl<F: Ring> Mul<&Vector<F>> for &SparseMatrix<F> {
+    type Output = Vector<F>;
+
+    fn mul(self, rhs: &Vector<F>) -> Self::Output {
+        self.mul_vector(rhs.as_slice())
+    }
+}
+
+/// An R1CS index `(A, B, C)` with its matrices stored in CSR format.
+#[derive(Clone, Debug)]
+pub struct SparseR1CSIndex<F> {
+    pub a: SparseMatrix<F>,
+    pub b: SparseMatrix<F>,
+    pub c: SparseMatrix<F>,
+}
+
+impl<F: Ring> From<&R1CSIndex<F>> for SparseR1CSIndex<F> {
+    fn from(index: &R1CSIndex<F>) -> Self {
+        Self {
+            a: SparseMatrix::from_dense(&index.a),
+            b: SparseMatrix::from_dense(&index.b),
+            c: SparseMatrix::from_dense(&index.c),
+        }
+    }
+}
+
+/// Digest of an R1CS statement over F, i.e., of the non-zero entries of A, B and C with their positions and of the public
+/// input x, each mapped to Z_q with `to_zq`. Prover and verifier absorb it before the first challenge, so that the
+/// challenges depend on the statement.
+pub(crate) fn statement_digest<R: PolyRing, F: Ring>(
+    index: &SparseR1CSIndex<F>,
+    x: &Vector<F>,
+    to_zq: impl Fn(F) -> R::BaseRing,
+) -> [u8; STATEMENT_DIGEST_LEN] {
+    let from_usize = |m: usize| R::BaseRing::try_from(m as u128).unwrap();
+    let mut statement = vec![];
+    for M in [&index.a, &index.b, &index.c] {
+        statement.extend([
+            from_usize(M.nrows()),
+            from_usize(M.ncols()),
+            from_usize(M.nnz()),
+        ]);
+        for i in 0..M.nrows() {
+            for (j, m_ij) in M.row(i) {
+                statement.extend([from_usize(i), from_usize(j), to_zq(*m_ij)]);
+            }
+        }
+    }
+    statement.push(from_usize(x.len()));
+    statement.extend(x.iter().map(|x_i| to_zq(*x_i)));
+
+    let io = IOPattern::new("r1cs_statement_digest")
+        .absorb_vector_canonical::<R::BaseRing>(statement.len(), "statement")
+        .challenge_bytes(STATEMENT_DIGEST_LEN, "digest");
+    let mut merlin = io.to_merlin();
+    merlin
+        .absorb_vector_canonical::<R::BaseRing>(&Vector::<R::BaseRing>::from_vec(statement))
+        .expect("error absorbing the statement");
+    let mut digest = [0u8; STATEMENT_DIGEST_LEN];
+    merlin
+        .fill_challenge_bytes(&mut digest)
+        .expect("error squeezing the digest of the statement");
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_std::rand::{thread_rng, Rng};
+    use num_traits::One;
+
+    use lattirust_arithmetic::ring::Z2;
+
+    use super::*;
+    use crate::gf2::{sum_binary_products, BitMatrix};
+
+    fn random_sparse_binary(nrows: usize, ncols: usize, nnz_per_row: usize) -> Matrix<Z2> {
+        let rng = &mut thread_rng();
+        let mut M = Matrix::<Z2>::zeros(nrows, ncols);
+        for i in 0..nrows {
+            for _ in 0..nnz_per_row {
+                M[(i, rng.gen_range(0..ncols))] = Z2::one();
+            }
+        }
+        M
+    }
+
+    fn random_binary(nrows: usize, ncols: usize) -> Matrix<Z2> {
+        let rng = &mut thread_rng();
+        Matrix::<Z2>::from_fn(nrows, ncols, |_, _| {
+            if rng.gen::<bool>() {
+                Z2::one()
+            } else {
+                Z2::zero()
+            }
+        })
+    }
+
+    fn random_binary_vector(n: usize) -> Vector<Z2> {
+        let rng = &mut thread_rng();
+        Vector::<Z2>::from_vec(
+            (0..n)
+                .map(|_| {
+                    if rng.gen::<bool>() {
+                        Z2::one()
+                    } else {
+                        Z2::zero()
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_roundtrip_dense() {
+        let M = random_sparse_binary(64, 96, 3);
+        let S = SparseMatrix::from_dense(&M);
+        assert!(S.nnz() <= 64 * 3);
+        assert_eq!(S.to_dense(), M);
+    }
+
+    #[test]
+    fn test_from_triplets() {
+        let S = SparseMatrix::<Z2>::from_triplets(
+            2,
+            3,
+            &[(1, 2, Z2::one()), (0, 1, Z2::one()), (1, 2, Z2::one())],
+        );
+        // the two entries at (1, 2) cancel over Z2
+        assert_eq!(S.nnz(), 1);
+        assert_eq!(S.row(0).collect::<Vec<_>>(), vec![(1, &Z2::one())]);
+    }
+
+    #[test]
+    fn test_mul_vector() {
+        let M = random_sparse_binary(64, 96, 5);
+        let S = SparseMatrix::from_dense(&M);
+        let v = random_binary_vector(96);
+        assert_eq!(&S * &v, &M * &v);
+
+        let u = random_binary_vector(64);
+        let expected = Vector::<Z2>::from_vec(
+            (0..96)
+                .map(|j| (0..64).fold(Z2::zero(), |acc, i| acc + u[i] * M[(i, j)]))
+                .collect(),
+        );
+        assert_eq!(S.left_mul_vector(u.as_slice()), expected);
+    }
+
+    #[test]
+    fn test_sum_binary_products() {
+        let (lambda, k, n) = (130, 64, 96);
+        let (A, B) = (random_sparse_binary(k, n, 4), random_sparse_binary(k, n, 4));
+        let (alpha, beta) = (random_binary(lambda, k), random_binary(lambda, k));
+
+        let delta = sum_binary_products(&[
+            (&BitMatrix::from(&alpha), &SparseMatrix::from_dense(&A)),
+            (&BitMatrix::from(&beta), &SparseMatrix::from_dense(&B)),
+        ]);
+        assert_eq!(delta.to_dense(), &alpha * &A + &beta * &B);
+    }
+}
@@ -0,0 +1,363 @@
+#![allow(non_snake_case)]
+
+//! Stacking of independent statements of the principal relation into one, which is proven with a single run of the
+//! protocol, so that the proof grows logarithmically instead of linearly in the number of statements.
+//!
+//! The witness vectors of all statements are concatenated, shorter vectors padded with zeros to the longest length,
+//! and each constraint only involves the witness vectors of its own statement. Padding does not change inner products
+//! or norms, so the stacked statement is satisfied if and only if every constraint of every statement is, and every
+//! witness is within the norm bound of its own statement.
+//!
+//! The protocol only bounds the norm of the whole witness, so each witness s_j is bounded by a constant constraint:
+//! - s_j is followed by a slack vector e_j, such that $||s_j||^2 + ||e_j||^2 = \beta_j^2$ (rounded down), and the
+//!   stacked witness ends with the conjugates $\sigma_{-1}(w)$ of all these vectors;
+//! - the constant constraint of s_j is $ct(\sum_w \langle w, \tilde{w} \rangle) = \beta_j^2$, where w ranges over the
+//!   vectors of s_j and e_j, and $\tilde{w}$ is the vector at the position of the conjugate of w;
+//! - the norm bound of the stacked witness is $2 \sum_j \beta_j^2$, which is below q.
+//!
+//! $ct(\langle w, \tilde{w} \rangle)$ is the inner product of the coefficients of w and of $\sigma_{-1}(\tilde{w})$, so
+//! by Cauchy-Schwarz, the constraint of s_j forces the squared norms of (s_j, e_j) and of their conjugate positions to
+//! sum to at least $2 \beta_j^2$. Summed over all statements, this is the whole norm bound, so every one of these sums
+//! is exactly $2 \beta_j^2$, which only happens if the conjugate positions do hold the conjugates, and
+//! $||s_j||^2 \leq \beta_j^2$.
+//! ```ignore
+//! let stacked = StackedStatement::new(&[(&index_1, &instance_1), (&index_2, &instance_2)]);
+//! let witness = stacked.witness(&[&witness_1, &witness_2]);
+//! let crs = CommonReferenceString::<R>::new_for_size(stacked.size);
+//! prove_principal_relation(&mut merlin, &crs, &stacked.index, &stacked.instance, &witness)?;
+//! ```
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+
+use lattirust_arithmetic::linear_algebra::{SymmetricMatrix, Vector};
+use lattirust_arithmetic::ring::PolyRing;
+use lattirust_arithmetic::traits::WithL2Norm;
+use relations::principal_relation::{
+    ConstantQuadraticConstraint, Index, Instance, QuadraticConstraint, Size, Witness,
+};
+
+use crate::falcon_agg::util::slack;
+
+#[derive(Clone, Debug)]
+pub struct StackedStatement<R: PolyRing> {
+    pub size: Size,
+    pub index: Index<R>,
+    pub instance: Instance<R>,
+    /// Position of the first witness vector of each statement in the stacked witness
+    pub offsets: Vec<usize>,
+    /// Squared norm bound of the witness of each statement, rounded down
+    pub norm_bounds_sq: Vec<u64>,
+}
+
+/// Pad v with zeros to length n
+fn pad<R: PolyRing>(v: &Vector<R>, n: usize) -> Vector<R> {
+    Vector::<R>::from_fn(n, |j, _| if j < v.len() { v[j] } else { R::zero() })
+}
+
+/// Place the coefficients of a constraint on r_k witness vectors at the given offset among r witness vectors of length n
+fn embed<R: PolyRing>(
+    A_k: &Option<SymmetricMatrix<R>>,
+    phi_k: &[Vector<R>],
+    offset: usize,
+    r: usize,
+    n: usize,
+) -> (Option<SymmetricMatrix<R>>, Vec<Vector<R>>) {
+    let A = A_k.as_ref().map(|A_k| {
+        let mut A = SymmetricMatrix::<R>::zero(r);
+        for i in 0..phi_k.len() {
+            for j in 0..=i {
+                // A[(i, j)] sets A[(j, i)] as well
+                A[(offset + i, offset + j)] = A_k[(i, j)];
+            }
+        }
+        A
+    });
+    let mut phi = vec![Vector::<R>::zeros(n); r];
+    for (i, phi_i) in phi_k.iter().enumerate() {
+        phi[offset + i] = pad(phi_i, n);
+    }
+    (A, phi)
+}
+
+impl<R: PolyRing> StackedStatement<R> {
+    /// Stack the statements, whose witnesses will be concatenated in the same order
+    pub fn new(statements: &[(&Index<R>, &Instance<R>)]) -> Self {
+        assert!(!statements.is_empty(), "no statements to stack");
+        assert!(
+            R::dimension() >= 4,
+            "the slack for the norm bounds needs 4 coefficients, but R has dimension {}",
+            R::dimension()
+        );
+        let n = statements.iter().map(|(index, _)| index.n).max().unwrap();
+        let offsets = statements
+            .iter()
+            .scan(0, |offset, (index, _)| {
+                let offset_k = *offset;
+                *offset += index.r;
+                Some(offset_k)
+            })
+            .collect::<Vec<_>>();
+        let num_statement_witnesses = statements.iter().map(|(index, _)| index.r).sum::<usize>();
+        // The vectors of the statements and the slack vectors, followed by their conjugates
+        let h = num_statement_witnesses + statements.len();
+        let r = 2 * h;
+
+        let mut quad_dot_prod_funcs = vec![];
+        let mut ct_quad_dot_prod_funcs = vec![];
+        for ((_, instance), offset) in statements.iter().zip(offsets.iter()) {
+            for constraint in &instance.quad_dot_prod_funcs {
+                quad_dot_prod_funcs.push(
+                    match embed(&constraint.A, &constraint.phi, *offset, r, n) {
+                        (Some(A), phi) => QuadraticConstraint::<R>::new(A, phi, constraint.b),
+                        (None, phi) => QuadraticConstraint::<R>::new_linear(phi, constraint.b),
+                    },
+                );
+            }
+            for constraint in &instance.ct_quad_dot_prod_funcs {
+                ct_quad_dot_prod_funcs.push(
+                    match embed(&constraint.A, &constraint.phi, *offset, r, n) {
+                        (Some(A), phi) => {
+                            ConstantQuadraticConstraint::<R>::new(A, phi, constraint.b)
+                        }
+                        (None, phi) => {
+                            ConstantQuadraticConstraint::<R>::new_linear(phi, constraint.b)
+                        }
+                    },
+                );
+            }
+        }
+
+        let norm_bounds_sq = statements
+            .iter()
+            .map(|(index, _)| index.norm_bound_squared.floor() as u64)
+            .collect::<Vec<_>>();
+        for (j, ((index, _), offset)) in statements.iter().zip(offsets.iter()).enumerate() {
+            let mut A = SymmetricMatrix::<R>::zero(r);
+            for i in (*offset..offset + index.r).chain([num_statement_witnesses + j]) {
+                // A[(h + i, i)] sets A[(i, h + i)] as well, so <w_i, w~_i> is counted twice
+                A[(h + i, i)] = R::one();
+            }
+            let b = R::BaseRing::try_from(2 * norm_bounds_sq[j] as u128).unwrap();
+            ct_quad_dot_prod_funcs.push(ConstantQuadraticConstraint::<R>::new(
+                A,
+                vec![Vector::<R>::zeros(n); r],
+                b,
+            ));
+        }
+        let norm_bound_sq = 2 * norm_bounds_sq.iter().sum::<u64>();
+        assert!(
+            BigUint::from(norm_bound_sq) < R::modulus(),
+            "the stacked norm bound {norm_bound_sq} is not below q, so the constant constraints do not bound the norm of each witness"
+        );
+
+        let size = Size {
+            num_witnesses: r,
+            witness_len: n,
+            norm_bound_sq: norm_bound_sq as f64,
+            num_constraints: quad_dot_prod_funcs.len(),
+            num_constant_constraints: ct_quad_dot_prod_funcs.len(),
+        };
+        StackedStatement {
+            size,
+            index: Index::<R>::new(&size),
+            instance: Instance::<R> {
+                quad_dot_prod_funcs,
+                ct_quad_dot_prod_funcs,
+            },
+            offsets,
+            norm_bounds_sq,
+        }
+    }
+
+    /// Position of the slack vector for the norm bound of statement j in the stacked witness
+    pub fn slack_idx(&self, j: usize) -> usize {
+        self.index.r / 2 - self.offsets.len() + j
+    }
+
+    /// Position of the conjugate of the i-th witness vector in the stacked witness
+    pub fn conj_idx(&self, i: usize) -> usize {
+        self.index.r / 2 + i
+    }
+
+    /// Concatenate the witnesses of the stacked statements, given in the same order as the statements, followed by the
+    /// slack vectors for their norm bounds and the conjugates of all these vectors.
+    /// Panics if a witness exceeds the norm bound of its statement.
+    pub fn witness(&self, witnesses: &[&Witness<R>]) -> Witness<R> {
+        assert_eq!(
+            witnesses.len(),
+            self.offsets.len(),
+            "expected one witness per stacked statement"
+        );
+        let n = self.index.n;
+        let mut s = witnesses
+            .iter()
+            .flat_map(|witness| witness.s.iter().map(|s_i| pad(s_i, n)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            s.len(),
+            self.slack_idx(0),
+            "witnesses do not have as many vectors as their statements"
+        );
+        for (j, (witness, bound_sq)) in witnesses.iter().zip(self.norm_bounds_sq.iter()).enumerate()
+        {
+            let norm_sq = witness
+                .s
+                .iter()
+                .map(|s_i| R::flattened(s_i).l2_norm_squared().to_u64().unwrap())
+                .sum::<u64>();
+            assert!(
+                norm_sq <= *bound_sq,
+                "witness {j} has squared norm {norm_sq}, which exceeds the bound {bound_sq} of its statement"
+            );
+            let mut e_j = Vector::<R>::zeros(n);
+            e_j[0] = slack(bound_sq - norm_sq);
+            s.push(e_j);
+        }
+        let conj = s
+            .iter()
+            .map(|s_i| R::apply_automorphism_vec(s_i))
+            .collect::<Vec<_>>();
+        s.extend(conj);
+        Witness::<R>::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lattirust_arithmetic::ring::{Pow2CyclotomicPolyRingNTT, Zq2};
+    use relations::principal_relation::PrincipalRelation;
+    use relations::Relation;
+
+    use super::*;
+    use crate::backend::{LabradorBackend, RustBackend};
+    use crate::common_reference_string::CommonReferenceString;
+    use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+
+    type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+    /// $\langle a, a \rangle + \langle 1, b \rangle = c$ for witness vectors of length n, where a has a one in each of
+    /// the first k entries and b is zero
+    fn statement(n: usize, k: usize, c: u128) -> (Index<R>, Instance<R>, Witness<R>) {
+        let mut builder = RelationBuilder::<R>::new(n, (n * n) as f64);
+        let (a, b) = (builder.witness("a"), builder.witness("b"));
+        builder.constrain(
+            QuadraticExpr::inner(a, a) + QuadraticExpr::linear(builder.ones(), b),
+            R::try_from(c).unwrap(),
+        );
+        let mut assignment = builder.assignment();
+        assignment
+            .assign(
+                a,
+                Vector::<R>::from_fn(n, |j, _| if j < k { R::one() } else { R::zero() }),
+            )
+            .assign(b, Vector::<R>::zeros(n));
+        let witness = assignment.witness().unwrap();
+        let (index, instance) = builder.build();
+        (index, instance, witness)
+    }
+
+    #[test]
+    fn test_stacking() {
+        let statements = [statement(4, 3, 3), statement(8, 5, 5), statement(2, 2, 2)];
+        let stacked = StackedStatement::new(
+            &statements
+                .iter()
+                .map(|(index, instance, _)| (index, instance))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(stacked.offsets, vec![0, 2, 4]);
+        // 6 witness vectors and 3 slack vectors, and their conjugates
+        assert_eq!((stacked.index.r, stacked.index.n), (18, 8));
+        assert_eq!(stacked.instance.quad_dot_prod_funcs.len(), 3);
+        assert_eq!(stacked.instance.ct_quad_dot_prod_funcs.len(), 3);
+        assert_eq!(stacked.norm_bounds_sq, vec![16, 64, 4]);
+
+        let witness = stacked.witness(
+            &statements
+                .iter()
+                .map(|(_, _, witness)| witness)
+                .collect::<Vec<_>>(),
+        );
+        assert!(PrincipalRelation::<R>::is_satisfied_err(
+            &stacked.index,
+            &stacked.instance,
+            &witness
+        )
+        .is_ok());
+
+        let crs = CommonReferenceString::<R>::new_for_size(stacked.size);
+        let proof = RustBackend
+            .prove(&crs, &stacked.index, &stacked.instance, &witness)
+            .unwrap();
+        RustBackend
+            .verify(&crs, &stacked.index, &stacked.instance, &proof)
+            .unwrap();
+
+        // A witness for the wrong statement breaks only its own constraint
+        let (_, _, wrong_witness) = statement(8, 4, 4);
+        let witness = stacked.witness(&[&statements[0].2, &wrong_witness, &statements[2].2]);
+        assert!(PrincipalRelation::<R>::is_satisfied_err(
+            &stacked.index,
+            &stacked.instance,
+            &witness
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stacking_bounds_each_norm() {
+        let statements = [statement(4, 3, 3), statement(2, 2, 2)];
+        let stacked = StackedStatement::new(
+            &statements
+                .iter()
+                .map(|(index, instance, _)| (index, instance))
+                .collect::<Vec<_>>(),
+        );
+        let mut witness = stacked.witness(&[&statements[0].2, &statements[1].2]);
+
+        // The second witness takes the norm left over by the first one: b = (2, -2) still satisfies its constraint, but
+        // has squared norm 8, above the bound 4 of its statement
+        let two = R::try_from(2u128).unwrap();
+        let b = Vector::<R>::from_fn(stacked.index.n, |j, _| match j {
+            0 => two,
+            1 => -two,
+            _ => R::zero(),
+        });
+        let b_idx = stacked.offsets[1] + 1;
+        witness.s[stacked.conj_idx(b_idx)] = R::apply_automorphism_vec(&b);
+        witness.s[b_idx] = b;
+        for j in 0..2 {
+            witness.s[stacked.slack_idx(j)] = Vector::<R>::zeros(stacked.index.n);
+            witness.s[stacked.conj_idx(stacked.slack_idx(j))] = Vector::<R>::zeros(stacked.index.n);
+        }
+        let norm_sq = witness
+            .s
+            .iter()
+            .map(|s_i| R::flattened(s_i).l2_norm_squared().to_f64().unwrap())
+            .sum::<f64>();
+        assert!(norm_sq <= stacked.size.norm_bound_sq);
+
+        // Only the norm constraints catch it
+        let size = Size {
+            num_constant_constraints: 0,
+            ..stacked.size
+        };
+        let without_norm_constraints = Instance::<R> {
+            quad_dot_prod_funcs: stacked.instance.quad_dot_prod_funcs.clone(),
+            ct_quad_dot_prod_funcs: vec![],
+        };
+        assert!(PrincipalRelation::<R>::is_satisfied_err(
+            &Index::<R>::new(&size),
+            &without_norm_constraints,
+            &witness
+        )
+        .is_ok());
+        assert!(PrincipalRelation::<R>::is_satisfied_err(
+            &stacked.index,
+            &stacked.instance,
+            &witness
+        )
+        .is_err());
+    }
+}
@@ -6,20 +6,26 @@ use tracing_subscriber::fmt::format::FmtSpan;
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
 use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
-use lattirust_arithmetic::nimue::iopattern::{SerIOPattern, SqueezeFromRandomBytes};
+use lattirust_arithmetic::linear_algebra::Vector;
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
 use lattirust_arithmetic::ring::Zq2;
 use lattirust_arithmetic::ring::{PolyRing, Pow2CyclotomicPolyRingNTT};
 use lattirust_arithmetic::traits::FromRandomBytes;
 use relations::principal_relation::PrincipalRelation;
 use relations::principal_relation::Size;
+use relations::principal_relation::{Index, Instance, Witness};
 use relations::reduction::Reduction;
 use relations::{test_completeness_with_init, test_soundness_with_init};
 
 use crate::common_reference_string::CommonReferenceString;
-#[cfg(not(feature = "c-binding"))]
-use crate::prover::prove_principal_relation_oneround;
-use crate::verifier::verify_principal_relation_oneround;
+use crate::iopattern::{principal_relation_iopattern, LabradorIOPattern};
+use crate::prover::{prove_principal_relation, prove_principal_relation_oneround};
+use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+use crate::test_util::ternary_vector;
+use crate::util::to_ring;
+use crate::verifier::{
+    shared_constraint_failures, verify_batch, verify_principal_relation_oneround,
+};
 
 // Q = 2^64+1
 const Q1: u64 = 274177;
@@ -41,7 +47,6 @@ pub struct Labrador<R: PolyRing> {
     _marker: std::marker::PhantomData<R>,
 }
 
-#[cfg(not(feature = "c-binding"))]
 impl<R: PolyRing> Reduction<PrincipalRelation<R>, PrincipalRelation<R>, CommonReferenceString<R>>
     for Labrador<R>
 where
@@ -56,36 +61,7 @@ where
         _index_in: &Self::IndexIn,
         _instance_in: &Self::InstanceIn,
     ) -> IOPattern {
-        let log_q = R::modulus().bits() as f64;
-        let num_aggregs = (128. / log_q).ceil() as usize;
-        IOPattern::new("reduction_binaryr1cs_principalrelation")
-            .absorb_vector::<R>(crs.k1, "prover message 1")
-            .squeeze_matrices::<R, WeightedTernaryChallengeSet<R>>(
-                256,
-                crs.n,
-                crs.r,
-                "verifier message 1",
-            )
-            .absorb_vector_canonical::<R::BaseRing>(256, "prover message 2")
-            .squeeze_vectors::<R::BaseRing, R::BaseRing>(
-                crs.num_constant_constraints,
-                num_aggregs,
-                "verifier message 2 (psi)",
-            )
-            .squeeze_vectors::<R::BaseRing, R::BaseRing>(
-                256,
-                num_aggregs,
-                "verifier message 2 (omega)",
-            )
-            .absorb_vec::<R>(num_aggregs, "prover message 3")
-            .squeeze_vector::<R, R>(crs.num_constraints, "verifier message 3 (alpha)")
-            .squeeze_vector::<R, R>(num_aggregs, "verifier message 3 (beta)")
-            .absorb_vector::<R>(crs.k2, "prover message 4")
-            .squeeze_vec::<R, LabradorChallengeSet<R>>(crs.r, "verifier message 4")
-            .absorb_vector::<R>(crs.n, "prover message 5 (z)")
-            .absorb_vectors::<R>(crs.k, crs.r, "prover message 5 (t)")
-            .absorb_symmetric_matrix::<R>(crs.r, "prover message 5 (G)")
-            .absorb_symmetric_matrix::<R>(crs.r, "prover message 5 (H)")
+        IOPattern::new("reduction_binaryr1cs_principalrelation").labrador_io(crs)
     }
 
     fn prove(
@@ -129,3 +105,116 @@ test_soundness_with_init!(
     TEST_SIZE,
     init
 );
+
+/// $\langle s_1, s_2 \rangle + \langle 1, s_1 \rangle = b$ and $ct(\langle s_2, s_2 \rangle) = b'$ for small witness vectors
+/// depending on the seed, so that all statements have the same size
+fn batch_statement(seed: usize) -> (Size, Index<R>, Instance<R>, Witness<R>) {
+    let n = 8;
+    let (s_1, s_2) = (
+        ternary_vector::<R>(n, seed),
+        ternary_vector::<R>(n, seed + 1),
+    );
+
+    let mut builder = RelationBuilder::<R>::new(n, (2 * n * D) as f64);
+    let (a, b) = (builder.witness("s_1"), builder.witness("s_2"));
+    let rhs = s_1.dot(&s_2) + builder.ones().dot(&s_1);
+    builder.constrain(
+        QuadraticExpr::inner(a, b) + QuadraticExpr::linear(builder.ones(), a),
+        rhs,
+    );
+    let rhs_ct = s_2.dot(&s_2).coefficients()[0];
+    builder.constrain_ct(QuadraticExpr::inner(b, b), rhs_ct);
+
+    let mut assignment = builder.assignment();
+    assignment.assign(a, s_1).assign(b, s_2);
+    let witness = assignment.witness().unwrap();
+    let size = builder.size();
+    let (index, instance) = builder.build();
+    (size, index, instance, witness)
+}
+
+#[test]
+fn test_verify_batch() {
+    init();
+    let statements = (0..4).map(batch_statement).collect::<Vec<_>>();
+    let crs = CommonReferenceString::<R>::new_for_size(statements[0].0);
+    let io = principal_relation_iopattern(&crs);
+    let mut proofs = statements
+        .iter()
+        .map(|(_, index, instance, witness)| {
+            let mut merlin = io.to_merlin();
+            prove_principal_relation(&mut merlin, &crs, index, instance, witness)
+                .unwrap()
+                .to_vec()
+        })
+        .collect::<Vec<_>>();
+
+    let batch = statements
+        .iter()
+        .zip(proofs.iter())
+        .map(|((_, index, instance, _), proof)| (index, instance, proof.as_slice()))
+        .collect::<Vec<_>>();
+    assert_eq!(verify_batch(&crs, &batch), Ok(()));
+
+    // Tamper with the final witness of proof 1, and check proof 3 against the statement of proof 0
+    let last = proofs[1].len() - 1;
+    proofs[1][last] ^= 1;
+    let mut batch = statements
+        .iter()
+        .zip(proofs.iter())
+        .map(|((_, index, instance, _), proof)| (index, instance, proof.as_slice()))
+        .collect::<Vec<_>>();
+    batch[3].0 = &statements[0].1;
+    batch[3].1 = &statements[0].2;
+    assert_eq!(verify_batch(&crs, &batch).unwrap_err().failed, vec![1, 3]);
+}
+
+#[test]
+fn test_shared_constraint_failures() {
+    let (n, r) = (8, 2);
+    let small = |seed: usize| {
+        Vector::<R>::from_fn(n, |j, _| {
+            to_ring(
+                &(0..D)
+                    .map(|k| ((seed + j + k) % 5) as i64 - 2)
+                    .collect::<Vec<_>>(),
+            )
+        })
+    };
+    let phi = (0..3)
+        .map(|l| (0..r).map(|i| small(7 * l + i)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let phi = phi.iter().collect::<Vec<_>>();
+    let witnesses = (0..3)
+        .map(|p| Witness::<R>::new((0..r).map(|i| small(3 * p + i)).collect()))
+        .collect::<Vec<_>>();
+    let mut b = witnesses
+        .iter()
+        .map(|witness| {
+            phi.iter()
+                .map(|phi_l| {
+                    phi_l
+                        .iter()
+                        .zip(witness.s.iter())
+                        .map(|(phi_li, s_i)| phi_li.dot(s_i))
+                        .reduce(|a, b| a + b)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let failures = |b: &[Vec<R>]| {
+        let candidates = witnesses
+            .iter()
+            .enumerate()
+            .map(|(p, witness)| (10 + p, b[p].as_slice(), witness))
+            .collect::<Vec<_>>();
+        shared_constraint_failures(&phi, &candidates)
+    };
+    assert!(failures(&b).is_empty());
+
+    // Break a single constraint of one candidate, and only modulo Q1: the difference is a multiple of Q2, which is a
+    // zero divisor in R
+    b[2][1] += R::from_scalar(Z64::try_from(Q2 as u128).unwrap());
+    assert_eq!(failures(&b), vec![12]);
+}
@@ -0,0 +1,39 @@
+//! Statements shared by the unit tests of several modules
+
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::ring::PolyRing;
+use relations::principal_relation::{Index, Instance, Witness};
+
+use crate::common_reference_string::CommonReferenceString;
+use crate::relation_builder::{QuadraticExpr, RelationBuilder};
+use crate::util::to_ring;
+
+/// n elements of R with coefficients in {-1, 0, 1}, the k-th coefficient of the j-th element being
+/// $(seed + j + k) \bmod 3 - 1$
+pub(crate) fn ternary_vector<R: PolyRing>(n: usize, seed: usize) -> Vector<R> {
+    Vector::<R>::from_fn(n, |j, _| {
+        to_ring(
+            &(0..R::dimension())
+                .map(|k| ((seed + j + k) % 3) as i64 - 1)
+                .collect::<Vec<_>>(),
+        )
+    })
+}
+
+/// $\langle a, b \rangle = v$ for a ternary a of length 8, see `ternary_vector`, and b with all coefficients 1, with a
+/// CRS for its size
+pub(crate) fn inner_product_statement<R: PolyRing>(
+) -> (CommonReferenceString<R>, Index<R>, Instance<R>, Witness<R>) {
+    let n = 8;
+    let mut builder = RelationBuilder::<R>::new(n, (2 * n * R::dimension()) as f64);
+    let (a, b) = (builder.witness("a"), builder.witness("b"));
+    let value_a = ternary_vector::<R>(n, 0);
+    let value_b = Vector::<R>::from_element(n, to_ring(&vec![1; R::dimension()]));
+    builder.constrain(QuadraticExpr::inner(a, b), value_a.dot(&value_b));
+    let mut assignment = builder.assignment();
+    assignment.assign(a, value_a).assign(b, value_b);
+    let witness = assignment.witness().unwrap();
+    let crs = CommonReferenceString::<R>::new_for_size(builder.size());
+    let (index, instance) = builder.build();
+    (crs, index, instance, witness)
+}
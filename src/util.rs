@@ -3,7 +3,8 @@
 use lattirust_arithmetic::decomposition::balanced_decomposition::decompose_balanced_vec_polyring;
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
-use num_traits::{One, Zero};
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
 
 use lattirust_arithmetic::linear_algebra::{Matrix, Scalar, SymmetricMatrix, Vector};
 use lattirust_arithmetic::ring::Ring;
@@ -114,6 +115,48 @@ pub fn embed<R: Zero + One>(x: Z2) -> R {
     }
 }
 
+/// Bound up to which `smallest_prime_factor_bound` looks for prime factors
+const TRIAL_DIVISION_BOUND: u64 = 1 << 16;
+
+/// Lower bound on the smallest prime factor of q: the factor itself if it is less than `TRIAL_DIVISION_BOUND`, q if it
+/// has no such factor and is less than `TRIAL_DIVISION_BOUND^2`, since it is then prime, and `TRIAL_DIVISION_BOUND`
+/// otherwise.
+/// A uniformly random linear combination over Z_q misses a non-zero vector with probability at most 1/p, where p is the
+/// smallest prime factor of q, so the number of repetitions of such checks is derived from this bound.
+pub fn smallest_prime_factor_bound(q: &BigUint) -> u64 {
+    (2..TRIAL_DIVISION_BOUND)
+        .find(|p| (q % *p).is_zero())
+        .or_else(|| {
+            q.to_u64()
+                .filter(|q| *q < TRIAL_DIVISION_BOUND * TRIAL_DIVISION_BOUND)
+        })
+        .unwrap_or(TRIAL_DIVISION_BOUND)
+}
+
+/// Map a polynomial with (signed) integer coefficients to an element of R
+pub fn to_ring<R: PolyRing>(coeffs: &[i64]) -> R {
+    assert_eq!(
+        coeffs.len(),
+        R::dimension(),
+        "polynomial of degree {} does not fit in a ring element of dimension {}",
+        coeffs.len(),
+        R::dimension()
+    );
+    R::from(
+        coeffs
+            .iter()
+            .map(|c| {
+                let abs = R::BaseRing::try_from(c.unsigned_abs() as u128).unwrap();
+                if *c < 0 {
+                    -abs
+                } else {
+                    abs
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
 pub fn basis_vector<R: PolyRing>(i: usize, n: usize) -> Vector<R> {
     debug_assert!(i < n, "i = {} must be less than n = {}", i, n);
     let mut coeffs = vec![R::zero(); n];
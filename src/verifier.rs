@@ -1,12 +1,17 @@
 #![allow(non_snake_case)]
 
+use std::fmt::{Display, Formatter};
+
+use ark_std::rand::thread_rng;
 use log::debug;
 use nimue::{Arthur, ProofError, ProofResult};
-use num_traits::ToPrimitive;
+use num_traits::{ToPrimitive, Zero};
+use rayon::prelude::*;
 
 use lattirust_arithmetic::challenge_set::labrador_challenge_set::LabradorChallengeSet;
 use lattirust_arithmetic::challenge_set::weighted_ternary::WeightedTernaryChallengeSet;
 use lattirust_arithmetic::decomposition::DecompositionFriendlySignedRepresentative;
+use lattirust_arithmetic::linear_algebra::{Matrix, Vector};
 use lattirust_arithmetic::nimue::arthur::SerArthur;
 use lattirust_arithmetic::nimue::traits::ChallengeFromRandomBytes;
 use lattirust_arithmetic::ring::representatives::WithSignedRepresentative;
@@ -16,8 +21,14 @@ use lattirust_util::{check, check_eq};
 use relations::principal_relation::{Index, Instance, PrincipalRelation, Witness};
 use relations::Relation;
 
+use crate::commitment::Commitment;
 use crate::common_reference_string::CommonReferenceString;
-use crate::shared::{compute_a__, compute_phi, compute_phi__, fold_instance, TranscriptView};
+use crate::iopattern::principal_relation_iopattern;
+use crate::shared::{
+    compute_a__, compute_phi, compute_phi__, fold_instance, fold_instance_precomputed,
+    TranscriptView,
+};
+use crate::util::smallest_prime_factor_bound;
 
 pub fn verify_principal_relation_oneround<'a, R: PolyRing>(
     arthur: &mut Arthur,
@@ -44,6 +55,22 @@ pub fn verify_core<'a, R: PolyRing>(
     instance: &'a Instance<R>,
     arthur: &mut Arthur,
 ) -> ProofResult<TranscriptView<R>>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+{
+    verify_core_committed(crs, index, instance, None, arthur)
+}
+
+/// `verify_core` for a witness whose outer commitment `commitment` is known, in which case the first message is only
+/// the part of u_1 that commits to the inner products
+fn verify_core_committed<'a, R: PolyRing>(
+    crs: &'a CommonReferenceString<R>,
+    index: &'a Index<R>,
+    instance: &'a Instance<R>,
+    commitment: Option<&Vector<R>>,
+    arthur: &mut Arthur,
+) -> ProofResult<TranscriptView<R>>
 where
     LabradorChallengeSet<R>: FromRandomBytes<R>,
     WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
@@ -52,18 +79,16 @@ where
     let num_constraints = instance.quad_dot_prod_funcs.len();
     let num_ct_constraints = instance.ct_quad_dot_prod_funcs.len();
 
-    let u_1 = arthur
-        .next_vector(crs.k1)
-        .expect("error extracting prover message 1 from transcript");
+    let mut u_1: Vector<R> = arthur.next_vector(crs.k1)?;
+    if let Some(commitment) = commitment {
+        u_1 += commitment;
+    }
 
     let num_projections = 256;
-    let Pi = arthur
-        .challenge_matrices::<R, WeightedTernaryChallengeSet<R>>(num_projections, n, r)
-        .expect("error extracting verifier message 1 from transcript");
+    let Pi =
+        arthur.challenge_matrices::<R, WeightedTernaryChallengeSet<R>>(num_projections, n, r)?;
 
-    let p = arthur
-        .next_vector_canonical::<R::BaseRing>(num_projections)
-        .expect("error extracting prover message 2 from transcript");
+    let p = arthur.next_vector_canonical::<R::BaseRing>(num_projections)?;
     let norm_p_sq = p.l2_norm_squared();
     let p_norm_bound_sq = 128f64 * index.norm_bound_squared;
     check!(
@@ -74,16 +99,15 @@ where
         )
     );
 
-    let psi = arthur
-        .challenge_vectors::<R::BaseRing, R::BaseRing>(num_ct_constraints, crs.num_aggregs)
-        .expect("error extracting verifier message 2 (psi) from transcript");
-    let omega = arthur
-        .challenge_vectors::<R::BaseRing, R::BaseRing>(num_projections, crs.num_aggregs)
-        .expect("error extracting verifier message 2 (omega) from transcript");
+    let psi = if num_ct_constraints > 0 {
+        arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(num_ct_constraints, crs.num_aggregs)?
+    } else {
+        vec![Vector::<R::BaseRing>::zeros(0); crs.num_aggregs]
+    };
+    let omega =
+        arthur.challenge_vectors::<R::BaseRing, R::BaseRing>(num_projections, crs.num_aggregs)?;
 
-    let b__ = arthur
-        .next_vec::<R>(crs.num_aggregs)
-        .expect("error extracting prover message 3 from transcript");
+    let b__ = arthur.next_vec::<R>(crs.num_aggregs)?;
 
     for k in 0..crs.num_aggregs {
         let mut rhs_k = omega[k].dot(&p);
@@ -93,20 +117,12 @@ where
         check_eq!(b__[k].coefficients()[0], rhs_k);
     }
 
-    let alpha = arthur
-        .challenge_vector::<R, R>(num_constraints)
-        .expect("error extracting verifier message 3 (alpha) from transcript");
-    let beta = arthur
-        .challenge_vector::<R, R>(crs.num_aggregs)
-        .expect("error extracting verifier message 3 (beta) from transcript");
+    let alpha = arthur.challenge_vector::<R, R>(num_constraints)?;
+    let beta = arthur.challenge_vector::<R, R>(crs.num_aggregs)?;
 
-    let u_2 = arthur
-        .next_vector(crs.k2)
-        .expect("error extracting prover message 4 from transcript");
+    let u_2 = arthur.next_vector(crs.k2)?;
 
-    let c = arthur
-        .challenge_vec::<R, LabradorChallengeSet<R>>(crs.r)
-        .expect("error extracting verifier message 4 from transcript");
+    let c = arthur.challenge_vec::<R, LabradorChallengeSet<R>>(crs.r)?;
 
     // Compute phi
     let phi__ = compute_phi__(crs, index, instance, &Pi, &psi, &omega);
@@ -159,3 +175,252 @@ where
         }
     }
 }
+
+/// Verify a proof made by `prove_principal_relation_committed` for the IO pattern `commit_and_prove_iopattern(crs)`,
+/// that the witness committed to in the published outer commitment u_1 satisfies the statement
+pub fn verify_principal_relation_committed<R: PolyRing>(
+    arthur: &mut Arthur,
+    crs: &CommonReferenceString<R>,
+    u_1: &Vector<R>,
+    index: &Index<R>,
+    instance: &Instance<R>,
+) -> Result<(), ProofError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    let commitment: Vector<R> = arthur.next_vector(crs.k1)?;
+    check_eq!(commitment, *u_1);
+
+    let Some(next_crs) = crs.next_crs.as_ref() else {
+        // The witness is sent in the clear
+        let s = arthur.next_vectors(index.n, index.r)?;
+        let witness = Witness::<R>::new(s);
+        check!(
+            Commitment::verify_opening(crs, u_1, &witness),
+            "the witness does not open the commitment"
+        );
+        return PrincipalRelation::<R>::is_satisfied_err(index, instance, &witness)
+            .map_err(|_| ProofError::InvalidProof);
+    };
+    let transcript = verify_core_committed(crs, index, instance, Some(u_1), arthur)?;
+    let (index_next, instance_next) = fold_instance(crs, instance, &transcript);
+    verify_principal_relation(arthur, next_crs, &index_next, &instance_next)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchVerificationError {
+    /// Positions of the proofs that do not verify, in increasing order
+    pub failed: Vec<usize>,
+}
+
+impl Display for BatchVerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "proofs {:?} do not verify", self.failed)
+    }
+}
+
+impl std::error::Error for BatchVerificationError {}
+
+/// Verify many proofs under the same CRS, each produced by `prove_principal_relation` for the IO pattern
+/// `principal_relation_iopattern(crs)`, and return the positions of all proofs that do not verify.
+///
+/// The transcripts are checked in parallel, and the parts of the folded instances that only depend on the CRS are
+/// computed once for all proofs.
+/// In the last instance, the constraints for the commitments u_1 and u_2 have the same coefficients $\phi_l$ for all
+/// proofs, so they are checked at once for random combinations, see `shared_constraint_failures`.
+pub fn verify_batch<R: PolyRing>(
+    crs: &CommonReferenceString<R>,
+    proofs: &[(&Index<R>, &Instance<R>, &[u8])],
+) -> Result<(), BatchVerificationError>
+where
+    LabradorChallengeSet<R>: FromRandomBytes<R>,
+    WeightedTernaryChallengeSet<R>: FromRandomBytes<R>,
+    <R as PolyRing>::BaseRing: WithSignedRepresentative,
+    <<R as PolyRing>::BaseRing as WithSignedRepresentative>::SignedRepresentative:
+        DecompositionFriendlySignedRepresentative,
+{
+    let io = principal_relation_iopattern(crs);
+
+    // The CRS of each round of the recursion, and what it determines of the folded instance
+    let mut crs_rounds = vec![];
+    let mut crs_curr = crs;
+    while let Some(next_crs) = crs_curr.next_crs.as_ref() {
+        crs_rounds.push(crs_curr);
+        crs_curr = next_crs;
+    }
+    let precomputations = crs_rounds
+        .par_iter()
+        .map(|crs_i| crs_i.folding_precomputation())
+        .collect::<Vec<_>>();
+
+    // The constraints for u_1 and u_2 come last in a folded instance
+    let shared_phi = precomputations
+        .last()
+        .map(|pre| {
+            pre.u_1_phi
+                .iter()
+                .chain(pre.u_2_phi.iter())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let num_shared = shared_phi.len();
+
+    // For each proof that passes all other checks, the right-hand sides of its shared constraints and its final witness
+    let results: Vec<ProofResult<(Vec<R>, Witness<R>)>> = proofs
+        .par_iter()
+        .map(|(index, instance, proof)| {
+            let mut arthur = io.to_arthur(proof);
+            let mut index_curr = (*index).clone();
+            let mut instance_curr = (*instance).clone();
+            for (crs_i, pre) in crs_rounds.iter().zip(precomputations.iter()) {
+                let transcript = verify_core(crs_i, &index_curr, &instance_curr, &mut arthur)?;
+                (index_curr, instance_curr) =
+                    fold_instance_precomputed(crs_i, pre, &instance_curr, &transcript);
+            }
+            let s = arthur.next_vectors(index_curr.n, index_curr.r)?;
+            let witness = Witness::<R>::new(s);
+
+            // A statement with fewer constraints than the shared ones cannot have been folded from this CRS
+            let num_unshared = instance_curr
+                .quad_dot_prod_funcs
+                .len()
+                .checked_sub(num_shared)
+                .ok_or(ProofError::InvalidProof)?;
+            let shared = instance_curr.quad_dot_prod_funcs.split_off(num_unshared);
+            index_curr.num_constraints = index_curr
+                .num_constraints
+                .checked_sub(num_shared)
+                .ok_or(ProofError::InvalidProof)?;
+            PrincipalRelation::<R>::is_satisfied_err(&index_curr, &instance_curr, &witness)
+                .map_err(|_| ProofError::InvalidProof)?;
+            Ok((
+                shared
+                    .into_iter()
+                    .map(|constraint| constraint.b)
+                    .collect::<Vec<_>>(),
+                witness,
+            ))
+        })
+        .collect();
+
+    let mut failed = vec![];
+    let mut passed = vec![];
+    for (p, result) in results.iter().enumerate() {
+        match result {
+            Ok(result) => passed.push((p, result)),
+            Err(_) => failed.push(p),
+        }
+    }
+
+    let candidates = passed
+        .iter()
+        .map(|(p, (b, witness))| (*p, b.as_slice(), witness))
+        .collect::<Vec<_>>();
+    failed.extend(shared_constraint_failures(&shared_phi, &candidates));
+    failed.sort_unstable();
+
+    if failed.is_empty() {
+        debug!("└ Verifier::verify_batch: OK");
+        Ok(())
+    } else {
+        debug!("└ Verifier::verify_batch: proofs {:?} failed", failed);
+        Err(BatchVerificationError { failed })
+    }
+}
+
+/// Statistical security of the random combinations in `shared_constraint_failures`
+const BATCH_SECURITY_PARAMETER: f64 = 128.;
+
+/// Positions of the candidates `(position, b, s)` for which $\sum_i \langle \phi_{l,i}, s_i \rangle = b_l$ does not hold
+/// for some l, where the coefficients `phi` are the same for all candidates.
+///
+/// All constraints are first checked at once for a random combination
+/// $\sum_p \gamma_p \sum_l \delta_l (\langle \phi_l, s_p \rangle - b_{p,l}) = 0$ with $\gamma_p, \delta_l$ uniform
+/// in R, which costs one inner product instead of one per candidate and constraint.
+/// R is not a field, so a combination only catches a violated constraint in each CRT slot of R separately: in a slot
+/// where some constraint does not hold, the sum over l vanishes with probability at most 1/p, and if it does not, the
+/// sum over p vanishes with probability at most 1/p, where p is the smallest prime factor of q.
+/// A combination thus misses violated constraints with probability at most 2/p, so it is repeated until the
+/// probability that all repetitions miss them is at most $2^{-128}$. For p = 2 this bound is trivial, so the
+/// combinations are skipped.
+/// Only if a combination does not vanish are the candidates checked one by one, to find the culprits.
+pub(crate) fn shared_constraint_failures<R: PolyRing>(
+    phi: &[&Vec<Vector<R>>],
+    candidates: &[(usize, &[R], &Witness<R>)],
+) -> Vec<usize> {
+    if phi.is_empty() || candidates.is_empty() {
+        return vec![];
+    }
+    let p_min = smallest_prime_factor_bound(&R::modulus()) as f64;
+    let num_repetitions = if p_min > 2. {
+        (BATCH_SECURITY_PARAMETER / (p_min / 2.).log2()).ceil() as usize
+    } else {
+        0
+    };
+
+    let r = phi[0].len();
+    let rng = &mut thread_rng();
+    let combination_vanishes = |gamma: Matrix<R>, delta: Matrix<R>| {
+        let psi = (0..r)
+            .map(|i| {
+                phi.iter()
+                    .enumerate()
+                    .map(|(l, phi_l)| &phi_l[i] * delta[(0, l)])
+                    .reduce(|a, b| a + b)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let sigma = (0..r)
+            .into_par_iter()
+            .map(|i| {
+                candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (_, _, witness))| &witness.s[i] * gamma[(0, j)])
+                    .reduce(|a, b| a + b)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let lhs = psi
+            .iter()
+            .zip(sigma.iter())
+            .map(|(psi_i, sigma_i)| psi_i.dot(sigma_i))
+            .fold(R::zero(), |acc, x| acc + x);
+        let mut rhs = R::zero();
+        for (j, (_, b, _)) in candidates.iter().enumerate() {
+            for (l, b_l) in b.iter().enumerate() {
+                rhs += gamma[(0, j)] * delta[(0, l)] * *b_l;
+            }
+        }
+        lhs == rhs
+    };
+    if num_repetitions > 0
+        && (0..num_repetitions).all(|_| {
+            combination_vanishes(
+                Matrix::<R>::rand(1, candidates.len(), rng),
+                Matrix::<R>::rand(1, phi.len(), rng),
+            )
+        })
+    {
+        return vec![];
+    }
+
+    candidates
+        .par_iter()
+        .filter_map(|(p, b, witness)| {
+            let satisfied = phi.iter().zip(b.iter()).all(|(phi_l, b_l)| {
+                let lhs_l = phi_l
+                    .iter()
+                    .zip(witness.s.iter())
+                    .map(|(phi_li, s_i)| phi_li.dot(s_i))
+                    .fold(R::zero(), |acc, x| acc + x);
+                lhs_l == *b_l
+            });
+            (!satisfied).then_some(*p)
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+//! Peak allocation of proofs, measured by `TrackingAllocator`, which can only be installed as the global allocator of
+//! a binary of its own.
+
+use std::hint::black_box;
+use std::sync::Barrier;
+use std::thread;
+
+use lattirust_arithmetic::linear_algebra::Vector;
+use lattirust_arithmetic::ring::{PolyRing, Pow2CyclotomicPolyRingNTT, Zq2};
+use num_traits::One;
+use relations::principal_relation::{Index, Instance, Witness};
+
+use labrador::common_reference_string::CommonReferenceString;
+use labrador::iopattern::principal_relation_iopattern;
+use labrador::progress::ProgressEvent;
+use labrador::prover::prove_principal_relation_with_progress;
+use labrador::prover::stats::{ProverStats, TrackingAllocator};
+use labrador::relation_builder::{QuadraticExpr, RelationBuilder};
+
+#[global_allocator]
+static A: TrackingAllocator = TrackingAllocator;
+
+type R = Pow2CyclotomicPolyRingNTT<Zq2<274177, 67280421310721>, 64>;
+
+/// Bytes that the first proof allocates while it runs, more than the proofs themselves allocate
+const LARGE_ALLOCATION: usize = 64 << 20;
+
+/// $\langle a, b \rangle = n$ for a = b = (1, ..., 1)
+fn statement() -> (CommonReferenceString<R>, Index<R>, Instance<R>, Witness<R>) {
+    let n = 8;
+    let mut builder = RelationBuilder::<R>::new(n, (2 * n * R::dimension()) as f64);
+    let (a, b) = (builder.witness("a"), builder.witness("b"));
+    let ones = Vector::<R>::from_element(n, R::one());
+    builder.constrain(QuadraticExpr::inner(a, b), ones.dot(&ones));
+    let mut assignment = builder.assignment();
+    assignment.assign(a, ones.clone()).assign(b, ones);
+    let witness = assignment.witness().unwrap();
+    let crs = CommonReferenceString::<R>::new_for_size(builder.size());
+    let (index, instance) = builder.build();
+    (crs, index, instance, witness)
+}
+
+/// Prove the statement, calling `on_first_round` when the first round starts
+fn prove(on_first_round: impl Fn() + Sync) -> ProverStats {
+    let (crs, index, instance, witness) = statement();
+    let io = principal_relation_iopattern(&crs);
+    let mut merlin = io.to_merlin();
+    let sink = |event: ProgressEvent| {
+        if let ProgressEvent::RoundStarted { round: 1, .. } = event {
+            on_first_round();
+        }
+    };
+    let (_, stats) = prove_principal_relation_with_progress(
+        &mut merlin,
+        &crs,
+        &index,
+        &instance,
+        &witness,
+        &sink,
+    )
+    .unwrap();
+    stats
+}
+
+#[test]
+fn test_peak_allocation() {
+    let stats = prove(|| {});
+    assert!(stats.peak_allocation.unwrap() > 0);
+}
+
+#[test]
+fn test_peak_allocation_concurrent() {
+    assert!(
+        statement().0.next_crs.is_some(),
+        "the statement must be proven in at least one round"
+    );
+    let (first_allocated, second_started) = (Barrier::new(2), Barrier::new(2));
+    let (first, second) = thread::scope(|scope| {
+        let first = scope.spawn(|| {
+            prove(|| {
+                drop(black_box(vec![1u8; LARGE_ALLOCATION]));
+                first_allocated.wait();
+                second_started.wait();
+            })
+        });
+        let second = scope.spawn(|| {
+            first_allocated.wait();
+            prove(|| {
+                second_started.wait();
+            })
+        });
+        (first.join().unwrap(), second.join().unwrap())
+    });
+
+    // The second proof starts measuring after the first one freed its large allocation. Had it reset the slot of the
+    // first proof, the peak of the first proof would not include it either.
+    let (first, second) = (
+        first.peak_allocation.unwrap(),
+        second.peak_allocation.unwrap(),
+    );
+    assert!(first >= LARGE_ALLOCATION);
+    assert!(0 < second && second < LARGE_ALLOCATION);
+}